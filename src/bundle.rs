@@ -0,0 +1,282 @@
+use std::{
+    collections::BTreeMap,
+    fmt,
+    path::{Path, PathBuf},
+};
+
+use clap::ValueEnum;
+use eyre::Result;
+use serde::{
+    Deserialize, Deserializer, Serialize,
+    de::{self, MapAccess, Visitor},
+};
+
+/// Version of the bundle format written by this CLI.
+pub const BUNDLE_VERSION: u32 = 1;
+
+/// Single-file package of everything needed to verify a quote.
+///
+/// Bundles are JSON objects. Items are kept as hex-encoded raw file contents, so that signed
+/// collateral stays byte-exact.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Bundle {
+    pub version: u32,
+    /// Free-form key-value pairs describing the bundle, such as where the collateral came from.
+    #[serde(default)]
+    pub metadata: BTreeMap<String, String>,
+    /// Hex-encoded contents of each item. Items may appear only once.
+    #[serde(deserialize_with = "unique_items")]
+    pub items: BTreeMap<BundleItem, String>,
+}
+
+/// Kind of file packaged in a bundle.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum BundleItem {
+    /// Raw quote bytes.
+    Quote,
+    /// TCB Info JSON, as served by PCS.
+    Tcbinfo,
+    /// QE identity JSON, as served by PCS.
+    Qeidentity,
+    /// PEM issuer chain of the TCB Info.
+    TcbinfoIssuerChain,
+    /// PEM issuer chain of the QE identity.
+    QeidentityIssuerChain,
+    /// PCK certificate revocation list.
+    PckCrl,
+    /// PEM issuer chain of the PCK CRL.
+    PckCrlIssuerChain,
+    /// Root CA certificate of the issuer chains, checked against the trusted one.
+    RootCa,
+    /// Root CA certificate revocation list.
+    RootCaCrl,
+}
+
+impl BundleItem {
+    /// File name used when extracting the item, whose stem is also the default Cairo item name.
+    pub const fn file_name(&self) -> &'static str {
+        match self {
+            Self::Quote => "quote.dat",
+            Self::Tcbinfo => "tcbinfo.json",
+            Self::Qeidentity => "qeidentity.json",
+            Self::TcbinfoIssuerChain => "tcbinfo_issuer_chain.pem",
+            Self::QeidentityIssuerChain => "qeidentity_issuer_chain.pem",
+            Self::PckCrl => "pck_crl.crl",
+            Self::PckCrlIssuerChain => "pck_crl_issuer_chain.pem",
+            Self::RootCa => "root_ca.cer",
+            Self::RootCaCrl => "root_ca_crl.crl",
+        }
+    }
+
+    /// Name of the Cairo item defined from this item by default, e.g. `TCBINFO_ISSUER_CHAIN`.
+    pub fn const_name(&self) -> String {
+        let file_name = self.file_name();
+        let stem = file_name.split('.').next().unwrap_or(file_name);
+        stem.to_uppercase()
+    }
+
+    pub fn name(&self) -> String {
+        self.to_possible_value()
+            .map(|value| value.get_name().to_owned())
+            .unwrap_or_default()
+    }
+}
+
+impl Bundle {
+    pub fn read(path: &Path) -> Result<Self> {
+        let bundle: Self = serde_json::from_str(&std::fs::read_to_string(path)?)
+            .map_err(|err| eyre::eyre!("Invalid bundle {}: {err}", path.display()))?;
+        if bundle.version != BUNDLE_VERSION {
+            eyre::bail!(
+                "Unsupported bundle version: {}, expected {BUNDLE_VERSION}",
+                bundle.version
+            );
+        }
+
+        Ok(bundle)
+    }
+
+    pub fn write(&self, path: &Path) -> Result<()> {
+        let mut output = serde_json::to_string_pretty(self)?;
+        output.push('\n');
+        std::fs::write(path, output)?;
+
+        Ok(())
+    }
+
+    pub fn insert(&mut self, item: BundleItem, contents: &[u8]) {
+        self.items.insert(item, hex::encode(contents));
+    }
+
+    /// Raw contents of `item`, which must be present.
+    pub fn get(&self, item: BundleItem) -> Result<Vec<u8>> {
+        let Some(contents) = self.find(item)? else {
+            eyre::bail!("Bundle has no {} item", item.name());
+        };
+
+        Ok(contents)
+    }
+
+    /// Raw contents of `item`, if present.
+    pub fn find(&self, item: BundleItem) -> Result<Option<Vec<u8>>> {
+        Ok(self.items.get(&item).map(hex::decode).transpose()?)
+    }
+}
+
+/// Deserializes bundle items, rejecting duplicates instead of keeping the last one.
+fn unique_items<'de, D>(deserializer: D) -> Result<BTreeMap<BundleItem, String>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    struct ItemsVisitor;
+
+    impl<'de> Visitor<'de> for ItemsVisitor {
+        type Value = BTreeMap<BundleItem, String>;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            formatter.write_str("a map of bundle items")
+        }
+
+        fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+        where
+            A: MapAccess<'de>,
+        {
+            let mut items = BTreeMap::new();
+            while let Some((item, contents)) = map.next_entry::<BundleItem, String>()? {
+                if items.insert(item, contents).is_some() {
+                    return Err(de::Error::custom(format!(
+                        "duplicate item: {}",
+                        item.name()
+                    )));
+                }
+            }
+
+            Ok(items)
+        }
+    }
+
+    deserializer.deserialize_map(ItemsVisitor)
+}
+
+/// Reads the input of a command, either from `input` or as `item` of `bundle`.
+pub fn read_input(
+    input: Option<&Path>,
+    bundle: Option<&Path>,
+    item: BundleItem,
+) -> Result<Vec<u8>> {
+    match (input, bundle) {
+        (Some(input), None) => Ok(std::fs::read(input)?),
+        (None, Some(bundle)) => Bundle::read(bundle)?.get(item),
+        _ => eyre::bail!("Expected exactly one of --input and --bundle"),
+    }
+}
+
+/// Reads the inputs of a multi-input command, either from `inputs` or as `items` of `bundle`.
+/// Bundle items are paired with their file name, to derive item names from.
+pub fn read_inputs(
+    inputs: &[PathBuf],
+    bundle: Option<&Path>,
+    items: &[BundleItem],
+) -> Result<Vec<(PathBuf, Vec<u8>)>> {
+    let Some(bundle) = bundle else {
+        return inputs
+            .iter()
+            .map(|input| Ok((input.clone(), std::fs::read(input)?)))
+            .collect();
+    };
+
+    let bundle = Bundle::read(bundle)?;
+    items
+        .iter()
+        .map(|item| Ok((PathBuf::from(item.file_name()), bundle.get(*item)?)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Reads `json` as a bundle file.
+    fn read(json: &str) -> Result<Bundle> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("bundle.json");
+        std::fs::write(&path, json)?;
+
+        Bundle::read(&path)
+    }
+
+    #[test]
+    fn reads_written_bundle() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("bundle.json");
+        let mut bundle = Bundle {
+            version: BUNDLE_VERSION,
+            ..Default::default()
+        };
+        bundle.insert(BundleItem::RootCaCrl, &[0x00, 0xff]);
+        bundle.write(&path).unwrap();
+
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "{\n  \"version\": 1,\n  \"metadata\": {},\n  \"items\": {\n    \"root-ca-crl\": \"00ff\"\n  }\n}\n"
+        );
+        let read = Bundle::read(&path).unwrap();
+        assert_eq!(read.get(BundleItem::RootCaCrl).unwrap(), [0x00, 0xff]);
+        assert_eq!(
+            read.get(BundleItem::RootCa).unwrap_err().to_string(),
+            "Bundle has no root-ca item"
+        );
+    }
+
+    #[test]
+    fn rejects_duplicate_items() {
+        let error = read(r#"{"version":1,"items":{"tcbinfo":"00","tcbinfo":"01"}}"#).unwrap_err();
+        assert!(
+            error.to_string().contains("duplicate item: tcbinfo"),
+            "{error}"
+        );
+    }
+
+    #[test]
+    fn rejects_unsupported_version_and_unknown_fields() {
+        let error = read(r#"{"version":2,"items":{}}"#).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Unsupported bundle version: 2, expected 1"
+        );
+
+        for json in [
+            r#"{"version":1,"items":{"quote":"00"},"extra":1}"#,
+            r#"{"version":1,"items":{"pck-cert":"00"}}"#,
+        ] {
+            assert!(
+                read(json)
+                    .unwrap_err()
+                    .to_string()
+                    .starts_with("Invalid bundle")
+            );
+        }
+    }
+
+    #[test]
+    fn reads_input_from_exactly_one_source() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("input");
+        std::fs::write(&path, "input").unwrap();
+
+        assert_eq!(
+            read_input(Some(&path), None, BundleItem::Quote).unwrap(),
+            b"input"
+        );
+        for (input, bundle) in [(None, None), (Some(path.as_path()), Some(path.as_path()))] {
+            assert_eq!(
+                read_input(input, bundle, BundleItem::Quote)
+                    .unwrap_err()
+                    .to_string(),
+                "Expected exactly one of --input and --bundle"
+            );
+        }
+    }
+}
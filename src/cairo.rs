@@ -1,22 +1,571 @@
-use std::io::{Result, Write};
+use std::{
+    io::{Result, Write},
+    path::PathBuf,
+};
 
-pub fn write_cairo_bytes<W>(mut writer: W, bytes: &[u8]) -> Result<()>
+use clap::{Args, ValueEnum};
+
+/// Cairo representation of a byte payload.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum Encoding {
+    /// Fixed-size `u8` array.
+    #[default]
+    U8,
+    /// Big-endian `u32` words, as consumed by Cairo's SHA-256.
+    U32,
+    /// Big-endian `u64` limbs.
+    U64,
+    /// Big-endian `u128` limbs.
+    U128,
+    /// Big-endian `u256` limbs.
+    U256,
+    /// 31-byte big-endian `felt252` words.
+    Felt252,
+    /// `ByteArray` built from 31-byte words.
+    ByteArray,
+}
+
+impl Encoding {
+    /// Number of bytes packed into each element, or `None` for plain bytes.
+    const fn word_size(&self) -> Option<usize> {
+        match self {
+            Self::U8 => None,
+            Self::U32 => Some(4),
+            Self::U64 => Some(8),
+            Self::U128 => Some(16),
+            Self::U256 => Some(32),
+            Self::Felt252 | Self::ByteArray => Some(31),
+        }
+    }
+
+    const fn element_type(&self) -> &'static str {
+        match self {
+            Self::U8 => "u8",
+            Self::U32 => "u32",
+            Self::U64 => "u64",
+            Self::U128 => "u128",
+            Self::U256 => "u256",
+            Self::Felt252 => "felt252",
+            Self::ByteArray => "ByteArray",
+        }
+    }
+
+    const fn words_per_line(&self) -> usize {
+        match self {
+            Self::U8 => 20,
+            Self::U32 => 8,
+            Self::U64 => 4,
+            Self::U128 => 2,
+            Self::U256 | Self::Felt252 | Self::ByteArray => 1,
+        }
+    }
+}
+
+/// Visibility of generated Cairo items.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum Visibility {
+    /// `pub`
+    #[default]
+    Pub,
+    /// `pub(crate)`
+    PubCrate,
+    /// Module-private.
+    Private,
+}
+
+impl Visibility {
+    pub const fn prefix(&self) -> &'static str {
+        match self {
+            Self::Pub => "pub ",
+            Self::PubCrate => "pub(crate) ",
+            Self::Private => "",
+        }
+    }
+}
+
+/// Kind of Cairo item holding a byte payload.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum Container {
+    /// Fixed-size array constant.
+    #[default]
+    Const,
+    /// Function returning a `Span`.
+    Span,
+    /// Function returning an `Array`.
+    Array,
+}
+
+/// Options controlling how byte payloads are written as Cairo items.
+#[derive(Debug, Clone, Args)]
+pub struct CairoBytesOptions {
+    /// Cairo representation of the bytes.
+    #[clap(long, value_enum, default_value_t = Encoding::U8)]
+    pub encoding: Encoding,
+    /// Visibility of the generated items.
+    #[clap(long, value_enum, default_value_t = Visibility::Pub)]
+    pub visibility: Visibility,
+    /// Kind of item holding the bytes. Ignored for the `byte-array` encoding, which is always
+    /// emitted as a function.
+    #[clap(long, value_enum, default_value_t = Container::Const)]
+    pub container: Container,
+}
+
+/// Writes `bytes` as a Cairo item named after `name`.
+///
+/// Constants use `name` as is, while functions use its lowercase form. Word encodings are
+/// accompanied by a `{name}_LEN` constant holding the original byte length. Words are big-endian,
+/// and a trailing partial word holds the remaining bytes in its low-order bytes, matching the
+/// `last_input_word` convention of Cairo's SHA-256.
+pub fn write_cairo_encoded<W>(
+    mut writer: W,
+    name: &str,
+    bytes: &[u8],
+    options: &CairoBytesOptions,
+) -> Result<()>
 where
     W: Write,
 {
-    writeln!(writer, "pub const DATA: [u8; {}] = [", bytes.len())?;
+    let vis = options.visibility.prefix();
+    let encoding = options.encoding;
+
+    let words = match encoding.word_size() {
+        None => bytes.iter().map(|byte| format!("{byte:#02x}")).collect(),
+        Some(word_size) => {
+            writeln!(writer, "{vis}const {name}_LEN: usize = {};", bytes.len())?;
+            writeln!(writer)?;
+
+            bytes
+                .chunks(word_size)
+                .map(|word| format!("0x{}", hex::encode(word)))
+                .collect::<Vec<_>>()
+        }
+    };
 
-    for chunk in bytes.chunks(20) {
-        write!(writer, "   ")?;
+    if encoding == Encoding::ByteArray {
+        writeln!(writer, "{vis}fn {}() -> ByteArray {{", name.to_lowercase())?;
+        writeln!(writer, "    let mut data: ByteArray = Default::default();")?;
 
-        for byte in chunk {
-            write!(writer, " {byte:#02x},")?;
+        for (word, chunk) in words.iter().zip(bytes.chunks(31)) {
+            writeln!(writer, "    data.append_word({word}, {});", chunk.len())?;
         }
 
-        writeln!(writer,)?;
+        writeln!(writer, "    data")?;
+        writeln!(writer, "}}")?;
+
+        return Ok(());
+    }
+
+    let ty = encoding.element_type();
+    match options.container {
+        Container::Const => {
+            writeln!(writer, "{vis}const {name}: [{ty}; {}] = [", words.len())?;
+            write_word_lines(&mut writer, &words, encoding.words_per_line(), "    ")?;
+            writeln!(writer, "];")?;
+        }
+        Container::Span | Container::Array => {
+            let (ret, suffix) = match options.container {
+                Container::Span => ("Span", ".span()"),
+                _ => ("Array", ""),
+            };
+
+            writeln!(
+                writer,
+                "{vis}fn {}() -> {ret}<{ty}> {{",
+                name.to_lowercase()
+            )?;
+            writeln!(writer, "    array![")?;
+            write_word_lines(&mut writer, &words, encoding.words_per_line(), "        ")?;
+            writeln!(writer, "    ]{suffix}")?;
+            writeln!(writer, "}}")?;
+        }
     }
 
-    writeln!(writer, "];")?;
+    Ok(())
+}
+
+/// Writes a function named `fn_name` returning the named items, in the given order.
+pub fn write_cairo_chain_fn<W>(
+    mut writer: W,
+    fn_name: &str,
+    names: &[String],
+    options: &CairoBytesOptions,
+) -> Result<()>
+where
+    W: Write,
+{
+    let vis = options.visibility.prefix();
+
+    if options.encoding == Encoding::ByteArray {
+        writeln!(writer, "{vis}fn {fn_name}() -> Array<ByteArray> {{")?;
+    } else {
+        writeln!(
+            writer,
+            "{vis}fn {fn_name}() -> Array<Span<{}>> {{",
+            options.encoding.element_type()
+        )?;
+    }
+    writeln!(writer, "    array![")?;
+
+    for name in names {
+        match (options.encoding, options.container) {
+            (Encoding::ByteArray, _) | (_, Container::Span) => {
+                writeln!(writer, "        {}(),", name.to_lowercase())?
+            }
+            (_, Container::Array) => writeln!(writer, "        {}().span(),", name.to_lowercase())?,
+            (_, Container::Const) => writeln!(writer, "        {name}.span(),")?,
+        }
+    }
+
+    writeln!(writer, "    ]")?;
+    writeln!(writer, "}}")?;
+
+    Ok(())
+}
+
+fn write_word_lines<W>(mut writer: W, words: &[String], per_line: usize, indent: &str) -> Result<()>
+where
+    W: Write,
+{
+    for line in words.chunks(per_line) {
+        writeln!(writer, "{indent}{},", line.join(", "))?;
+    }
 
     Ok(())
 }
+
+/// Strict and reserved Cairo keywords, which cannot be used as identifiers.
+const KEYWORDS: [&str; 44] = [
+    "Self",
+    "as",
+    "break",
+    "const",
+    "continue",
+    "do",
+    "dyn",
+    "else",
+    "enum",
+    "extern",
+    "false",
+    "fn",
+    "for",
+    "hint",
+    "if",
+    "impl",
+    "implicits",
+    "in",
+    "let",
+    "loop",
+    "macro",
+    "match",
+    "mod",
+    "move",
+    "mut",
+    "nopanic",
+    "of",
+    "pub",
+    "ref",
+    "return",
+    "self",
+    "static",
+    "static_assert",
+    "struct",
+    "super",
+    "trait",
+    "true",
+    "try",
+    "type",
+    "typeof",
+    "unsafe",
+    "use",
+    "where",
+    "while",
+];
+
+/// Whether `name` can be used as a Cairo identifier.
+pub fn is_valid_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+        && !KEYWORDS.contains(&name)
+}
+
+/// Whether `name` can name a byte item, both as is for constants and lowercased for functions.
+pub fn is_valid_item_name(name: &str) -> bool {
+    is_valid_identifier(name) && is_valid_identifier(&name.to_lowercase())
+}
+
+/// Resolves one constant name per input.
+///
+/// Explicit names must match the inputs one-to-one. Without them, a single input is named `DATA`
+/// and multiple inputs are named after their file stems.
+pub fn const_names(inputs: &[PathBuf], names: &[String]) -> eyre::Result<Vec<String>> {
+    let names = if !names.is_empty() {
+        if names.len() != inputs.len() {
+            eyre::bail!(
+                "Expected one name per input ({}), got {}",
+                inputs.len(),
+                names.len()
+            );
+        }
+
+        names.to_vec()
+    } else if inputs.len() == 1 {
+        vec!["DATA".to_owned()]
+    } else {
+        inputs
+            .iter()
+            .map(|input| {
+                let stem = input
+                    .file_stem()
+                    .map(|stem| stem.to_string_lossy())
+                    .unwrap_or_default();
+
+                stem.chars()
+                    .map(|c| {
+                        if c.is_ascii_alphanumeric() {
+                            c.to_ascii_uppercase()
+                        } else {
+                            '_'
+                        }
+                    })
+                    .collect()
+            })
+            .collect()
+    };
+
+    for (ind, name) in names.iter().enumerate() {
+        if !is_valid_item_name(name) {
+            eyre::bail!("Invalid Cairo identifier: {name}");
+        }
+        // Function names are lowercased
+        if let Some(other) = names[..ind]
+            .iter()
+            .find(|other| other.eq_ignore_ascii_case(name))
+        {
+            if other == name {
+                eyre::bail!("Duplicate Cairo identifier: {name}");
+            }
+            eyre::bail!("Cairo identifiers {other} and {name} only differ in case");
+        }
+    }
+
+    Ok(names)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LENGTHS: [usize; 5] = [0, 1, 31, 32, 33];
+
+    fn encode(encoding: Encoding, bytes: &[u8]) -> String {
+        let options = CairoBytesOptions {
+            encoding,
+            visibility: Visibility::Pub,
+            container: Container::Const,
+        };
+        let mut output = vec![];
+        write_cairo_encoded(&mut output, "DATA", bytes, &options).unwrap();
+
+        String::from_utf8(output).unwrap()
+    }
+
+    /// Hex literals of the words of `output`, without their `0x` prefix.
+    fn words(output: &str) -> Vec<&str> {
+        output
+            .split(|c: char| !c.is_ascii_alphanumeric())
+            .filter_map(|token| token.strip_prefix("0x"))
+            .collect()
+    }
+
+    #[test]
+    fn packs_words_of_every_encoding() {
+        for encoding in Encoding::value_variants() {
+            let word_size = encoding.word_size().unwrap_or(1);
+
+            for len in LENGTHS {
+                // Leading zero bytes must survive packing
+                let bytes = (0..len as u8).collect::<Vec<_>>();
+                let output = encode(*encoding, &bytes);
+                let words = words(&output);
+
+                let chunks = bytes.chunks(word_size).collect::<Vec<_>>();
+                assert_eq!(words.len(), chunks.len(), "{encoding:?}, {len} bytes");
+                for (word, chunk) in words.iter().zip(chunks) {
+                    // Bytes are written without padding, words with the width of their chunk
+                    let word = match encoding {
+                        Encoding::U8 => format!("{word:0>2}"),
+                        _ => word.to_string(),
+                    };
+                    assert_eq!(
+                        hex::decode(word).unwrap(),
+                        chunk,
+                        "{encoding:?}, {len} bytes"
+                    );
+                }
+
+                if *encoding != Encoding::U8 {
+                    assert!(output.starts_with(&format!("pub const DATA_LEN: usize = {len};\n")));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn declares_array_size_in_words() {
+        for (encoding, ty, sizes) in [
+            (Encoding::U8, "u8", [0, 1, 31, 32, 33]),
+            (Encoding::U32, "u32", [0, 1, 8, 8, 9]),
+            (Encoding::U64, "u64", [0, 1, 4, 4, 5]),
+            (Encoding::U128, "u128", [0, 1, 2, 2, 3]),
+            (Encoding::U256, "u256", [0, 1, 1, 1, 2]),
+            (Encoding::Felt252, "felt252", [0, 1, 1, 2, 2]),
+        ] {
+            for (len, size) in LENGTHS.into_iter().zip(sizes) {
+                let output = encode(encoding, &vec![0xff; len]);
+                assert!(
+                    output.contains(&format!("pub const DATA: [{ty}; {size}] = [\n")),
+                    "{encoding:?}, {len} bytes:\n{output}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn appends_partial_byte_array_words_with_their_length() {
+        assert_eq!(
+            encode(Encoding::ByteArray, &[0xab; 33]),
+            format!(
+                "pub const DATA_LEN: usize = 33;\n\n\
+                 pub fn data() -> ByteArray {{\n    \
+                 let mut data: ByteArray = Default::default();\n    \
+                 data.append_word(0x{}, 31);\n    \
+                 data.append_word(0xabab, 2);\n    \
+                 data\n\
+                 }}\n",
+                "ab".repeat(31)
+            )
+        );
+
+        assert_eq!(
+            encode(Encoding::ByteArray, &[]),
+            "pub const DATA_LEN: usize = 0;\n\n\
+             pub fn data() -> ByteArray {\n    \
+             let mut data: ByteArray = Default::default();\n    \
+             data\n\
+             }\n"
+        );
+    }
+
+    #[test]
+    fn writes_every_container_with_its_visibility() {
+        let write = |container, visibility| {
+            let options = CairoBytesOptions {
+                encoding: Encoding::U32,
+                visibility,
+                container,
+            };
+            let mut output = vec![];
+            write_cairo_encoded(&mut output, "QUOTE", &[1, 2, 3, 4, 5], &options).unwrap();
+
+            String::from_utf8(output).unwrap()
+        };
+
+        assert_eq!(
+            write(Container::Const, Visibility::Private),
+            "const QUOTE_LEN: usize = 5;\n\n\
+             const QUOTE: [u32; 2] = [\n    0x01020304, 0x05,\n];\n"
+        );
+        assert_eq!(
+            write(Container::Span, Visibility::PubCrate),
+            "pub(crate) const QUOTE_LEN: usize = 5;\n\n\
+             pub(crate) fn quote() -> Span<u32> {\n    \
+             array![\n        0x01020304, 0x05,\n    ].span()\n}\n"
+        );
+        assert_eq!(
+            write(Container::Array, Visibility::Pub),
+            "pub const QUOTE_LEN: usize = 5;\n\n\
+             pub fn quote() -> Array<u32> {\n    \
+             array![\n        0x01020304, 0x05,\n    ]\n}\n"
+        );
+    }
+
+    #[test]
+    fn chain_fn_refers_to_items_by_container() {
+        let names = ["LEAF".to_owned(), "ROOT".to_owned()];
+        for (encoding, container, items) in [
+            (
+                Encoding::U8,
+                Container::Const,
+                "LEAF.span(),\n        ROOT.span(),",
+            ),
+            (Encoding::U8, Container::Span, "leaf(),\n        root(),"),
+            (
+                Encoding::U8,
+                Container::Array,
+                "leaf().span(),\n        root().span(),",
+            ),
+            (
+                Encoding::ByteArray,
+                Container::Const,
+                "leaf(),\n        root(),",
+            ),
+        ] {
+            let options = CairoBytesOptions {
+                encoding,
+                visibility: Visibility::Private,
+                container,
+            };
+            let mut output = vec![];
+            write_cairo_chain_fn(&mut output, "chain", &names, &options).unwrap();
+
+            let ret = match encoding {
+                Encoding::ByteArray => "Array<ByteArray>",
+                _ => "Array<Span<u8>>",
+            };
+            assert_eq!(
+                String::from_utf8(output).unwrap(),
+                format!("fn chain() -> {ret} {{\n    array![\n        {items}\n    ]\n}}\n")
+            );
+        }
+    }
+
+    #[test]
+    fn names_several_inputs() {
+        let inputs = [
+            PathBuf::from("certs/pck-leaf.der"),
+            PathBuf::from("root.der"),
+        ];
+        assert_eq!(const_names(&inputs, &[]).unwrap(), ["PCK_LEAF", "ROOT"]);
+        assert_eq!(const_names(&inputs[..1], &[]).unwrap(), ["DATA"]);
+        assert_eq!(
+            const_names(&inputs, &["LEAF".to_owned(), "ROOT_CA".to_owned()]).unwrap(),
+            ["LEAF", "ROOT_CA"]
+        );
+
+        for (names, error) in [
+            (&["LEAF"][..], "Expected one name per input (2), got 1"),
+            (&["LEAF", "1ROOT"], "Invalid Cairo identifier: 1ROOT"),
+            (&["LEAF", "LEAF"], "Duplicate Cairo identifier: LEAF"),
+            (
+                &["DATA", "Data"],
+                "Cairo identifiers DATA and Data only differ in case",
+            ),
+            (&["LEAF", "let"], "Invalid Cairo identifier: let"),
+            // Lowercased as a function name
+            (&["LEAF", "MATCH"], "Invalid Cairo identifier: MATCH"),
+        ] {
+            let names = names
+                .iter()
+                .map(|name| name.to_string())
+                .collect::<Vec<_>>();
+            assert_eq!(const_names(&inputs, &names).unwrap_err().to_string(), error);
+        }
+
+        let inputs = [PathBuf::from("a/root.der"), PathBuf::from("b/root.pem")];
+        assert_eq!(
+            const_names(&inputs, &[]).unwrap_err().to_string(),
+            "Duplicate Cairo identifier: ROOT"
+        );
+    }
+}
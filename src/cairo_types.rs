@@ -0,0 +1,263 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::PathBuf,
+};
+
+use clap::Args;
+use eyre::Result;
+use serde::Deserialize;
+
+/// Options overriding the Cairo module path and type names used by generated code.
+#[derive(Debug, Clone, Default, Args)]
+pub struct CairoTypesArgs {
+    /// Path to a JSON file with per-collateral module paths and type names, in the form of
+    /// `{"tcbinfo": {"module": "...", "names": {"TcbInfoV3": "..."}}}`. Sections are `tcbinfo`,
+    /// `qeidentity` and `quote`.
+    #[clap(long)]
+    types_config: Option<PathBuf>,
+    /// Cairo module path to import the generated types from. Takes precedence over the config
+    /// file.
+    #[clap(long)]
+    types_module: Option<String>,
+    /// Type name override in the form of `DEFAULT=CUSTOM`. Can be repeated. Takes precedence over
+    /// the config file.
+    #[clap(long, value_parser = parse_type_name)]
+    type_name: Vec<(String, String)>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TypesConfigSection {
+    module: Option<String>,
+    #[serde(default)]
+    names: HashMap<String, String>,
+}
+
+/// Resolved module path and type names for one kind of generated code.
+#[derive(Debug)]
+pub struct CairoTypes {
+    module: String,
+    names: HashMap<String, String>,
+}
+
+impl CairoTypesArgs {
+    /// Resolves the types of the given config section, validating overrides against the type
+    /// names the generator knows about.
+    pub fn resolve(
+        &self,
+        section: &str,
+        default_module: &str,
+        known_names: &[&str],
+    ) -> Result<CairoTypes> {
+        let mut config = match &self.types_config {
+            Some(path) => {
+                let mut sections: BTreeMap<String, TypesConfigSection> =
+                    serde_json::from_str(&std::fs::read_to_string(path)?)?;
+
+                if let Some(unknown) = sections
+                    .keys()
+                    .find(|key| !["tcbinfo", "qeidentity", "quote"].contains(&key.as_str()))
+                {
+                    eyre::bail!("Unknown types config section: {unknown}");
+                }
+
+                sections.remove(section).unwrap_or_default()
+            }
+            None => TypesConfigSection::default(),
+        };
+
+        if let Some(module) = &self.types_module {
+            config.module = Some(module.clone());
+        }
+        for (default, custom) in &self.type_name {
+            config.names.insert(default.clone(), custom.clone());
+        }
+
+        for (default, custom) in &config.names {
+            if !known_names.contains(&default.as_str()) {
+                eyre::bail!("Unknown {section} type name: {default}");
+            }
+            if !is_valid_path(custom) {
+                eyre::bail!("Invalid Cairo type name: {custom}");
+            }
+        }
+
+        let module = config.module.unwrap_or_else(|| default_module.to_owned());
+        if !is_valid_path(&module) {
+            eyre::bail!("Invalid Cairo module path: {module}");
+        }
+
+        Ok(CairoTypes {
+            module,
+            names: config.names,
+        })
+    }
+}
+
+impl CairoTypes {
+    pub fn module(&self) -> &str {
+        &self.module
+    }
+
+    /// Name to use in place of the default type name.
+    pub fn name(&self, default: &str) -> String {
+        self.names
+            .get(default)
+            .cloned()
+            .unwrap_or_else(|| default.to_owned())
+    }
+
+    /// Resolved names of the given default type names, keyed by the default name.
+    pub fn names(&self, defaults: &[&str]) -> BTreeMap<String, String> {
+        defaults
+            .iter()
+            .map(|default| ((*default).to_owned(), self.name(default)))
+            .collect()
+    }
+
+    /// Sorted, deduplicated names to import for the given default type names.
+    pub fn imports(&self, defaults: &[&str]) -> Vec<String> {
+        let mut imports = defaults
+            .iter()
+            .map(|default| self.name(default))
+            .collect::<Vec<_>>();
+        imports.sort();
+        imports.dedup();
+        imports
+    }
+}
+
+fn parse_type_name(value: &str) -> Result<(String, String), String> {
+    match value.split_once('=') {
+        Some((default, custom)) if !default.is_empty() && !custom.is_empty() => {
+            Ok((default.to_owned(), custom.to_owned()))
+        }
+        _ => Err(format!("expected DEFAULT=CUSTOM, got \"{value}\"")),
+    }
+}
+
+fn is_valid_path(path: &str) -> bool {
+    path.split("::").all(|segment| {
+        crate::cairo::is_valid_identifier(segment) || matches!(segment, "self" | "super")
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NAMES: [&str; 2] = ["TcbInfoV3", "TcbLevel"];
+
+    fn resolve(args: &CairoTypesArgs) -> Result<CairoTypes> {
+        args.resolve("tcbinfo", "crate::types::tcbinfo", &NAMES)
+    }
+
+    /// Arguments reading the given JSON as types config.
+    fn with_config(dir: &tempfile::TempDir, json: &str) -> CairoTypesArgs {
+        let path = dir.path().join("types.json");
+        std::fs::write(&path, json).unwrap();
+
+        CairoTypesArgs {
+            types_config: Some(path),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn defaults_without_overrides() {
+        let types = resolve(&CairoTypesArgs::default()).unwrap();
+
+        assert_eq!(types.module(), "crate::types::tcbinfo");
+        assert_eq!(types.name("TcbLevel"), "TcbLevel");
+        assert_eq!(types.imports(&NAMES), ["TcbInfoV3", "TcbLevel"]);
+    }
+
+    #[test]
+    fn prefers_command_line_over_config_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let config = r#"{
+            "tcbinfo": {"module": "a::b", "names": {"TcbInfoV3": "TcbInfo", "TcbLevel": "Level"}},
+            "quote": {"module": "c::d"}
+        }"#;
+        let types = resolve(&with_config(&dir, config)).unwrap();
+        assert_eq!(types.module(), "a::b");
+        assert_eq!(types.imports(&NAMES), ["Level", "TcbInfo"]);
+
+        let args = CairoTypesArgs {
+            types_module: Some("e::f".to_owned()),
+            type_name: vec![("TcbLevel".to_owned(), "TcbInfo".to_owned())],
+            ..with_config(&dir, config)
+        };
+        let types = resolve(&args).unwrap();
+        assert_eq!(types.module(), "e::f");
+        assert_eq!(types.name("TcbInfoV3"), "TcbInfo");
+        assert_eq!(types.name("TcbLevel"), "TcbInfo");
+        assert_eq!(types.imports(&NAMES), ["TcbInfo"]);
+    }
+
+    #[test]
+    fn rejects_unknown_sections_and_type_names() {
+        let dir = tempfile::tempdir().unwrap();
+        for (config, error) in [
+            (
+                r#"{"tcb_info": {}}"#,
+                "Unknown types config section: tcb_info",
+            ),
+            (
+                r#"{"tcbinfo": {"names": {"TcbInfoV2": "TcbInfo"}}}"#,
+                "Unknown tcbinfo type name: TcbInfoV2",
+            ),
+            (
+                r#"{"tcbinfo": {"module": "a::b", "name": {}}}"#,
+                "unknown field `name`, expected `module` or `names` at line 1 column 37",
+            ),
+        ] {
+            let result = resolve(&with_config(&dir, config));
+            assert_eq!(result.unwrap_err().to_string(), error);
+        }
+
+        let args = CairoTypesArgs {
+            type_name: vec![("QuoteHeader".to_owned(), "Header".to_owned())],
+            ..Default::default()
+        };
+        assert_eq!(
+            resolve(&args).unwrap_err().to_string(),
+            "Unknown tcbinfo type name: QuoteHeader"
+        );
+        assert_eq!(
+            parse_type_name("TcbLevel").unwrap_err(),
+            "expected DEFAULT=CUSTOM, got \"TcbLevel\""
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_paths() {
+        for (module, error) in [
+            ("a::", "Invalid Cairo module path: a::"),
+            ("a:b", "Invalid Cairo module path: a:b"),
+            ("a::1b", "Invalid Cairo module path: a::1b"),
+            ("a::type", "Invalid Cairo module path: a::type"),
+        ] {
+            let args = CairoTypesArgs {
+                types_module: Some(module.to_owned()),
+                ..Default::default()
+            };
+            assert_eq!(resolve(&args).unwrap_err().to_string(), error);
+        }
+
+        let args = CairoTypesArgs {
+            types_module: Some("super::types".to_owned()),
+            ..Default::default()
+        };
+        assert!(resolve(&args).is_ok());
+
+        let args = CairoTypesArgs {
+            type_name: vec![("TcbLevel".to_owned(), "Tcb Level".to_owned())],
+            ..Default::default()
+        };
+        assert_eq!(
+            resolve(&args).unwrap_err().to_string(),
+            "Invalid Cairo type name: Tcb Level"
+        );
+    }
+}
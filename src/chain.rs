@@ -0,0 +1,198 @@
+use eyre::Result;
+use x509_parser::prelude::{FromDer, X509Certificate};
+
+/// Orders DER-encoded certificates from leaf to root.
+///
+/// The input may be in any order. Exactly one self-signed root must be present, every other
+/// certificate must be reachable from it through issuer links, every issuer must be a CA, and all
+/// signatures along the way are verified. Validity periods are not checked here, but with `--at`
+/// of the commands reading the chain.
+///
+/// The root is only checked to be self-signed: it is up to the caller to compare it against a
+/// trusted root.
+pub fn order_chain(certs: Vec<Vec<u8>>) -> Result<Vec<Vec<u8>>> {
+    let mut parsed = vec![];
+    for der in &certs {
+        let (_, cert) = X509Certificate::from_der(der)
+            .map_err(|err| eyre::eyre!("Failed to parse certificate: {err}"))?;
+        parsed.push(cert);
+    }
+
+    let mut roots = parsed
+        .iter()
+        .enumerate()
+        .filter(|(_, cert)| cert.subject() == cert.issuer());
+    let root_ind = match (roots.next(), roots.next()) {
+        (Some((ind, _)), None) => ind,
+        (None, _) => eyre::bail!("No self-signed root certificate found in chain"),
+        (Some(_), Some(_)) => eyre::bail!("More than one self-signed certificate found in chain"),
+    };
+
+    parsed[root_ind]
+        .verify_signature(None)
+        .map_err(|err| eyre::eyre!("Invalid root certificate signature: {err}"))?;
+
+    // Walk down from the root, consuming one issued certificate at a time
+    let mut ordered = vec![root_ind];
+    while ordered.len() < parsed.len() {
+        let parent = &parsed[*ordered.last().unwrap()];
+
+        let mut children = parsed
+            .iter()
+            .enumerate()
+            .filter(|(ind, cert)| !ordered.contains(ind) && cert.issuer() == parent.subject());
+        let (child_ind, child) = match (children.next(), children.next()) {
+            (Some(child), None) => child,
+            (None, _) => eyre::bail!(
+                "Certificate chain is broken: no certificate issued by {}",
+                parent.subject()
+            ),
+            (Some(_), Some(_)) => eyre::bail!(
+                "Certificate chain is ambiguous: multiple certificates issued by {}",
+                parent.subject()
+            ),
+        };
+
+        if !parent.is_ca() {
+            eyre::bail!(
+                "Certificate {} is not a CA, but issues {}",
+                parent.subject(),
+                child.subject()
+            );
+        }
+        child
+            .verify_signature(Some(parent.public_key()))
+            .map_err(|err| {
+                eyre::eyre!(
+                    "Invalid signature on certificate {}: {err}",
+                    child.subject()
+                )
+            })?;
+
+        ordered.push(child_ind);
+    }
+
+    let mut certs = certs.into_iter().map(Some).collect::<Vec<_>>();
+    Ok(ordered
+        .into_iter()
+        .rev()
+        .map(|ind| certs[ind].take().unwrap())
+        .collect())
+}
+
+/// Stable constant names for each certificate of a leaf-to-root chain.
+pub fn chain_names(len: usize) -> Vec<String> {
+    match len {
+        0 => vec![],
+        1 => vec!["ROOT".into()],
+        2 => vec!["LEAF".into(), "ROOT".into()],
+        3 => vec!["LEAF".into(), "INTERMEDIATE".into(), "ROOT".into()],
+        _ => std::iter::once("LEAF".into())
+            .chain((1..len - 1).map(|ind| format!("INTERMEDIATE_{ind}")))
+            .chain(std::iter::once("ROOT".into()))
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rcgen::{CertifiedIssuer, KeyPair};
+
+    use super::*;
+    use crate::test_utils::{Pki, params};
+
+    #[test]
+    fn orders_shuffled_chain_from_leaf_to_root() {
+        let [leaf, intermediate, root] = Pki::new("Test").chain_der();
+
+        for shuffled in [
+            [&root, &leaf, &intermediate],
+            [&intermediate, &root, &leaf],
+            [&leaf, &intermediate, &root],
+        ] {
+            let ordered = order_chain(shuffled.map(Vec::clone).to_vec()).unwrap();
+            assert_eq!(ordered, [leaf.clone(), intermediate.clone(), root.clone()]);
+        }
+    }
+
+    #[test]
+    fn rejects_foreign_certificate() {
+        let [leaf, intermediate, root] = Pki::new("Test").chain_der();
+        let [other_leaf, _, other_root] = Pki::new("Other").chain_der();
+
+        // Issued by a CA absent from the chain
+        let error = order_chain(vec![
+            leaf.clone(),
+            intermediate.clone(),
+            root.clone(),
+            other_leaf,
+        ])
+        .unwrap_err();
+        assert!(error.to_string().starts_with("Certificate chain is broken"));
+
+        let error = order_chain(vec![leaf, intermediate, root, other_root]).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "More than one self-signed certificate found in chain"
+        );
+    }
+
+    #[test]
+    fn rejects_certificate_signed_by_another_key() {
+        let [leaf, _, root] = Pki::new("Test").chain_der();
+        // Same subjects, but different keys
+        let [_, other_intermediate, _] = Pki::new("Test").chain_der();
+
+        let error = order_chain(vec![leaf, other_intermediate, root]).unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .starts_with("Invalid signature on certificate CN=Test Intermediate CA")
+        );
+    }
+
+    #[test]
+    fn names_chain_certificates_from_leaf_to_root() {
+        assert_eq!(chain_names(1), ["ROOT"]);
+        assert_eq!(chain_names(2), ["LEAF", "ROOT"]);
+        assert_eq!(chain_names(3), ["LEAF", "INTERMEDIATE", "ROOT"]);
+        assert_eq!(
+            chain_names(5),
+            [
+                "LEAF",
+                "INTERMEDIATE_1",
+                "INTERMEDIATE_2",
+                "INTERMEDIATE_3",
+                "ROOT"
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_issuer_without_ca_constraint() {
+        let pki = Pki::new("Test");
+        let issuer = CertifiedIssuer::signed_by(
+            params("Not a CA", false),
+            KeyPair::generate().unwrap(),
+            &pki.root,
+        )
+        .unwrap();
+        let leaf = CertifiedIssuer::signed_by(
+            params("Leaf", false),
+            KeyPair::generate().unwrap(),
+            &issuer,
+        )
+        .unwrap();
+
+        let error = order_chain(
+            [&leaf, &issuer, &pki.root]
+                .map(|cert| cert.der().to_vec())
+                .to_vec(),
+        )
+        .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Certificate CN=Not a CA is not a CA, but issues CN=Leaf"
+        );
+    }
+}
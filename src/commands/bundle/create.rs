@@ -0,0 +1,108 @@
+use std::path::PathBuf;
+
+use clap::Parser;
+use eyre::Result;
+
+use crate::{
+    bundle::{BUNDLE_VERSION, Bundle, BundleItem},
+    quote::Quote,
+};
+
+#[derive(Debug, Parser)]
+pub struct CreateCommand {
+    /// Path to the output bundle file.
+    #[clap(long)]
+    output: PathBuf,
+    /// Path to the quote file.
+    #[clap(long)]
+    quote: Option<PathBuf>,
+    /// Path to the tcbinfo JSON file.
+    #[clap(long)]
+    tcbinfo: Option<PathBuf>,
+    /// Path to the qeidentity JSON file.
+    #[clap(long)]
+    qeidentity: Option<PathBuf>,
+    /// Path to the PEM issuer chain of the tcbinfo.
+    #[clap(long)]
+    tcbinfo_issuer_chain: Option<PathBuf>,
+    /// Path to the PEM issuer chain of the qeidentity.
+    #[clap(long)]
+    qeidentity_issuer_chain: Option<PathBuf>,
+    /// Path to the PCK CRL.
+    #[clap(long)]
+    pck_crl: Option<PathBuf>,
+    /// Path to the PEM issuer chain of the PCK CRL.
+    #[clap(long)]
+    pck_crl_issuer_chain: Option<PathBuf>,
+    /// Path to the root CA certificate.
+    #[clap(long)]
+    root_ca: Option<PathBuf>,
+    /// Path to the root CA CRL.
+    #[clap(long)]
+    root_ca_crl: Option<PathBuf>,
+    /// Metadata entry in the form of `KEY=VALUE`. Can be repeated.
+    #[clap(long, value_parser = parse_metadata)]
+    metadata: Vec<(String, String)>,
+}
+
+impl CreateCommand {
+    pub fn run(self) -> Result<()> {
+        let mut bundle = Bundle {
+            version: BUNDLE_VERSION,
+            metadata: self.metadata.iter().cloned().collect(),
+            ..Default::default()
+        };
+
+        for (item, path) in [
+            (BundleItem::Quote, &self.quote),
+            (BundleItem::Tcbinfo, &self.tcbinfo),
+            (BundleItem::Qeidentity, &self.qeidentity),
+            (BundleItem::TcbinfoIssuerChain, &self.tcbinfo_issuer_chain),
+            (
+                BundleItem::QeidentityIssuerChain,
+                &self.qeidentity_issuer_chain,
+            ),
+            (BundleItem::PckCrl, &self.pck_crl),
+            (BundleItem::PckCrlIssuerChain, &self.pck_crl_issuer_chain),
+            (BundleItem::RootCa, &self.root_ca),
+            (BundleItem::RootCaCrl, &self.root_ca_crl),
+        ] {
+            let Some(path) = path else {
+                continue;
+            };
+
+            let contents = std::fs::read(path)?;
+            check_item(item, &contents)
+                .map_err(|err| err.wrap_err(format!("Invalid {}", path.display())))?;
+            bundle.insert(item, &contents);
+        }
+
+        if bundle.items.is_empty() {
+            eyre::bail!("No items to bundle");
+        }
+
+        bundle.write(&self.output)
+    }
+}
+
+/// Catches obviously misplaced files early, leaving full validation to the preprocess commands.
+fn check_item(item: BundleItem, contents: &[u8]) -> Result<()> {
+    match item {
+        BundleItem::Quote => {
+            Quote::from_bytes(contents)?;
+        }
+        BundleItem::Tcbinfo | BundleItem::Qeidentity => {
+            serde_json::from_slice::<serde_json::Value>(contents)?;
+        }
+        _ => {}
+    }
+
+    Ok(())
+}
+
+fn parse_metadata(value: &str) -> Result<(String, String), String> {
+    match value.split_once('=') {
+        Some((key, value)) if !key.is_empty() => Ok((key.to_owned(), value.to_owned())),
+        _ => Err(format!("expected KEY=VALUE, got \"{value}\"")),
+    }
+}
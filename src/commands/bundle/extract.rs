@@ -0,0 +1,37 @@
+use std::path::PathBuf;
+
+use clap::Parser;
+use eyre::Result;
+
+use crate::bundle::{Bundle, BundleItem};
+
+#[derive(Debug, Parser)]
+pub struct ExtractCommand {
+    /// Path to the bundle file.
+    #[clap(long)]
+    input: PathBuf,
+    /// Directory to write the items into, each named after its kind, e.g. `tcbinfo.json`.
+    #[clap(long)]
+    output: PathBuf,
+    /// Item to extract. Can be repeated. Defaults to all items of the bundle.
+    #[clap(long, value_enum)]
+    item: Vec<BundleItem>,
+}
+
+impl ExtractCommand {
+    pub fn run(self) -> Result<()> {
+        let bundle = Bundle::read(&self.input)?;
+        let items = if self.item.is_empty() {
+            bundle.items.keys().copied().collect()
+        } else {
+            self.item
+        };
+
+        std::fs::create_dir_all(&self.output)?;
+        for item in items {
+            std::fs::write(self.output.join(item.file_name()), bundle.get(item)?)?;
+        }
+
+        Ok(())
+    }
+}
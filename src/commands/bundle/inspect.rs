@@ -0,0 +1,46 @@
+use std::path::PathBuf;
+
+use clap::Parser;
+use eyre::Result;
+use sha2::{Digest, Sha256};
+
+use crate::bundle::Bundle;
+
+#[derive(Debug, Parser)]
+pub struct InspectCommand {
+    /// Path to the bundle file.
+    #[clap(long)]
+    input: PathBuf,
+}
+
+impl InspectCommand {
+    pub fn run(self) -> Result<()> {
+        print!("{}", describe(&Bundle::read(&self.input)?)?);
+
+        Ok(())
+    }
+}
+
+/// Human-readable summary of the metadata and items of `bundle`.
+pub fn describe(bundle: &Bundle) -> Result<String> {
+    let mut output = format!("Bundle version {}\n", bundle.version);
+    if !bundle.metadata.is_empty() {
+        output.push_str("Metadata:\n");
+        for (key, value) in &bundle.metadata {
+            output.push_str(&format!("  {key}: {value}\n"));
+        }
+    }
+
+    output.push_str("Items:\n");
+    for item in bundle.items.keys() {
+        let contents = bundle.get(*item)?;
+        output.push_str(&format!(
+            "  {}: {} bytes, sha256 {}\n",
+            item.name(),
+            contents.len(),
+            hex::encode(Sha256::digest(&contents))
+        ));
+    }
+
+    Ok(output)
+}
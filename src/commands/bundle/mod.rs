@@ -0,0 +1,120 @@
+use clap::{Parser, Subcommand};
+use eyre::Result;
+
+mod create;
+use create::CreateCommand;
+
+mod inspect;
+use inspect::InspectCommand;
+
+mod extract;
+use extract::ExtractCommand;
+
+#[derive(Debug, Parser)]
+pub struct Bundle {
+    #[clap(subcommand)]
+    command: Subcommands,
+}
+
+#[derive(Debug, Subcommand)]
+enum Subcommands {
+    /// Package a quote and its collateral into a single bundle file.
+    Create(CreateCommand),
+    /// Print the metadata and items of a bundle.
+    Inspect(InspectCommand),
+    /// Write the items of a bundle back to separate files.
+    Extract(ExtractCommand),
+}
+
+impl Bundle {
+    pub fn run(self) -> Result<()> {
+        match self.command {
+            Subcommands::Create(cmd) => cmd.run(),
+            Subcommands::Inspect(cmd) => cmd.run(),
+            Subcommands::Extract(cmd) => cmd.run(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use sha2::{Digest, Sha256};
+
+    use super::*;
+    use crate::bundle::{Bundle as BundleFile, BundleItem};
+
+    fn run(args: &[&str]) -> Result<()> {
+        Bundle::try_parse_from([&["bundle"], args].concat())?.run()
+    }
+
+    #[test]
+    fn round_trips_items_through_create_inspect_and_extract() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = |name: &str| dir.path().join(name).to_str().unwrap().to_owned();
+
+        let tcbinfo = br#"{"tcbInfo": {"id":"SGX"},  "signature":"00"}"#;
+        let pck_crl = [0x30, 0x82, 0xff, 0x00];
+        std::fs::write(path("tcbinfo.json"), tcbinfo).unwrap();
+        std::fs::write(path("crl.der"), pck_crl).unwrap();
+
+        run(&[
+            "create",
+            "--output",
+            &path("bundle.json"),
+            "--tcbinfo",
+            &path("tcbinfo.json"),
+            "--pck-crl",
+            &path("crl.der"),
+            "--metadata",
+            "source=pcs",
+        ])
+        .unwrap();
+
+        let bundle = BundleFile::read(dir.path().join("bundle.json").as_path()).unwrap();
+        assert_eq!(
+            inspect::describe(&bundle).unwrap(),
+            format!(
+                "Bundle version 1\nMetadata:\n  source: pcs\nItems:\n  \
+                 tcbinfo: {} bytes, sha256 {}\n  pck-crl: 4 bytes, sha256 {}\n",
+                tcbinfo.len(),
+                hex::encode(Sha256::digest(tcbinfo)),
+                hex::encode(Sha256::digest(pck_crl))
+            )
+        );
+
+        run(&[
+            "extract",
+            "--input",
+            &path("bundle.json"),
+            "--output",
+            &path("all"),
+        ])
+        .unwrap();
+        assert_eq!(std::fs::read(path("all/tcbinfo.json")).unwrap(), tcbinfo);
+        assert_eq!(std::fs::read(path("all/pck_crl.crl")).unwrap(), pck_crl);
+
+        let args = ["--input", &path("bundle.json"), "--output", &path("some")];
+        run(&[&["extract"], &args[..], &["--item", "pck-crl"]].concat()).unwrap();
+        assert!(!dir.path().join("some/tcbinfo.json").exists());
+        assert_eq!(std::fs::read(path("some/pck_crl.crl")).unwrap(), pck_crl);
+
+        let error = run(&[&["extract"], &args[..], &["--item", "quote"]].concat()).unwrap_err();
+        assert_eq!(error.to_string(), "Bundle has no quote item");
+        assert_eq!(bundle.get(BundleItem::PckCrl).unwrap(), pck_crl);
+    }
+
+    #[test]
+    fn rejects_misplaced_or_missing_items() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = |name: &str| dir.path().join(name).to_str().unwrap().to_owned();
+        std::fs::write(path("crl.der"), [0x30, 0x82]).unwrap();
+
+        let error = run(&["create", "--output", &path("bundle.json")]).unwrap_err();
+        assert_eq!(error.to_string(), "No items to bundle");
+
+        let output = ["create", "--output", &path("bundle.json")];
+        let error = run(&[&output[..], &["--tcbinfo", &path("crl.der")]].concat()).unwrap_err();
+        assert_eq!(error.to_string(), format!("Invalid {}", path("crl.der")));
+        assert!(!dir.path().join("bundle.json").exists());
+    }
+}
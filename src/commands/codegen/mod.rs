@@ -0,0 +1,25 @@
+use clap::{Parser, Subcommand};
+use eyre::Result;
+
+mod types;
+use types::TypesCommand;
+
+#[derive(Debug, Parser)]
+pub struct Codegen {
+    #[clap(subcommand)]
+    command: Subcommands,
+}
+
+#[derive(Debug, Subcommand)]
+enum Subcommands {
+    /// Generate the Cairo type definitions matching the data emitted by `preprocess`.
+    Types(TypesCommand),
+}
+
+impl Codegen {
+    pub fn run(self) -> Result<()> {
+        match self.command {
+            Subcommands::Types(cmd) => cmd.run(),
+        }
+    }
+}
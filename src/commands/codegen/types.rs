@@ -0,0 +1,146 @@
+use std::path::PathBuf;
+
+use clap::{Parser, ValueEnum};
+use eyre::Result;
+
+use crate::{
+    cairo_types::CairoTypesArgs,
+    commands::preprocess,
+    emitter::{DateFormat, StringFormat, TcbStatusFormat},
+};
+
+#[derive(Debug, Parser)]
+pub struct TypesCommand {
+    /// Kind of data to generate the type definitions for.
+    #[clap(long, value_enum)]
+    kind: TypesKind,
+    /// Path to the output Cairo file.
+    #[clap(long)]
+    output: PathBuf,
+    /// Cairo representation of dates. Must match the `--date-format` used for the data.
+    #[clap(long, value_enum, default_value_t = DateFormat::OffsetDateTime)]
+    date_format: DateFormat,
+    /// Cairo representation of collateral strings. Must match the `--string-format` used for the
+    /// data.
+    #[clap(long, value_enum, default_value_t = StringFormat::ByteArray)]
+    string_format: StringFormat,
+    /// Cairo representation of TCB statuses. With `enum`, the `TcbStatus` enum is defined as well.
+    /// Must match the `--tcb-status-format` used for the data.
+    #[clap(long, value_enum, default_value_t = TcbStatusFormat::String)]
+    tcb_status_format: TcbStatusFormat,
+    /// Type name overrides. Module paths are ignored as the types are defined in the output file.
+    #[clap(flatten)]
+    types: CairoTypesArgs,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum TypesKind {
+    /// Types of `preprocess tcbinfo` data.
+    Tcbinfo,
+    /// Types of `preprocess qeidentity` data.
+    Qeidentity,
+    /// Types of `preprocess quote --format cairo-struct` data.
+    Quote,
+}
+
+impl TypesCommand {
+    pub fn run(self) -> Result<()> {
+        let mut output = String::new();
+
+        let type_defs = match self.kind {
+            TypesKind::Tcbinfo => {
+                let types = self.types.resolve(
+                    "tcbinfo",
+                    preprocess::tcbinfo::TYPES_MODULE,
+                    &preprocess::tcbinfo::TYPE_NAMES,
+                )?;
+                if let Some(imports) = self.date_format.type_imports() {
+                    output.push_str(imports);
+                    output.push('\n');
+                }
+                preprocess::tcbinfo::type_defs(
+                    &types,
+                    self.date_format,
+                    self.string_format,
+                    self.tcb_status_format,
+                )
+            }
+            TypesKind::Qeidentity => {
+                let types = self.types.resolve(
+                    "qeidentity",
+                    preprocess::qeidentity::TYPES_MODULE,
+                    &preprocess::qeidentity::TYPE_NAMES,
+                )?;
+                if let Some(imports) = self.date_format.type_imports() {
+                    output.push_str(imports);
+                    output.push('\n');
+                }
+                preprocess::qeidentity::type_defs(
+                    &types,
+                    self.date_format,
+                    self.string_format,
+                    self.tcb_status_format,
+                )
+            }
+            TypesKind::Quote => {
+                let types = self.types.resolve(
+                    "quote",
+                    preprocess::quote::TYPES_MODULE,
+                    &preprocess::quote::TYPE_NAMES,
+                )?;
+                preprocess::quote::type_defs(&types)
+            }
+        };
+
+        let type_defs = type_defs
+            .iter()
+            .map(|type_def| type_def.render())
+            .collect::<Vec<_>>();
+        output.push_str(&type_defs.join("\n"));
+
+        std::fs::write(&self.output, output)?;
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Runs `codegen types` with `args`, returning the generated Cairo.
+    fn generate(args: &[&str]) -> String {
+        let dir = tempfile::tempdir().unwrap();
+        let output = dir.path().join("types.cairo");
+
+        let mut command = vec!["types", "--output", output.to_str().unwrap()];
+        command.extend(args);
+        TypesCommand::try_parse_from(command)
+            .unwrap()
+            .run()
+            .unwrap();
+
+        std::fs::read_to_string(output).unwrap()
+    }
+
+    #[test]
+    fn defines_renamed_types_of_each_kind() {
+        let cairo = generate(&[
+            "--kind",
+            "qeidentity",
+            "--type-name",
+            "EnclaveIdentityV2TcbLevel=QeTcb",
+        ]);
+        assert!(cairo.starts_with("use time::OffsetDateTime;\n\n#[derive(Drop)]\n"));
+        assert!(cairo.contains("pub struct QeTcb {\n    pub isvsvn: u16,\n}\n"));
+        assert!(cairo.contains("    pub tcb: QeTcb,\n    pub tcb_date: OffsetDateTime,\n"));
+        assert!(!cairo.contains("EnclaveIdentityV2TcbLevel "));
+
+        let cairo = generate(&["--kind", "tcbinfo", "--date-format", "unix"]);
+        assert!(cairo.starts_with("#[derive(Drop, Serde)]\npub struct TcbInfoV3 {\n"));
+        assert!(cairo.contains("    pub issue_date: u64,\n"));
+
+        let cairo = generate(&["--kind", "quote", "--types-module", "ignored"]);
+        assert!(cairo.contains("pub enum QuoteBody {\n"));
+    }
+}
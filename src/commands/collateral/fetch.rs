@@ -0,0 +1,374 @@
+use std::path::PathBuf;
+
+use clap::Parser;
+use eyre::Result;
+
+use crate::{
+    bundle::BundleItem,
+    chain::order_chain,
+    commands::preprocess::{qeidentity, tcbinfo},
+    pck::{fmspc, pck_chain},
+    pcs::{Fetched, INTEL_PCS_URL, PckCa, PcsClient},
+    quote::{Quote, TeeType},
+    store::CollateralStore,
+    verify::{parse_issuer_chain, read_certificate, verify_crl, verify_signed_body},
+};
+
+#[derive(Debug, Parser)]
+pub struct FetchCommand {
+    /// Base URL of PCS or of a PCCS serving the PCS v4 API.
+    #[clap(long, env = "DCAP_CAIRO_PCS_URL", default_value = INTEL_PCS_URL)]
+    base_url: String,
+    /// Hex-encoded FMSPC to fetch the tcbinfo of. Required unless given `--quote`.
+    #[clap(long, required_unless_present = "quote", requires = "tee_type")]
+    fmspc: Option<String>,
+    /// TEE type to fetch collateral for. Only used with `--fmspc`.
+    #[clap(long, value_enum)]
+    tee_type: Option<TeeType>,
+    /// Path to a quote to fetch collateral for, using the TEE type of the quote and the FMSPC of
+    /// its PCK certificate.
+    #[clap(long, conflicts_with_all = ["fmspc", "tee_type"])]
+    quote: Option<PathBuf>,
+    /// TCB evaluation data number of the tcbinfo and qeidentity. Defaults to the one currently
+    /// served.
+    #[clap(long)]
+    tcb_evaluation_data_number: Option<u32>,
+    /// CA whose PCK CRL to fetch.
+    #[clap(long, value_enum, default_value_t = PckCa::Processor)]
+    ca: PckCa,
+    /// Directory to write the collateral into, with the file names of `bundle extract`.
+    #[clap(long)]
+    output: PathBuf,
+    /// Path to the trusted root CA certificate, in PEM or DER format, which every issuer chain
+    /// must end with. Required with `--store`. Otherwise, collateral is only checked against the
+    /// root of the issuer chains served along with it.
+    #[clap(long)]
+    root_ca: Option<PathBuf>,
+    /// Path to a collateral store to also import the tcbinfo and qeidentity into.
+    #[clap(long, requires = "root_ca")]
+    store: Option<PathBuf>,
+}
+
+impl FetchCommand {
+    pub fn run(self) -> Result<()> {
+        let (tee_type, fmspc) = match (&self.quote, &self.fmspc, self.tee_type) {
+            (Some(quote), _, _) => {
+                let quote = Quote::from_bytes(&std::fs::read(quote)?)?;
+                (
+                    quote.header.tee_type,
+                    fmspc(&pck_chain(&quote)?[0])?.to_vec(),
+                )
+            }
+            (None, Some(fmspc), Some(tee_type)) => {
+                let fmspc = hex::decode(fmspc)
+                    .map_err(|err| eyre::eyre!("Invalid FMSPC {fmspc}: {err}"))?;
+                if fmspc.len() != 6 {
+                    eyre::bail!("Invalid FMSPC: expected 6 bytes, got {}", fmspc.len());
+                }
+                (tee_type, fmspc)
+            }
+            _ => eyre::bail!("Expected either --quote or both --fmspc and --tee-type"),
+        };
+
+        let client = PcsClient::new(&self.base_url);
+        let tcb_info = client.tcb_info(tee_type, &fmspc, self.tcb_evaluation_data_number)?;
+        let qe_identity = client.qe_identity(tee_type, self.tcb_evaluation_data_number)?;
+        let pck_crl = client.pck_crl(self.ca)?;
+        let root_ca_crl = client.root_ca_crl()?;
+
+        // Check everything before writing anything
+        let tcb_info_chain = issuer_chain(&tcb_info, BundleItem::Tcbinfo)?;
+        let qe_identity_chain = issuer_chain(&qe_identity, BundleItem::Qeidentity)?;
+        let pck_crl_chain = issuer_chain(&pck_crl, BundleItem::PckCrl)?;
+        let root_ca = match &self.root_ca {
+            Some(root_ca) => read_certificate(root_ca)?,
+            None => order_chain(parse_issuer_chain(tcb_info_chain)?)?
+                .pop()
+                .unwrap_or_default(),
+        };
+
+        let tcb_info_json = std::str::from_utf8(&tcb_info.body)?;
+        verify_signed_body(
+            parse_issuer_chain(tcb_info_chain)?,
+            &root_ca,
+            &tcbinfo::signed_body(tcb_info_json)?,
+        )
+        .map_err(|err| err.wrap_err("Failed to verify the fetched tcbinfo"))?;
+
+        let qe_identity_json = std::str::from_utf8(&qe_identity.body)?;
+        verify_signed_body(
+            parse_issuer_chain(qe_identity_chain)?,
+            &root_ca,
+            &qeidentity::signed_body(qe_identity_json)?,
+        )
+        .map_err(|err| err.wrap_err("Failed to verify the fetched qeidentity"))?;
+
+        let pck_crl_issuers = order_chain(parse_issuer_chain(pck_crl_chain)?)?;
+        if pck_crl_issuers.last() != Some(&root_ca) {
+            eyre::bail!("PCK CRL issuer chain does not end with the trusted root CA");
+        }
+        verify_crl(&pck_crl.body, &pck_crl_issuers[0])
+            .map_err(|err| err.wrap_err("Failed to verify the fetched PCK CRL"))?;
+        verify_crl(&root_ca_crl, &root_ca)
+            .map_err(|err| err.wrap_err("Failed to verify the fetched root CA CRL"))?;
+
+        std::fs::create_dir_all(&self.output)?;
+        for (item, contents) in [
+            (BundleItem::Tcbinfo, tcb_info.body.as_slice()),
+            (BundleItem::TcbinfoIssuerChain, tcb_info_chain),
+            (BundleItem::Qeidentity, &qe_identity.body),
+            (BundleItem::QeidentityIssuerChain, qe_identity_chain),
+            (BundleItem::PckCrl, &pck_crl.body),
+            (BundleItem::PckCrlIssuerChain, pck_crl_chain),
+            (BundleItem::RootCaCrl, &root_ca_crl),
+        ] {
+            std::fs::write(self.output.join(item.file_name()), contents)?;
+        }
+
+        if let Some(store) = &self.store {
+            let mut store = CollateralStore::open(store)?;
+            for json in [tcb_info_json, qe_identity_json] {
+                store.import(json)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// Issuer chain served along with `item`, which PCS always sends.
+fn issuer_chain(fetched: &Fetched, item: BundleItem) -> Result<&[u8]> {
+    match &fetched.issuer_chain {
+        Some(issuer_chain) => Ok(issuer_chain),
+        None => eyre::bail!("Missing issuer chain header for {}", item.name()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{BufRead, BufReader, Write},
+        net::TcpListener,
+    };
+
+    use super::*;
+    use crate::test_utils::{Pki, qe_identity_json, tcb_info_json, url_encode};
+
+    /// Canned response of the mock server: path without query, issuer chain header and body.
+    type Route = (&'static str, Option<(&'static str, String)>, Vec<u8>);
+
+    /// Serves `routes` on a local port until the test exits, answering 404 to anything else.
+    fn serve(routes: Vec<Route>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let base_url = format!("http://{}", listener.local_addr().unwrap());
+
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut reader = BufReader::new(&stream);
+                let mut request_line = String::new();
+                reader.read_line(&mut request_line).unwrap();
+                loop {
+                    let mut header = String::new();
+                    reader.read_line(&mut header).unwrap();
+                    if header.trim().is_empty() {
+                        break;
+                    }
+                }
+
+                let target = request_line.split(' ').nth(1).unwrap_or_default();
+                let path = target.split('?').next().unwrap_or_default();
+                let (status, header, body) =
+                    match routes.iter().find(|(route, _, _)| *route == path) {
+                        Some((_, header, body)) => ("200 OK", header.clone(), body.clone()),
+                        None => ("404 Not Found", None, vec![]),
+                    };
+
+                let mut response = format!(
+                    "HTTP/1.1 {status}\r\nContent-Length: {}\r\nConnection: close\r\n",
+                    body.len()
+                );
+                if let Some((name, value)) = header {
+                    response.push_str(&format!("{name}: {}\r\n", url_encode(&value)));
+                }
+                response.push_str("\r\n");
+                stream.write_all(response.as_bytes()).unwrap();
+                stream.write_all(&body).unwrap();
+            }
+        });
+
+        base_url
+    }
+
+    fn pcs_routes(pki: &Pki) -> Vec<Route> {
+        vec![
+            (
+                "/sgx/certification/v4/tcb",
+                Some(("TCB-Info-Issuer-Chain", pki.chain_pem())),
+                tcb_info_json(Some(pki), &[(13, "2024-03-13T00:00:00Z", "UpToDate")]).into_bytes(),
+            ),
+            (
+                "/sgx/certification/v4/qe/identity",
+                Some(("SGX-Enclave-Identity-Issuer-Chain", pki.chain_pem())),
+                qe_identity_json(Some(pki), &[(8, "2024-03-13T00:00:00Z", "UpToDate")])
+                    .into_bytes(),
+            ),
+            (
+                "/sgx/certification/v4/pckcrl",
+                Some(("SGX-PCK-CRL-Issuer-Chain", pki.crl_chain_pem())),
+                pki.pck_crl.clone(),
+            ),
+            (
+                "/sgx/certification/v4/rootcacrl",
+                None,
+                hex::encode(&pki.root_ca_crl).into_bytes(),
+            ),
+        ]
+    }
+
+    fn fetch(base_url: &str, dir: &std::path::Path, extra_args: &[&str]) -> Result<()> {
+        let output = dir.join("out");
+        let args = [
+            "fetch",
+            "--base-url",
+            base_url,
+            "--fmspc",
+            "00906ED50000",
+            "--tee-type",
+            "sgx",
+            "--output",
+            output.to_str().unwrap(),
+        ];
+        FetchCommand::try_parse_from(args.iter().chain(extra_args))?.run()
+    }
+
+    #[test]
+    fn fetches_verifies_and_imports() {
+        let pki = Pki::new("Test");
+        let base_url = serve(pcs_routes(&pki));
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("root.der"), pki.root_der()).unwrap();
+
+        let root_ca = dir.path().join("root.der");
+        let store = dir.path().join("store");
+        fetch(
+            &base_url,
+            dir.path(),
+            &[
+                "--root-ca",
+                root_ca.to_str().unwrap(),
+                "--store",
+                store.to_str().unwrap(),
+            ],
+        )
+        .unwrap();
+
+        for item in [
+            BundleItem::Tcbinfo,
+            BundleItem::TcbinfoIssuerChain,
+            BundleItem::Qeidentity,
+            BundleItem::QeidentityIssuerChain,
+            BundleItem::PckCrl,
+            BundleItem::PckCrlIssuerChain,
+            BundleItem::RootCaCrl,
+        ] {
+            assert!(dir.path().join("out").join(item.file_name()).exists());
+        }
+        assert_eq!(
+            std::fs::read(dir.path().join("out/root_ca_crl.crl")).unwrap(),
+            pki.root_ca_crl
+        );
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("out/tcbinfo_issuer_chain.pem")).unwrap(),
+            pki.chain_pem()
+        );
+
+        let store = CollateralStore::open(&store).unwrap();
+        assert_eq!(store.tcb_infos().len(), 1);
+        assert_eq!(store.qe_identities().len(), 1);
+    }
+
+    #[test]
+    fn rejects_untrusted_root() {
+        let pki = Pki::new("Test");
+        let base_url = serve(pcs_routes(&pki));
+        let dir = tempfile::tempdir().unwrap();
+        let root_ca = dir.path().join("root.der");
+        std::fs::write(&root_ca, Pki::new("Other").root_der()).unwrap();
+
+        let err = fetch(
+            &base_url,
+            dir.path(),
+            &["--root-ca", root_ca.to_str().unwrap()],
+        )
+        .unwrap_err();
+        assert!(format!("{err:?}").contains("trusted root CA"), "{err:?}");
+        assert!(!dir.path().join("out").exists());
+    }
+
+    #[test]
+    fn rejects_tampered_collateral() {
+        let pki = Pki::new("Test");
+        let mut routes = pcs_routes(&pki);
+        let tampered = String::from_utf8(routes[0].2.clone())
+            .unwrap()
+            .replace("UpToDate", "OutOfDate");
+        routes[0].2 = tampered.into_bytes();
+        let base_url = serve(routes);
+        let dir = tempfile::tempdir().unwrap();
+
+        let err = fetch(&base_url, dir.path(), &[]).unwrap_err();
+        assert!(
+            format!("{err:?}").contains("Invalid collateral signature"),
+            "{err:?}"
+        );
+        assert!(!dir.path().join("out").exists());
+    }
+
+    #[test]
+    fn writes_nothing_without_issuer_chain() {
+        let pki = Pki::new("Test");
+        let mut routes = pcs_routes(&pki);
+        routes[2].1 = None;
+        let base_url = serve(routes);
+        let dir = tempfile::tempdir().unwrap();
+
+        let err = fetch(&base_url, dir.path(), &[]).unwrap_err();
+        assert!(format!("{err}").contains("Missing issuer chain header for pck-crl"));
+        assert!(!dir.path().join("out").exists());
+    }
+
+    #[test]
+    fn rejects_fmspc_of_wrong_length() {
+        let dir = tempfile::tempdir().unwrap();
+        let output = dir.path().join("out");
+        let err = FetchCommand::try_parse_from([
+            "fetch",
+            "--base-url",
+            "http://127.0.0.1:1",
+            "--fmspc",
+            "00906E",
+            "--tee-type",
+            "sgx",
+            "--output",
+            output.to_str().unwrap(),
+        ])
+        .unwrap()
+        .run()
+        .unwrap_err();
+        assert_eq!(err.to_string(), "Invalid FMSPC: expected 6 bytes, got 3");
+    }
+
+    #[test]
+    fn reports_http_errors() {
+        let base_url = serve(vec![]);
+        let dir = tempfile::tempdir().unwrap();
+
+        let err = fetch(&base_url, dir.path(), &[]).unwrap_err();
+        assert!(
+            err.to_string()
+                .ends_with("failed with status 404 Not Found"),
+            "{err}"
+        );
+    }
+}
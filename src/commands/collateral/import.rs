@@ -0,0 +1,35 @@
+use std::path::PathBuf;
+
+use clap::Parser;
+use eyre::Result;
+
+use crate::store::{CollateralStore, StoreEntry};
+
+#[derive(Debug, Parser)]
+pub struct ImportCommand {
+    /// Path to the collateral store directory, created if needed.
+    #[clap(long)]
+    store: PathBuf,
+    /// Path to a tcbinfo or qeidentity JSON file. Can be repeated.
+    #[clap(long, required = true)]
+    input: Vec<PathBuf>,
+}
+
+impl ImportCommand {
+    pub fn run(self) -> Result<()> {
+        let mut store = CollateralStore::open(&self.store)?;
+        for input in &self.input {
+            let entry = store
+                .import(&std::fs::read_to_string(input)?)
+                .map_err(|err| err.wrap_err(format!("Failed to import {}", input.display())))?;
+
+            let file = match &entry {
+                StoreEntry::TcbInfo(entry) => &entry.file,
+                StoreEntry::QeIdentity(entry) => &entry.file,
+            };
+            println!("{} -> {file}", input.display());
+        }
+
+        Ok(())
+    }
+}
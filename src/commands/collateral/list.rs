@@ -0,0 +1,37 @@
+use std::path::PathBuf;
+
+use clap::Parser;
+use eyre::Result;
+
+use crate::store::CollateralStore;
+
+#[derive(Debug, Parser)]
+pub struct ListCommand {
+    /// Path to the collateral store directory.
+    #[clap(long)]
+    store: PathBuf,
+}
+
+impl ListCommand {
+    pub fn run(self) -> Result<()> {
+        let store = CollateralStore::open(&self.store)?;
+
+        println!("tcbinfo:");
+        for entry in store.tcb_infos() {
+            println!(
+                "  {} FMSPC {} TCB evaluation data number {}: {}",
+                entry.tee_type, entry.fmspc, entry.tcb_evaluation_data_number, entry.file
+            );
+        }
+
+        println!("qeidentity:");
+        for entry in store.qe_identities() {
+            println!(
+                "  {} TCB evaluation data number {}: {}",
+                entry.id, entry.tcb_evaluation_data_number, entry.file
+            );
+        }
+
+        Ok(())
+    }
+}
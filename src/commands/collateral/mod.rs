@@ -0,0 +1,43 @@
+use clap::{Parser, Subcommand};
+use eyre::Result;
+
+mod fetch;
+use fetch::FetchCommand;
+
+mod import;
+use import::ImportCommand;
+
+mod list;
+use list::ListCommand;
+
+mod resolve;
+use resolve::ResolveCommand;
+
+#[derive(Debug, Parser)]
+pub struct Collateral {
+    #[clap(subcommand)]
+    command: Subcommands,
+}
+
+#[derive(Debug, Subcommand)]
+enum Subcommands {
+    /// Download the collateral of an FMSPC from PCS or a PCCS.
+    Fetch(FetchCommand),
+    /// Import tcbinfo and qeidentity JSON files into a collateral store.
+    Import(ImportCommand),
+    /// List the collateral of a store.
+    List(ListCommand),
+    /// Print the paths of the tcbinfo and qeidentity files matching a quote.
+    Resolve(ResolveCommand),
+}
+
+impl Collateral {
+    pub fn run(self) -> Result<()> {
+        match self.command {
+            Subcommands::Fetch(cmd) => cmd.run(),
+            Subcommands::Import(cmd) => cmd.run(),
+            Subcommands::List(cmd) => cmd.run(),
+            Subcommands::Resolve(cmd) => cmd.run(),
+        }
+    }
+}
@@ -0,0 +1,41 @@
+use std::path::PathBuf;
+
+use clap::Parser;
+use eyre::Result;
+
+use crate::{quote::Quote, store::CollateralStore};
+
+#[derive(Debug, Parser)]
+pub struct ResolveCommand {
+    /// Path to the collateral store directory.
+    #[clap(long)]
+    store: PathBuf,
+    /// Path to the quote file.
+    #[clap(long)]
+    quote: PathBuf,
+    /// TCB evaluation data number of the collateral. Defaults to the highest one in the store.
+    #[clap(long)]
+    tcb_evaluation_data_number: Option<u32>,
+}
+
+impl ResolveCommand {
+    pub fn run(self) -> Result<()> {
+        let store = CollateralStore::open(&self.store)?;
+        let quote = Quote::from_bytes(&std::fs::read(&self.quote)?)?;
+
+        println!(
+            "tcbinfo: {}",
+            store
+                .tcb_info_for_quote(&quote, self.tcb_evaluation_data_number)?
+                .display()
+        );
+        println!(
+            "qeidentity: {}",
+            store
+                .qe_identity_for_quote(&quote, self.tcb_evaluation_data_number)?
+                .display()
+        );
+
+        Ok(())
+    }
+}
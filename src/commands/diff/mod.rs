@@ -0,0 +1,49 @@
+use std::path::PathBuf;
+
+use clap::{Args, Parser, Subcommand};
+use eyre::Result;
+
+use super::preprocess::{qeidentity, tcbinfo};
+use crate::diff::{Diff, DiffFormat};
+
+#[derive(Debug, Parser)]
+pub struct DiffCommand {
+    #[clap(subcommand)]
+    command: Subcommands,
+}
+
+#[derive(Debug, Subcommand)]
+enum Subcommands {
+    /// Compare two tcbinfo JSON files.
+    Tcbinfo(DiffArgs),
+    /// Compare two qeidentity JSON files.
+    Qeidentity(DiffArgs),
+}
+
+#[derive(Debug, Args)]
+struct DiffArgs {
+    /// Path to the old JSON file.
+    old: PathBuf,
+    /// Path to the new JSON file.
+    new: PathBuf,
+    /// Output format of the changes.
+    #[clap(long, value_enum, default_value_t = DiffFormat::Text)]
+    format: DiffFormat,
+}
+
+impl DiffCommand {
+    pub fn run(self) -> Result<()> {
+        let (args, diff): (_, fn(&str, &str) -> Result<Diff>) = match &self.command {
+            Subcommands::Tcbinfo(args) => (args, tcbinfo::diff),
+            Subcommands::Qeidentity(args) => (args, qeidentity::diff),
+        };
+
+        let diff = diff(
+            &std::fs::read_to_string(&args.old)?,
+            &std::fs::read_to_string(&args.new)?,
+        )?;
+        print!("{}", diff.render(args.format)?);
+
+        Ok(())
+    }
+}
@@ -1,2 +1,14 @@
+mod bundle;
+pub use bundle::Bundle;
+
+mod collateral;
+pub use collateral::Collateral;
+
+mod codegen;
+pub use codegen::Codegen;
+
+mod diff;
+pub use diff::DiffCommand;
+
 mod preprocess;
 pub use preprocess::Preprocess;
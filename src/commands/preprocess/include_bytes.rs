@@ -1,27 +1,100 @@
-use std::path::PathBuf;
+use std::{io::Write, path::PathBuf};
 
 use clap::Parser;
 use eyre::Result;
 
-use crate::cairo::write_cairo_bytes;
+use crate::{
+    bundle::{BundleItem, read_inputs},
+    cairo::{CairoBytesOptions, const_names, write_cairo_encoded},
+};
 
 #[derive(Debug, Parser)]
 pub struct IncludeBytes {
-    /// Path to the input binary file.
-    #[clap(long)]
-    input: PathBuf,
+    /// Path to the input binary file. Can be repeated to define multiple items in one file.
+    #[clap(long, required_unless_present = "bundle")]
+    input: Vec<PathBuf>,
+    /// Path to an attestation bundle to read the `--item`s from instead of `--input`.
+    #[clap(long, conflicts_with = "input", requires = "item")]
+    bundle: Option<PathBuf>,
+    /// Bundle item to read. Can be repeated. Item names default to the item kinds, e.g.
+    /// `TCBINFO_ISSUER_CHAIN`.
+    #[clap(long, value_enum, requires = "bundle")]
+    item: Vec<BundleItem>,
     /// Path to the output Cairo file.
     #[clap(long)]
     output: PathBuf,
+    /// Name of the generated item, one per input. Defaults to the item kinds with `--bundle`, and
+    /// otherwise to `DATA` for a single input and to the file stems for multiple ones.
+    #[clap(long)]
+    name: Vec<String>,
+    #[clap(flatten)]
+    options: CairoBytesOptions,
 }
 
 impl IncludeBytes {
     pub fn run(self) -> Result<()> {
-        let raw_bytes = std::fs::read(&self.input)?;
+        let inputs = read_inputs(&self.input, self.bundle.as_deref(), &self.item)?;
+        let paths = inputs
+            .iter()
+            .map(|(path, _)| path.clone())
+            .collect::<Vec<_>>();
+        let item_names = self
+            .item
+            .iter()
+            .map(BundleItem::const_name)
+            .collect::<Vec<_>>();
+        let names = if self.name.is_empty() {
+            const_names(&paths, &item_names)?
+        } else {
+            const_names(&paths, &self.name)?
+        };
 
         let mut output_file = std::fs::File::create(&self.output)?;
-        write_cairo_bytes(&mut output_file, &raw_bytes)?;
+        for (ind, (name, (_, raw_bytes))) in names.iter().zip(inputs.iter()).enumerate() {
+            if ind > 0 {
+                writeln!(output_file)?;
+            }
+
+            write_cairo_encoded(&mut output_file, name, raw_bytes, &self.options)?;
+        }
 
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        bundle::{BUNDLE_VERSION, Bundle},
+        test_utils::run_command,
+    };
+
+    #[test]
+    fn bundle_items_are_named_after_their_kind() {
+        let dir = tempfile::tempdir().unwrap();
+        let bundle_path = dir.path().join("bundle.json");
+        let mut bundle = Bundle {
+            version: BUNDLE_VERSION,
+            ..Default::default()
+        };
+        bundle.insert(BundleItem::Tcbinfo, b"{}");
+        bundle.insert(BundleItem::PckCrl, b"crl");
+        bundle.write(&bundle_path).unwrap();
+
+        for (items, expected) in [
+            (&["tcbinfo"][..], &["TCBINFO"][..]),
+            (&["tcbinfo", "pck-crl"][..], &["TCBINFO", "PCK_CRL"][..]),
+        ] {
+            let mut args = vec!["--bundle", bundle_path.to_str().unwrap()];
+            for item in items {
+                args.extend(["--item", item]);
+            }
+            let cairo = run_command(&args, IncludeBytes::run).unwrap();
+            for name in expected {
+                assert!(cairo.contains(&format!("pub const {name}:")), "{cairo}");
+            }
+            assert!(!cairo.contains("DATA"), "{cairo}");
+        }
+    }
+}
@@ -1,7 +1,7 @@
 use clap::{Parser, Subcommand};
 use eyre::Result;
 
-mod quote;
+pub(crate) mod quote;
 use quote::QuoteCommand;
 
 mod pem;
@@ -10,12 +10,15 @@ use pem::PemCommand;
 mod include_bytes;
 use include_bytes::IncludeBytes;
 
-mod qeidentity;
+pub(crate) mod qeidentity;
 use qeidentity::QeidentityCommand;
 
-mod tcbinfo;
+pub(crate) mod tcbinfo;
 use tcbinfo::TcbinfoCommand;
 
+mod templates;
+use templates::TemplatesCommand;
+
 #[derive(Debug, Parser)]
 pub struct Preprocess {
     #[clap(subcommand)]
@@ -35,6 +38,9 @@ enum Subcommands {
     Qeidentity(QeidentityCommand),
     /// Pre-process tcbinfo JSON file to convert to Cairo struct definition.
     Tcbinfo(TcbinfoCommand),
+    /// Write the default Cairo code generation templates to a directory, as a starting point for
+    /// `--template-dir`.
+    Templates(TemplatesCommand),
 }
 
 impl Preprocess {
@@ -45,6 +51,7 @@ impl Preprocess {
             Subcommands::IncludeBytes(cmd) => cmd.run(),
             Subcommands::Qeidentity(cmd) => cmd.run(),
             Subcommands::Tcbinfo(cmd) => cmd.run(),
+            Subcommands::Templates(cmd) => cmd.run(),
         }
     }
 }
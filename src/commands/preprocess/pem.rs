@@ -1,39 +1,135 @@
-use std::path::PathBuf;
+use std::{
+    io::Write,
+    path::{Path, PathBuf},
+};
 
 use clap::Parser;
 use eyre::Result;
 use x509_parser::pem::Pem;
 
-use crate::cairo::write_cairo_bytes;
+use crate::{
+    bundle::{BundleItem, read_inputs},
+    cairo::{CairoBytesOptions, const_names, write_cairo_chain_fn, write_cairo_encoded},
+    chain::{chain_names, order_chain},
+};
 
 #[derive(Debug, Parser)]
 pub struct PemCommand {
-    /// Path to the PEM file.
-    #[clap(long)]
-    input: PathBuf,
+    /// Path to the PEM file, either a single PEM block or a certificate chain. Can be repeated to
+    /// define multiple items in one file.
+    #[clap(long, required_unless_present = "bundle")]
+    input: Vec<PathBuf>,
+    /// Path to an attestation bundle to read the `--item`s from instead of `--input`.
+    #[clap(long, conflicts_with = "input", requires = "item")]
+    bundle: Option<PathBuf>,
+    /// Bundle item to read. Can be repeated. Item names default to the item kinds, e.g.
+    /// `TCBINFO_ISSUER_CHAIN`, which prefixes the names of chain certificates.
+    #[clap(long, value_enum, requires = "bundle")]
+    item: Vec<BundleItem>,
     /// Path to the output Cairo file.
     #[clap(long)]
     output: PathBuf,
+    /// Name of the generated item, one per input. Certificate chains use it as a prefix of the
+    /// `LEAF`, `INTERMEDIATE` and `ROOT` items.
+    #[clap(long)]
+    name: Vec<String>,
+    #[clap(flatten)]
+    options: CairoBytesOptions,
+}
+
+enum PemContents {
+    Single(Vec<u8>),
+    Chain(Vec<Vec<u8>>),
 }
 
 impl PemCommand {
     pub fn run(self) -> Result<()> {
-        let raw_bytes = std::fs::read(&self.input)?;
-        let mut pem_iter = Pem::iter_from_buffer(&raw_bytes);
-        let pem = match pem_iter.next() {
-            Some(Ok(pem)) => pem,
-            Some(Err(err)) => eyre::bail!("Failed to parse PEM file: {err}"),
-            None => eyre::bail!("Empty PEM file"),
+        let inputs = read_inputs(&self.input, self.bundle.as_deref(), &self.item)?;
+        let paths = inputs
+            .iter()
+            .map(|(path, _)| path.clone())
+            .collect::<Vec<_>>();
+        let item_names = self
+            .item
+            .iter()
+            .map(BundleItem::const_name)
+            .collect::<Vec<_>>();
+        let names = if self.name.is_empty() {
+            const_names(&paths, &item_names)?
+        } else {
+            const_names(&paths, &self.name)?
         };
 
-        // Cert-chain not supported for now
-        if pem_iter.next().is_some() {
-            eyre::bail!("This command can only be used for a single PEM-certificate");
+        // Chains keep their bare names unless named explicitly, after their bundle item, or for
+        // disambiguation
+        let prefix_chains = !self.name.is_empty() || !self.item.is_empty() || inputs.len() > 1;
+
+        let mut contents = vec![];
+        for (path, raw_bytes) in &inputs {
+            contents.push(read_pem(path, raw_bytes)?);
         }
 
         let mut output_file = std::fs::File::create(&self.output)?;
-        write_cairo_bytes(&mut output_file, &pem.contents)?;
+        for (ind, (name, content)) in names.iter().zip(contents.iter()).enumerate() {
+            if ind > 0 {
+                writeln!(output_file)?;
+            }
+
+            match content {
+                PemContents::Single(der) => {
+                    write_cairo_encoded(&mut output_file, name, der, &self.options)?;
+                }
+                PemContents::Chain(certs) => {
+                    let (cert_names, fn_name) = if prefix_chains {
+                        (
+                            chain_names(certs.len())
+                                .into_iter()
+                                .map(|cert_name| format!("{name}_{cert_name}"))
+                                .collect::<Vec<_>>(),
+                            format!("{}_chain", name.to_lowercase()),
+                        )
+                    } else {
+                        (chain_names(certs.len()), "chain".to_owned())
+                    };
+
+                    for (cert_name, cert) in cert_names.iter().zip(certs.iter()) {
+                        write_cairo_encoded(&mut output_file, cert_name, cert, &self.options)?;
+                        writeln!(output_file)?;
+                    }
+
+                    write_cairo_chain_fn(&mut output_file, &fn_name, &cert_names, &self.options)?;
+                }
+            }
+        }
 
         Ok(())
     }
 }
+
+fn read_pem(input: &Path, raw_bytes: &[u8]) -> Result<PemContents> {
+    let mut pems = vec![];
+    for pem in Pem::iter_from_buffer(raw_bytes) {
+        match pem {
+            Ok(pem) => pems.push(pem),
+            Err(err) => eyre::bail!("Failed to parse PEM file {}: {err}", input.display()),
+        }
+    }
+
+    match pems.len() {
+        0 => eyre::bail!("Empty PEM file: {}", input.display()),
+        1 => Ok(PemContents::Single(pems.remove(0).contents)),
+        _ => {
+            // Multiple PEM blocks are only accepted as a certificate chain
+            let mut certs = vec![];
+            for pem in pems {
+                if pem.label != "CERTIFICATE" {
+                    eyre::bail!("Unexpected PEM label in chain: {}", pem.label);
+                }
+
+                certs.push(pem.contents);
+            }
+
+            Ok(PemContents::Chain(order_chain(certs)?))
+        }
+    }
+}
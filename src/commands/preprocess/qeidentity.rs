@@ -1,29 +1,109 @@
-use std::fmt::Write;
 use std::path::PathBuf;
 
 use clap::Parser;
 use eyre::Result;
 use serde::{Deserialize, Serialize};
 
+use crate::{
+    bundle::{Bundle, BundleItem, read_input},
+    cairo::Encoding,
+    cairo_types::{CairoTypes, CairoTypesArgs},
+    diff::Diff,
+    emitter::{
+        DateFormat, Expr, Function, Let, StringFormat, TCB_STATUS_TYPE, TcbStatusArgs,
+        TcbStatusFormat, TypeDef, date_type, render_use, tcb_date_lets, tcb_date_var,
+    },
+    freshness::{FreshnessArgs, Validity},
+    quote::{EnclaveId, Quote},
+    schema::{check_hex, parse_json},
+    signed_body::SignedBody,
+    store::CollateralStore,
+    template::{Generated, TemplateArgs, TemplateKind},
+    verify::VerifyArgs,
+};
+
+/// Module the generated types are imported from unless overridden.
+pub const TYPES_MODULE: &str = "crate::types::enclave_identity";
+
+pub const TYPE_NAMES: [&str; 5] = [
+    "EnclaveIdentityV2",
+    "EnclaveIdentityV2Inner",
+    "EnclaveIdentityV2TcbLevel",
+    "EnclaveIdentityV2TcbLevelItem",
+    TCB_STATUS_TYPE,
+];
+
 #[derive(Debug, Parser)]
 pub struct QeidentityCommand {
-    /// Path to the input JSON file.
-    #[clap(long)]
-    input: PathBuf,
+    /// Path to the input JSON file. Required unless reading from `--bundle` or `--store`.
+    #[clap(long, required_unless_present_any = ["bundle", "store"])]
+    input: Option<PathBuf>,
+    /// Path to an attestation bundle to read the qeidentity from instead of `--input`, along with
+    /// its issuer chain unless given with `--issuer-chain`.
+    #[clap(long, conflicts_with = "input")]
+    bundle: Option<PathBuf>,
+    /// Path to a collateral store to pick the qeidentity matching `--quote` from instead of `--input`.
+    #[clap(long, conflicts_with_all = ["input", "bundle"], requires = "quote")]
+    store: Option<PathBuf>,
+    /// TCB evaluation data number of the qeidentity picked from `--store`. Defaults to the highest one.
+    #[clap(long, requires = "store")]
+    tcb_evaluation_data_number: Option<u32>,
     /// Path to the output Cairo file.
     #[clap(long)]
     output: PathBuf,
+    /// Path to the quote the identity will be used with. A warning is printed if the identity is
+    /// for the quoting enclave of another TEE type. With `--store`, the identity of the quote's
+    /// quoting enclave is picked.
+    #[clap(long)]
+    quote: Option<PathBuf>,
+    /// Cairo representation of issue, next update and TCB dates.
+    #[clap(long, value_enum, default_value_t = DateFormat::OffsetDateTime)]
+    date_format: DateFormat,
+    /// Cairo representation of the ID, TCB statuses and advisory IDs.
+    #[clap(long, value_enum, default_value_t = StringFormat::ByteArray)]
+    string_format: StringFormat,
+    #[clap(flatten)]
+    tcb_status: TcbStatusArgs,
+    /// Also emit the byte-exact signed `enclaveIdentity` object, or `qeIdentity` for v1, as
+    /// `SIGNED_BODY`, along with its SHA-256 digest and the `r` and `s` components of its
+    /// signature.
+    #[clap(long)]
+    signed_body: bool,
+    /// Cairo representation of the signed body. Only used with `--signed-body`.
+    #[clap(long, value_enum, default_value_t = Encoding::U8)]
+    signed_body_encoding: Encoding,
+    #[clap(flatten)]
+    verify: VerifyArgs,
+    #[clap(flatten)]
+    freshness: FreshnessArgs,
+    #[clap(flatten)]
+    types: CairoTypesArgs,
+    #[clap(flatten)]
+    template: TemplateArgs,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
-#[serde(rename_all = "camelCase")]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
 struct QeIdentityJson {
     enclave_identity: EnclaveIdentityInnerJson,
     signature: String,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+/// Just enough of any enclave identity version to pick the model to parse it with.
+#[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
+struct QeIdentityVersionJson {
+    enclave_identity: Option<QeIdentityVersionInnerJson>,
+    qe_identity: Option<QeIdentityVersionInnerJson>,
+}
+
+#[derive(Debug, Deserialize)]
+struct QeIdentityVersionInnerJson {
+    version: u32,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
 struct EnclaveIdentityInnerJson {
     id: String,
     version: u32,
@@ -40,380 +120,779 @@ struct EnclaveIdentityInnerJson {
 }
 
 #[derive(Debug, Deserialize, Serialize)]
-#[serde(rename_all = "camelCase")]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
 struct TcbLevelJson {
     tcb: TcbJson,
     tcb_date: String,
     tcb_status: String,
-    #[serde(skip_serializing_if = "Option::is_none")]
+    #[serde(rename = "advisoryIDs", skip_serializing_if = "Option::is_none")]
     advisory_ids: Option<Vec<String>>,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
-#[serde(rename_all = "camelCase")]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
 struct TcbJson {
     isvsvn: u16,
 }
 
-impl QeidentityCommand {
-    pub fn run(self) -> Result<()> {
-        let json_content = std::fs::read_to_string(&self.input)?;
-        let qe_identity: QeIdentityJson = serde_json::from_str(&json_content)?;
-
-        let mut output = String::new();
+/// QE identity v1, signed as a `qeIdentity` object with a single ISV SVN instead of TCB levels.
+/// Normalized to the v2 model before generating code.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+struct QeIdentityV1Json {
+    qe_identity: QeIdentityV1InnerJson,
+    signature: String,
+}
 
-        // Add imports
-        output.push_str("use time::{DateTrait, Month, OffsetDateTimeTrait, TimeTrait};\n");
-        output.push_str("use crate::types::enclave_identity::{\n");
-        output.push_str(
-            "    EnclaveIdentityV2, EnclaveIdentityV2Inner, EnclaveIdentityV2TcbLevel,\n",
-        );
-        output.push_str("    EnclaveIdentityV2TcbLevelItem,\n");
-        output.push_str("};\n\n");
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+struct QeIdentityV1InnerJson {
+    version: u32,
+    issue_date: String,
+    next_update: String,
+    miscselect: String,
+    miscselect_mask: String,
+    attributes: String,
+    attributes_mask: String,
+    mrsigner: String,
+    isvprodid: u16,
+    isvsvn: u16,
+}
 
-        output.push_str("pub fn data() -> EnclaveIdentityV2 {\n");
+impl From<QeIdentityV1Json> for QeIdentityJson {
+    fn from(v1: QeIdentityV1Json) -> Self {
+        let inner = v1.qe_identity;
+        Self {
+            enclave_identity: EnclaveIdentityInnerJson {
+                // v1 only covers the SGX QE and predates TCB evaluation data numbers
+                id: "QE".to_owned(),
+                version: inner.version,
+                tcb_evaluation_data_number: 0,
+                // Any ISV SVN at least as high as the single one of v1 is up to date
+                tcb_levels: vec![TcbLevelJson {
+                    tcb: TcbJson {
+                        isvsvn: inner.isvsvn,
+                    },
+                    tcb_date: inner.issue_date.clone(),
+                    tcb_status: "UpToDate".to_owned(),
+                    advisory_ids: None,
+                }],
+                issue_date: inner.issue_date,
+                next_update: inner.next_update,
+                miscselect: inner.miscselect,
+                miscselect_mask: inner.miscselect_mask,
+                attributes: inner.attributes,
+                attributes_mask: inner.attributes_mask,
+                mrsigner: inner.mrsigner,
+                isvprodid: inner.isvprodid,
+            },
+            signature: v1.signature,
+        }
+    }
+}
 
-        // Parse and generate issue_date
-        let issue_date = parse_datetime(&qe_identity.enclave_identity.issue_date)?;
-        writeln!(
-            &mut output,
-            "    // {}",
-            qe_identity.enclave_identity.issue_date
-        )?;
-        output.push_str("    let issue_date = OffsetDateTimeTrait::new_utc(\n");
-        writeln!(
-            &mut output,
-            "        DateTrait::from_calendar_date({}, Month::{}, {}).unwrap(),",
-            issue_date.year,
-            month_name(issue_date.month),
-            issue_date.day
-        )?;
-        writeln!(
-            &mut output,
-            "        TimeTrait::from_hms_milli({}, {}, {}, {}).unwrap(),",
-            issue_date.hour, issue_date.minute, issue_date.second, issue_date.millisecond
-        )?;
-        output.push_str("    );\n\n");
-
-        // Parse and generate next_update
-        let next_update = parse_datetime(&qe_identity.enclave_identity.next_update)?;
-        writeln!(
-            &mut output,
-            "    // {}",
-            qe_identity.enclave_identity.next_update
-        )?;
-        output.push_str("    let next_update = OffsetDateTimeTrait::new_utc(\n");
-        writeln!(
-            &mut output,
-            "        DateTrait::from_calendar_date({}, Month::{}, {}).unwrap(),",
-            next_update.year,
-            month_name(next_update.month),
-            next_update.day
-        )?;
-        writeln!(
-            &mut output,
-            "        TimeTrait::from_hms_milli({}, {}, {}, {}).unwrap(),",
-            next_update.hour, next_update.minute, next_update.second, next_update.millisecond
-        )?;
-        output.push_str("    );\n\n");
-
-        // Parse and generate tcb_date if there are tcb_levels
-        if !qe_identity.enclave_identity.tcb_levels.is_empty() {
-            let tcb_date = parse_datetime(&qe_identity.enclave_identity.tcb_levels[0].tcb_date)?;
-            writeln!(
-                &mut output,
-                "    // {}",
-                qe_identity.enclave_identity.tcb_levels[0].tcb_date
-            )?;
-            output.push_str("    let tcb_date = OffsetDateTimeTrait::new_utc(\n");
-            writeln!(
-                &mut output,
-                "        DateTrait::from_calendar_date({}, Month::{}, {}).unwrap(),",
-                tcb_date.year,
-                month_name(tcb_date.month),
-                tcb_date.day
-            )?;
-            writeln!(
-                &mut output,
-                "        TimeTrait::from_hms_milli({}, {}, {}, {}).unwrap(),",
-                tcb_date.hour, tcb_date.minute, tcb_date.second, tcb_date.millisecond
-            )?;
-            output.push_str("    );\n\n");
+impl QeIdentityJson {
+    /// Checks the length of every hex-encoded field, located under `signed_key`.
+    fn check_lengths(&self, signed_key: &str) -> Result<()> {
+        let identity = &self.enclave_identity;
+        for (field, value, len) in [
+            ("miscselect", &identity.miscselect, 4),
+            ("miscselectMask", &identity.miscselect_mask, 4),
+            ("attributes", &identity.attributes, 16),
+            ("attributesMask", &identity.attributes_mask, 16),
+            ("mrsigner", &identity.mrsigner, 32),
+        ] {
+            check_hex(&format!("{signed_key}.{field}"), value, len)?;
         }
 
-        // Start generating the struct
-        output.push_str("    EnclaveIdentityV2 {\n");
-        output.push_str("        enclave_identity: EnclaveIdentityV2Inner {\n");
-        writeln!(
-            &mut output,
-            "            id: \"{}\",",
-            qe_identity.enclave_identity.id
-        )?;
-        writeln!(
-            &mut output,
-            "            version: {},",
-            qe_identity.enclave_identity.version
-        )?;
-        output.push_str("            issue_date,\n");
-        output.push_str("            next_update,\n");
-        writeln!(
-            &mut output,
-            "            tcb_evaluation_data_number: {},",
-            qe_identity.enclave_identity.tcb_evaluation_data_number
-        )?;
+        check_hex("signature", &self.signature, 64)
+    }
+}
 
-        // miscselect
-        let miscselect_bytes = hex::decode(&qe_identity.enclave_identity.miscselect)?;
-        output.push_str("            miscselect: array![");
-        output.push_str(&format_bytes_inline(&miscselect_bytes));
-        output.push_str("].span(),\n");
-
-        // miscselect_mask
-        let miscselect_mask_bytes = hex::decode(&qe_identity.enclave_identity.miscselect_mask)?;
-        output.push_str("            miscselect_mask: array![");
-        output.push_str(&format_bytes_from_hex_string(
-            &qe_identity.enclave_identity.miscselect_mask,
-            &miscselect_mask_bytes,
-            false,
-        ));
-        output.push_str("].span(),\n");
-
-        // attributes
-        let attributes_bytes = hex::decode(&qe_identity.enclave_identity.attributes)?;
-        output.push_str("            attributes: array![");
-        output.push_str(&format_bytes_multiline(
-            &attributes_bytes,
-            16,
-            "                ",
-        ));
-        output.push_str("].span(),\n");
-
-        // attributes_mask
-        let attributes_mask_bytes = hex::decode(&qe_identity.enclave_identity.attributes_mask)?;
-        output.push_str("            attributes_mask: array![");
-        output.push_str(&format_bytes_from_hex_string_multiline(
-            &qe_identity.enclave_identity.attributes_mask,
-            &attributes_mask_bytes,
-            16,
-            "                ",
-        ));
-        output.push_str("].span(),\n");
-
-        // mrsigner
-        let mrsigner_bytes = hex::decode(&qe_identity.enclave_identity.mrsigner)?;
-        output.push_str("            mrsigner: array![");
-        output.push_str(&format_bytes_from_hex_string_multiline(
-            &qe_identity.enclave_identity.mrsigner,
-            &mrsigner_bytes,
-            16,
-            "                ",
-        ));
-        output.push_str("].span(),\n");
-
-        writeln!(
-            &mut output,
-            "            isvprodid: {},",
-            qe_identity.enclave_identity.isvprodid
-        )?;
+/// Parses an enclave identity of any supported version into the v2 model, along with the name of
+/// its signed top-level field.
+fn parse_qe_identity(json: &str) -> Result<(QeIdentityJson, &'static str)> {
+    let probe: QeIdentityVersionJson = parse_json(json)?;
+    let (qe_identity, signed_key): (QeIdentityJson, _) =
+        match (probe.enclave_identity, probe.qe_identity) {
+            (Some(inner), None) if inner.version == 2 => (parse_json(json)?, "enclaveIdentity"),
+            (None, Some(inner)) if inner.version == 1 => {
+                (parse_json::<QeIdentityV1Json>(json)?.into(), "qeIdentity")
+            }
+            (Some(inner), None) | (None, Some(inner)) => {
+                eyre::bail!("Unsupported enclave identity version: {}", inner.version)
+            }
+            _ => eyre::bail!("Expected exactly one of enclaveIdentity and qeIdentity"),
+        };
+    qe_identity.check_lengths(signed_key)?;
 
-        // tcb_levels
-        output.push_str("            tcb_levels: array![\n");
-        for tcb_level in &qe_identity.enclave_identity.tcb_levels {
-            output.push_str("                EnclaveIdentityV2TcbLevelItem {\n");
-            writeln!(
-                &mut output,
-                "                    tcb: EnclaveIdentityV2TcbLevel {{ isvsvn: {} }},",
-                tcb_level.tcb.isvsvn
-            )?;
-            output.push_str("                    tcb_date,\n");
-            writeln!(
-                &mut output,
-                "                    tcb_status: \"{}\",",
-                tcb_level.tcb_status
+    Ok((qe_identity, signed_key))
+}
+
+/// Signed body of an enclave identity document of any supported version, after validating it.
+pub fn signed_body(json: &str) -> Result<SignedBody> {
+    let (qe_identity, signed_key) = parse_qe_identity(json)?;
+    SignedBody::extract(json, signed_key, &qe_identity.signature)
+}
+
+/// Semantic changes between two enclave identity documents of any supported version.
+pub fn diff(old_json: &str, new_json: &str) -> Result<Diff> {
+    let old = parse_qe_identity(old_json)?.0.enclave_identity;
+    let new = parse_qe_identity(new_json)?.0.enclave_identity;
+
+    let mut diff = Diff::default();
+    diff.value("enclaveIdentity.id", &old.id, &new.id)?;
+    diff.value("enclaveIdentity.version", &old.version, &new.version)?;
+    diff.date(
+        "enclaveIdentity.issueDate",
+        &old.issue_date,
+        &new.issue_date,
+    )?;
+    diff.date(
+        "enclaveIdentity.nextUpdate",
+        &old.next_update,
+        &new.next_update,
+    )?;
+    diff.value(
+        "enclaveIdentity.tcbEvaluationDataNumber",
+        &old.tcb_evaluation_data_number,
+        &new.tcb_evaluation_data_number,
+    )?;
+    for (path, old_value, new_value) in [
+        (
+            "enclaveIdentity.miscselect",
+            &old.miscselect,
+            &new.miscselect,
+        ),
+        (
+            "enclaveIdentity.miscselectMask",
+            &old.miscselect_mask,
+            &new.miscselect_mask,
+        ),
+        (
+            "enclaveIdentity.attributes",
+            &old.attributes,
+            &new.attributes,
+        ),
+        (
+            "enclaveIdentity.attributesMask",
+            &old.attributes_mask,
+            &new.attributes_mask,
+        ),
+        ("enclaveIdentity.mrsigner", &old.mrsigner, &new.mrsigner),
+    ] {
+        diff.value(path, &old_value.to_lowercase(), &new_value.to_lowercase())?;
+    }
+    diff.value("enclaveIdentity.isvprodid", &old.isvprodid, &new.isvprodid)?;
+
+    diff.keyed(
+        "enclaveIdentity.tcbLevels",
+        &old.tcb_levels,
+        &new.tcb_levels,
+        |tcb_level| format!("isvsvn={}", tcb_level.tcb.isvsvn),
+        |diff, path, old, new| {
+            diff.date(&format!("{path}.tcbDate"), &old.tcb_date, &new.tcb_date)?;
+            diff.value(
+                &format!("{path}.tcbStatus"),
+                &old.tcb_status,
+                &new.tcb_status,
             )?;
+            diff.set(
+                &format!("{path}.advisoryIDs"),
+                old.advisory_ids.as_deref().unwrap_or_default(),
+                new.advisory_ids.as_deref().unwrap_or_default(),
+            );
 
-            if let Some(advisory_ids) = &tcb_level.advisory_ids {
-                output.push_str("                    advisory_ids: Option::Some(array![");
-                for (i, id) in advisory_ids.iter().enumerate() {
-                    if i > 0 {
-                        output.push_str(", ");
-                    }
-                    write!(&mut output, "\"{}\"", id)?;
-                }
-                output.push_str("].span()),\n");
-            } else {
-                output.push_str("                    advisory_ids: Option::None,\n");
-            }
-            output.push_str("                },\n");
+            Ok(())
+        },
+    )?;
+
+    Ok(diff)
+}
+
+/// Cairo counterparts of the JSON models above, matching the generated data.
+pub fn type_defs(
+    types: &CairoTypes,
+    date_format: DateFormat,
+    string_format: StringFormat,
+    tcb_status_format: TcbStatusFormat,
+) -> Vec<TypeDef> {
+    let date = date_format.cairo_type().to_owned();
+    let bytes = || "Span<u8>".to_owned();
+    let string = || string_format.cairo_type().to_owned();
+
+    let mut type_defs = vec![
+        TypeDef::structure(
+            types.name("EnclaveIdentityV2"),
+            vec![
+                ("enclave_identity", types.name("EnclaveIdentityV2Inner")),
+                ("signature", bytes()),
+            ],
+        ),
+        TypeDef::structure(
+            types.name("EnclaveIdentityV2Inner"),
+            vec![
+                ("id", string()),
+                ("version", "u32".to_owned()),
+                ("issue_date", date.clone()),
+                ("next_update", date.clone()),
+                ("tcb_evaluation_data_number", "u32".to_owned()),
+                ("miscselect", bytes()),
+                ("miscselect_mask", bytes()),
+                ("attributes", bytes()),
+                ("attributes_mask", bytes()),
+                ("mrsigner", bytes()),
+                ("isvprodid", "u16".to_owned()),
+                (
+                    "tcb_levels",
+                    format!("Span<{}>", types.name("EnclaveIdentityV2TcbLevelItem")),
+                ),
+            ],
+        ),
+        TypeDef::structure(
+            types.name("EnclaveIdentityV2TcbLevelItem"),
+            vec![
+                ("tcb", types.name("EnclaveIdentityV2TcbLevel")),
+                ("tcb_date", date),
+                (
+                    "tcb_status",
+                    tcb_status_format.cairo_type(string_format, &types.name(TCB_STATUS_TYPE)),
+                ),
+                ("advisory_ids", format!("Option<Span<{}>>", string())),
+            ],
+        ),
+        TypeDef::structure(
+            types.name("EnclaveIdentityV2TcbLevel"),
+            vec![("isvsvn", "u16".to_owned())],
+        ),
+    ];
+    if tcb_status_format == TcbStatusFormat::Enum {
+        type_defs.push(TypeDef::tcb_status(types.name(TCB_STATUS_TYPE)));
+    }
+    if !date_format.implements_serde() {
+        TypeDef::drop_serde(&mut type_defs, date_format.cairo_type());
+    }
+
+    type_defs
+}
+
+impl QeidentityCommand {
+    pub fn run(self) -> Result<()> {
+        let types = self
+            .types
+            .resolve("qeidentity", TYPES_MODULE, &TYPE_NAMES)?;
+
+        let bundle = self.bundle.as_deref().map(Bundle::read).transpose()?;
+        let quote = match &self.quote {
+            Some(quote) => Some(Quote::from_bytes(&std::fs::read(quote)?)?),
+            None => None,
+        };
+        let json_content = match (&self.store, &quote) {
+            (Some(store), Some(quote)) => std::fs::read_to_string(
+                CollateralStore::open(store)?
+                    .qe_identity_for_quote(quote, self.tcb_evaluation_data_number)?,
+            )?,
+            _ => match &bundle {
+                Some(bundle) => String::from_utf8(bundle.get(BundleItem::Qeidentity)?)?,
+                None => String::from_utf8(read_input(
+                    self.input.as_deref(),
+                    None,
+                    BundleItem::Qeidentity,
+                )?)?,
+            },
+        };
+        let (qe_identity, signed_key) = parse_qe_identity(&json_content)?;
+        let identity = &qe_identity.enclave_identity;
+        let enclave_id = EnclaveId::parse(&identity.id)?;
+        if let Some(quote) = &quote {
+            check_quote_enclave(enclave_id, quote);
         }
-        output.push_str("            ].span(),\n");
 
-        output.push_str("        },\n");
+        let signed_body = SignedBody::extract(&json_content, signed_key, &qe_identity.signature)?;
+        self.verify.verify(
+            &signed_body,
+            bundle.as_ref(),
+            BundleItem::QeidentityIssuerChain,
+        )?;
 
-        // signature
-        let signature_bytes = hex::decode(&qe_identity.signature)?;
-        output.push_str("        signature: array![");
-        output.push_str(&format_bytes_from_hex_string_multiline(
-            &qe_identity.signature,
-            &signature_bytes,
-            16,
-            "            ",
-        ));
-        output.push_str("].span(),\n");
+        let mut validities = vec![Validity::collateral(
+            "Enclave identity",
+            &identity.issue_date,
+            &identity.next_update,
+        )?];
+        if let Some(issuer_chain) = self
+            .verify
+            .issuer_chain(bundle.as_ref(), BundleItem::QeidentityIssuerChain)?
+        {
+            validities.extend(Validity::certificates("Issuer chain", &issuer_chain)?);
+        }
+        self.freshness.check(&validities)?;
 
-        output.push_str("    }\n");
-        output.push_str("}\n");
+        let tcb_status_type = types.name(TCB_STATUS_TYPE);
+        let mut imports = TYPE_NAMES.to_vec();
+        if !self.tcb_status.imports_enum() {
+            imports.retain(|name| *name != TCB_STATUS_TYPE);
+        }
 
+        let mut lets = vec![
+            Let::datetime("issue_date", &identity.issue_date, self.date_format)?,
+            Let::datetime("next_update", &identity.next_update, self.date_format)?,
+        ];
+        lets.extend(tcb_date_lets(
+            identity
+                .tcb_levels
+                .iter()
+                .map(|tcb_level| tcb_level.tcb_date.as_str()),
+            self.date_format,
+        )?);
+
+        let data = self.data(&types, &qe_identity)?;
+
+        let generated = Generated {
+            date_imports: self.date_format.imports().unwrap_or_default().to_owned(),
+            type_imports: render_use(types.module(), &types.imports(&imports)),
+            date_let_type: date_type(self.date_format),
+            type_defs: self
+                .tcb_status
+                .type_def(&tcb_status_type)
+                .map(|type_def| type_def.render() + "\n")
+                .unwrap_or_default(),
+            data: Function {
+                name: "data".to_owned(),
+                ret: types.name("EnclaveIdentityV2"),
+                lets,
+                body: data,
+            }
+            .render(),
+            signed_body: if self.signed_body {
+                signed_body.render(self.signed_body_encoding)?
+            } else {
+                String::new()
+            },
+            types_module: types.module().to_owned(),
+            types: types.names(&TYPE_NAMES),
+        };
+
+        let output = self.template.render(
+            TemplateKind::Qeidentity,
+            &qe_identity,
+            generated,
+            self.date_format,
+            self.string_format,
+            self.tcb_status.tcb_status_format,
+        )?;
         std::fs::write(&self.output, output)?;
 
         Ok(())
     }
+
+    /// Returns the identity as a struct literal, referencing the dates bound by `tcb_date_lets`.
+    fn data(&self, types: &CairoTypes, qe_identity: &QeIdentityJson) -> Result<Expr> {
+        let identity = &qe_identity.enclave_identity;
+        let tcb_status_type = types.name(TCB_STATUS_TYPE);
+
+        let mut tcb_levels = vec![];
+        for tcb_level in &identity.tcb_levels {
+            tcb_levels.push(Expr::structure(
+                types.name("EnclaveIdentityV2TcbLevelItem"),
+                vec![
+                    (
+                        "tcb",
+                        Expr::structure(
+                            types.name("EnclaveIdentityV2TcbLevel"),
+                            vec![("isvsvn", Expr::int(tcb_level.tcb.isvsvn))],
+                        ),
+                    ),
+                    ("tcb_date", tcb_date_var(&tcb_level.tcb_date)?),
+                    (
+                        "tcb_status",
+                        Expr::tcb_status(
+                            &tcb_level.tcb_status,
+                            self.tcb_status.tcb_status_format,
+                            self.string_format,
+                            &tcb_status_type,
+                        )?,
+                    ),
+                    (
+                        "advisory_ids",
+                        match &tcb_level.advisory_ids {
+                            Some(advisory_ids) => Expr::some(
+                                Expr::array(
+                                    advisory_ids
+                                        .iter()
+                                        .map(|id| Expr::string(id, self.string_format))
+                                        .collect::<Result<_>>()?,
+                                )
+                                .span(),
+                            ),
+                            None => Expr::none(),
+                        },
+                    ),
+                ],
+            ));
+        }
+
+        Ok(Expr::structure(
+            types.name("EnclaveIdentityV2"),
+            vec![
+                (
+                    "enclave_identity",
+                    Expr::structure(
+                        types.name("EnclaveIdentityV2Inner"),
+                        vec![
+                            ("id", Expr::string(&identity.id, self.string_format)?),
+                            ("version", Expr::int(identity.version)),
+                            ("issue_date", Expr::raw("issue_date")),
+                            ("next_update", Expr::raw("next_update")),
+                            (
+                                "tcb_evaluation_data_number",
+                                Expr::int(identity.tcb_evaluation_data_number),
+                            ),
+                            (
+                                "miscselect",
+                                Expr::bytes(&hex::decode(&identity.miscselect)?).span(),
+                            ),
+                            (
+                                "miscselect_mask",
+                                Expr::bytes(&hex::decode(&identity.miscselect_mask)?).span(),
+                            ),
+                            (
+                                "attributes",
+                                Expr::bytes(&hex::decode(&identity.attributes)?).span(),
+                            ),
+                            (
+                                "attributes_mask",
+                                Expr::bytes(&hex::decode(&identity.attributes_mask)?).span(),
+                            ),
+                            (
+                                "mrsigner",
+                                Expr::bytes(&hex::decode(&identity.mrsigner)?).span(),
+                            ),
+                            ("isvprodid", Expr::int(identity.isvprodid)),
+                            ("tcb_levels", Expr::array(tcb_levels).span()),
+                        ],
+                    ),
+                ),
+                (
+                    "signature",
+                    Expr::bytes(&hex::decode(&qe_identity.signature)?).span(),
+                ),
+            ],
+        ))
+    }
 }
 
-fn format_bytes_inline(bytes: &[u8]) -> String {
-    bytes
-        .iter()
-        .map(|b| format!("0x{:02X}", b))
-        .collect::<Vec<_>>()
-        .join(", ")
+/// Warns if an identity of `enclave_id` is for the quoting enclave of another TEE type than
+/// `quote`.
+fn check_quote_enclave(enclave_id: EnclaveId, quote: &Quote) {
+    if let Some(tee_type) = enclave_id.tee_type()
+        && tee_type != quote.header.tee_type
+    {
+        eprintln!(
+            "Warning: {} identity used with a quote of TEE type {}",
+            enclave_id.name(),
+            quote.header.tee_type.name()
+        );
+    }
 }
 
-fn format_bytes_multiline(bytes: &[u8], per_line: usize, indent: &str) -> String {
-    let mut result = String::new();
-    for (i, chunk) in bytes.chunks(per_line).enumerate() {
-        if i > 0 {
-            result.push(',');
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        diff::DiffFormat,
+        test_utils::{assert_matches_type_defs, qe_identity_json, run_preprocess},
+    };
+
+    /// Runs `qeidentity` on `json` with `args`, returning the generated Cairo. The signature of
+    /// `json` is not verified.
+    fn generate(json: &str, args: &[&str]) -> Result<String> {
+        let args = [&["--allow-unverified"], args].concat();
+        run_preprocess(json.as_bytes(), &args, QeidentityCommand::run)
+    }
+
+    #[test]
+    fn levels_get_their_own_tcb_date() {
+        let json = qe_identity_json(
+            None,
+            &[
+                (8, "2024-03-13T00:00:00Z", "UpToDate"),
+                (6, "2024-03-13T00:00:00Z", "OutOfDate"),
+                (5, "2018-01-04T00:00:00Z", "OutOfDate"),
+            ],
+        );
+        let cairo = generate(&json, &[]).unwrap();
+
+        assert_eq!(cairo.matches("let tcb_date_2024_03_13 =").count(), 1);
+        assert_eq!(cairo.matches("let tcb_date_2018_01_04 =").count(), 1);
+        assert_eq!(cairo.matches("tcb_date: tcb_date_2024_03_13,").count(), 2);
+        assert_eq!(cairo.matches("tcb_date: tcb_date_2018_01_04,").count(), 1);
+        assert!(
+            cairo.find("let tcb_date_2024_03_13 =").unwrap()
+                < cairo.find("let tcb_date_2018_01_04 =").unwrap()
+        );
+        assert!(!cairo.contains("tcb_date,"));
+    }
+
+    #[test]
+    fn emits_tcb_statuses_as_enum_variants() {
+        let json = qe_identity_json(None, &[(8, "2024-03-13T00:00:00Z", "UpToDate")]);
+
+        let cairo = generate(&json, &["--tcb-status-format", "enum"]).unwrap();
+        assert!(cairo.contains("    EnclaveIdentityV2TcbLevelItem, TcbStatus,\n};\n"));
+        assert!(cairo.contains("tcb_status: TcbStatus::UpToDate,"));
+        assert!(!cairo.contains("enum TcbStatus"));
+
+        let args = ["--tcb-status-format", "enum", "--define-tcb-status"];
+        let cairo = generate(&json, &args).unwrap();
+        assert!(cairo.contains("    EnclaveIdentityV2TcbLevelItem,\n};\n"));
+        assert!(cairo.contains(&format!(
+            "\n{}\npub fn data() -> EnclaveIdentityV2 {{\n",
+            TypeDef::tcb_status(TCB_STATUS_TYPE).render()
+        )));
+
+        // Only used with the enum format
+        let cairo = generate(&json, &["--define-tcb-status"]).unwrap();
+        assert!(!cairo.contains("TcbStatus"));
+        assert!(cairo.contains(r#"tcb_status: "UpToDate","#));
+    }
+
+    #[test]
+    fn rejects_unknown_tcb_statuses_in_enum_format() {
+        for status in ["UptoDate", "Up To Date", "TCBRevoked"] {
+            let json = qe_identity_json(None, &[(8, "2024-03-13T00:00:00Z", status)]);
+            assert!(generate(&json, &[]).is_ok());
+
+            let error = generate(&json, &["--tcb-status-format", "enum"]).unwrap_err();
+            assert_eq!(
+                error.to_string(),
+                format!(
+                    "Unknown TCB status: {status}, expected one of UpToDate, SWHardeningNeeded, \
+                     ConfigurationNeeded, ConfigurationAndSWHardeningNeeded, OutOfDate, \
+                     OutOfDateConfigurationNeeded, Revoked"
+                )
+            );
         }
-        result.push('\n');
-        result.push_str(indent);
-        result.push_str(
-            &chunk
-                .iter()
-                .map(|b| format!("0x{:02x}", b))
-                .collect::<Vec<_>>()
-                .join(", "),
+    }
+
+    #[test]
+    fn appends_signed_body_after_data() {
+        let json = qe_identity_json(None, &[(8, "2024-03-13T00:00:00Z", "UpToDate")]);
+        let cairo = generate(&json, &["--signed-body"]).unwrap();
+
+        let signed_body = SignedBody::extract(&json, "enclaveIdentity", &"00".repeat(64)).unwrap();
+        let expected = format!("}}\n\n{}", signed_body.render(Encoding::U8).unwrap());
+        assert!(cairo.ends_with(&expected), "{cairo}");
+        assert!(!generate(&json, &[]).unwrap().contains("SIGNED_BODY"));
+    }
+
+    #[test]
+    fn normalizes_v1_to_v2_model() {
+        let json = format!(
+            r#"{{"qeIdentity":{{"version":1,"issueDate":"2019-06-10T12:00:00Z","nextUpdate":"2019-07-10T12:00:00Z","miscselect":"00000000","miscselectMask":"FFFFFFFF","attributes":"11000000000000000000000000000000","attributesMask":"FBFFFFFFFFFFFFFF0000000000000000","mrsigner":"{}","isvprodid":1,"isvsvn":2}},"signature":"{}"}}"#,
+            "8C".repeat(32),
+            "00".repeat(64)
         );
+
+        let (qe_identity, signed_key) = parse_qe_identity(&json).unwrap();
+        assert_eq!(signed_key, "qeIdentity");
+
+        let identity = qe_identity.enclave_identity;
+        assert_eq!(identity.id, "QE");
+        assert_eq!(identity.version, 1);
+        assert_eq!(identity.tcb_evaluation_data_number, 0);
+        let [tcb_level] = identity.tcb_levels.as_slice() else {
+            panic!("expected a single TCB level");
+        };
+        assert_eq!(tcb_level.tcb.isvsvn, 2);
+        assert_eq!(tcb_level.tcb_date, "2019-06-10T12:00:00Z");
+        assert_eq!(tcb_level.tcb_status, "UpToDate");
+        assert!(tcb_level.advisory_ids.is_none());
+
+        let cairo = generate(&json, &[]).unwrap();
+        assert!(cairo.contains("            id: \"QE\",\n            version: 1,\n"));
     }
-    result.push_str(",\n");
-    result.push_str(&indent[..indent.len() - 4]);
-    result
-}
 
-fn format_bytes_from_hex_string(hex_str: &str, bytes: &[u8], multiline: bool) -> String {
-    let is_uppercase = hex_str.chars().any(|c| c.is_ascii_uppercase());
-
-    if multiline {
-        format_bytes_from_hex_string_multiline_impl(bytes, 16, "", is_uppercase)
-    } else {
-        bytes
-            .iter()
-            .map(|b| {
-                if is_uppercase {
-                    format!("0x{:02X}", b)
-                } else {
-                    format!("0x{:02x}", b)
-                }
-            })
-            .collect::<Vec<_>>()
-            .join(", ")
+    #[test]
+    fn rejects_unsupported_versions_and_layouts() {
+        let json = qe_identity_json(None, &[(8, "2024-03-13T00:00:00Z", "UpToDate")]);
+        for (json, error) in [
+            (
+                json.replace(r#""version":2"#, r#""version":3"#),
+                "Unsupported enclave identity version: 3",
+            ),
+            (
+                json.replace("enclaveIdentity", "qeIdentity"),
+                "Unsupported enclave identity version: 2",
+            ),
+            (
+                json.replace(
+                    r#""signature""#,
+                    r#""qeIdentity":{"version":1},"signature""#,
+                ),
+                "Expected exactly one of enclaveIdentity and qeIdentity",
+            ),
+            (
+                json.replace("enclaveIdentity", "identity"),
+                "Expected exactly one of enclaveIdentity and qeIdentity",
+            ),
+        ] {
+            assert_eq!(generate(&json, &[]).unwrap_err().to_string(), error);
+        }
     }
-}
 
-fn format_bytes_from_hex_string_multiline(
-    hex_str: &str,
-    bytes: &[u8],
-    per_line: usize,
-    indent: &str,
-) -> String {
-    let is_uppercase = hex_str.chars().any(|c| c.is_ascii_uppercase());
-    format_bytes_from_hex_string_multiline_impl(bytes, per_line, indent, is_uppercase)
-}
+    #[test]
+    fn validates_id_without_quote() {
+        let json = qe_identity_json(None, &[(8, "2024-03-13T00:00:00Z", "UpToDate")]);
+        for id in ["QE", "QVE", "TD_QE"] {
+            let json = json.replace(r#""id":"QE""#, &format!(r#""id":"{id}""#));
+            assert!(
+                generate(&json, &[])
+                    .unwrap()
+                    .contains(&format!("id: \"{id}\","))
+            );
+        }
 
-fn format_bytes_from_hex_string_multiline_impl(
-    bytes: &[u8],
-    per_line: usize,
-    indent: &str,
-    uppercase: bool,
-) -> String {
-    let mut result = String::new();
-    for (i, chunk) in bytes.chunks(per_line).enumerate() {
-        if i > 0 {
-            result.push(',');
+        for id in ["BOGUS", "qe", "TDQE"] {
+            let json = json.replace(r#""id":"QE""#, &format!(r#""id":"{id}""#));
+            assert_eq!(
+                generate(&json, &[]).unwrap_err().to_string(),
+                format!("Unknown enclave identity id: {id}, expected QE, QVE or TD_QE")
+            );
         }
-        result.push('\n');
-        result.push_str(indent);
-        result.push_str(
-            &chunk
-                .iter()
-                .map(|b| {
-                    if uppercase {
-                        format!("0x{:02X}", b)
-                    } else {
-                        format!("0x{:02x}", b)
-                    }
-                })
-                .collect::<Vec<_>>()
-                .join(", "),
+    }
+
+    #[test]
+    fn diff_matches_levels_by_isvsvn() {
+        let old = qe_identity_json(
+            None,
+            &[
+                (8, "2024-03-13T00:00:00Z", "UpToDate"),
+                (6, "2023-08-09T00:00:00Z", "OutOfDate"),
+            ],
+        );
+        let new = qe_identity_json(
+            None,
+            &[
+                (9, "2024-08-14T00:00:00Z", "UpToDate"),
+                (8, "2024-03-13T00:00:00Z", "OutOfDate"),
+            ],
+        )
+        .replace(
+            r#""tcbEvaluationDataNumber":16"#,
+            r#""tcbEvaluationDataNumber":17"#,
+        );
+
+        assert_eq!(
+            diff(&old, &new).unwrap().render(DiffFormat::Text).unwrap(),
+            "~ enclaveIdentity.tcbEvaluationDataNumber: 16 -> 17\n\
+             ~ enclaveIdentity.tcbLevels[isvsvn=8].tcbStatus: \"UpToDate\" -> \"OutOfDate\"\n\
+             - enclaveIdentity.tcbLevels[isvsvn=6]: {\"tcb\":{\"isvsvn\":6},\"tcbDate\":\"2023-08-09T00:00:00Z\",\"tcbStatus\":\"OutOfDate\"}\n\
+             + enclaveIdentity.tcbLevels[isvsvn=9]: {\"tcb\":{\"isvsvn\":9},\"tcbDate\":\"2024-08-14T00:00:00Z\",\"tcbStatus\":\"UpToDate\"}\n"
         );
     }
-    result.push_str(",\n");
-    result.push_str(&indent[..indent.len() - 4]);
-    result
-}
 
-struct DateTime {
-    year: i32,
-    month: u8,
-    day: u8,
-    hour: u8,
-    minute: u8,
-    second: u8,
-    millisecond: u16,
-}
+    #[test]
+    fn diff_reads_advisory_ids() {
+        let old = qe_identity_json(None, &[(8, "2024-03-13T00:00:00Z", "OutOfDate")]);
+        let new = old.replace(
+            r#""tcbStatus":"OutOfDate""#,
+            r#""tcbStatus":"OutOfDate","advisoryIDs":["INTEL-SA-00837"]"#,
+        );
 
-fn parse_datetime(datetime_str: &str) -> Result<DateTime> {
-    // Parse ISO 8601 datetime string like "2025-02-13T03:39:00Z"
-    let datetime_str = datetime_str.trim_end_matches('Z');
-    let parts: Vec<&str> = datetime_str.split('T').collect();
-    if parts.len() != 2 {
-        return Err(eyre::eyre!("Invalid datetime format"));
+        assert_eq!(
+            diff(&old, &new).unwrap().render(DiffFormat::Text).unwrap(),
+            "+ enclaveIdentity.tcbLevels[isvsvn=8].advisoryIDs: \"INTEL-SA-00837\"\n"
+        );
     }
 
-    let date_parts: Vec<&str> = parts[0].split('-').collect();
-    if date_parts.len() != 3 {
-        return Err(eyre::eyre!("Invalid date format"));
+    #[test]
+    fn rejects_invalid_documents_with_their_json_path() {
+        let json = qe_identity_json(None, &[(8, "2024-03-13T00:00:00Z", "UpToDate")]);
+        for (from, to, expected) in [
+            (
+                r#""isvsvn":8}"#,
+                r#""isvsvn":8,"isvprodid":1}"#,
+                "Invalid JSON at enclaveIdentity.tcbLevels[0].tcb.isvprodid: unknown field",
+            ),
+            (
+                r#""isvprodid":1,"#,
+                r#""isvprodid":"1","#,
+                "Invalid JSON at enclaveIdentity.isvprodid: invalid type",
+            ),
+            (
+                r#""mrsigner":"DC"#,
+                r#""mrsigner":""#,
+                "Invalid length at enclaveIdentity.mrsigner: expected 32 bytes, got 31",
+            ),
+            (
+                r#""attributesMask":"FBFF"#,
+                r#""attributesMask":"FB"#,
+                "Invalid length at enclaveIdentity.attributesMask: expected 16 bytes, got 15",
+            ),
+            (
+                r#""miscselect":"00000000""#,
+                r#""miscselect":"0000000g""#,
+                "Invalid hex string at enclaveIdentity.miscselect: ",
+            ),
+        ] {
+            assert!(json.contains(from), "{from}");
+            let error = parse_qe_identity(&json.replacen(from, to, 1)).unwrap_err();
+            assert!(error.to_string().starts_with(expected), "{error}");
+        }
     }
 
-    let time_parts: Vec<&str> = parts[1].split(':').collect();
-    if time_parts.len() != 3 {
-        return Err(eyre::eyre!("Invalid time format"));
+    #[test]
+    fn same_day_tcb_dates_get_distinct_bindings() {
+        let json = qe_identity_json(
+            None,
+            &[
+                (8, "2024-03-13T12:00:00Z", "UpToDate"),
+                (6, "2024-03-13T00:00:00Z", "OutOfDate"),
+            ],
+        );
+        let cairo = generate(&json, &[]).unwrap();
+
+        assert_eq!(cairo.matches("let tcb_date_2024_03_13 =").count(), 1);
+        assert_eq!(
+            cairo.matches("let tcb_date_2024_03_13_12_00_00 =").count(),
+            1
+        );
+        assert!(cairo.contains("TimeTrait::from_hms_milli(12, 0, 0, 0)"));
+        assert!(cairo.contains("tcb_date: tcb_date_2024_03_13_12_00_00,"));
+        assert!(cairo.contains("tcb_date: tcb_date_2024_03_13,"));
     }
 
-    Ok(DateTime {
-        year: date_parts[0].parse()?,
-        month: date_parts[1].parse()?,
-        day: date_parts[2].parse()?,
-        hour: time_parts[0].parse()?,
-        minute: time_parts[1].parse()?,
-        second: time_parts[2].parse()?,
-        millisecond: 0,
-    })
-}
+    #[test]
+    fn type_defs_match_data() {
+        let command = QeidentityCommand::try_parse_from([
+            "qeidentity",
+            "--output",
+            "out.cairo",
+            "--input",
+            "in.json",
+            "--tcb-status-format",
+            "enum",
+        ])
+        .unwrap();
+        let types = command
+            .types
+            .resolve("qeidentity", TYPES_MODULE, &TYPE_NAMES)
+            .unwrap();
+        let type_defs = type_defs(
+            &types,
+            command.date_format,
+            command.string_format,
+            command.tcb_status.tcb_status_format,
+        );
 
-fn month_name(month: u8) -> &'static str {
-    match month {
-        1 => "January",
-        2 => "February",
-        3 => "March",
-        4 => "April",
-        5 => "May",
-        6 => "June",
-        7 => "July",
-        8 => "August",
-        9 => "September",
-        10 => "October",
-        11 => "November",
-        12 => "December",
-        _ => panic!("Invalid month"),
+        let (qe_identity, _) = parse_qe_identity(&qe_identity_json(
+            None,
+            &[(8, "2024-03-13T00:00:00Z", "UpToDate")],
+        ))
+        .unwrap();
+        let used =
+            assert_matches_type_defs(&command.data(&types, &qe_identity).unwrap(), &type_defs);
+        assert_eq!(used, TYPE_NAMES.map(str::to_owned).into());
     }
 }
@@ -1,24 +1,191 @@
-use std::path::PathBuf;
+use std::{io::Write, path::PathBuf};
 
-use clap::Parser;
+use clap::{Parser, ValueEnum};
 use eyre::Result;
 use x509_parser::pem::Pem;
 
-use crate::quote::{CertData, Quote};
+use crate::{
+    bundle::{BundleItem, read_input},
+    cairo::{CairoBytesOptions, is_valid_item_name, write_cairo_chain_fn, write_cairo_encoded},
+    cairo_types::{CairoTypes, CairoTypesArgs},
+    chain::{chain_names, order_chain},
+    emitter::{DateFormat, Expr, Function, StringFormat, TcbStatusFormat, TypeDef, render_use},
+    freshness::{FreshnessArgs, Validity},
+    quote::{CertData, EnclaveReport, Quote, Td10Report, TeeType},
+    template::{Generated, TemplateArgs, TemplateKind},
+};
 
 #[derive(Debug, Parser)]
 pub struct QuoteCommand {
-    /// Path to the quote file.
-    #[clap(long)]
-    input: PathBuf,
+    /// Path to the quote file. Required unless reading from `--bundle`.
+    #[clap(long, required_unless_present = "bundle")]
+    input: Option<PathBuf>,
+    /// Path to an attestation bundle to read the quote from instead of `--input`.
+    #[clap(long, conflicts_with = "input")]
+    bundle: Option<PathBuf>,
     /// Path to the modified quote file.
     #[clap(long)]
     output: PathBuf,
+    /// Output format of the modified quote.
+    #[clap(long, value_enum, default_value_t = QuoteFormat::Binary)]
+    format: QuoteFormat,
+    /// Emit the quote as separate items for each of its parts instead of a single one. Requires
+    /// `--format cairo`.
+    #[clap(long)]
+    split: bool,
+    /// Name of the generated item. With `--split`, used as a prefix of each part's name instead.
+    /// Requires `--format cairo`.
+    #[clap(long)]
+    name: Option<String>,
+    #[clap(flatten)]
+    options: CairoBytesOptions,
+    #[clap(flatten)]
+    freshness: FreshnessArgs,
+    /// Type overrides for `--format cairo-struct`.
+    #[clap(flatten)]
+    types: CairoTypesArgs,
+    /// Template overrides for `--format cairo-struct`.
+    #[clap(flatten)]
+    template: TemplateArgs,
+}
+
+/// Module the generated types are imported from unless overridden.
+pub const TYPES_MODULE: &str = "crate::types::quote";
+
+pub const TYPE_NAMES: [&str; 7] = [
+    "EnclaveReport",
+    "QeReportCertData",
+    "Quote",
+    "QuoteBody",
+    "QuoteHeader",
+    "QuoteSignatureData",
+    "TD10ReportBody",
+];
+
+/// Types used by SGX quotes, i.e. all but the TDX report body.
+const SGX_TYPE_NAMES: [&str; 6] = [
+    "EnclaveReport",
+    "QeReportCertData",
+    "Quote",
+    "QuoteBody",
+    "QuoteHeader",
+    "QuoteSignatureData",
+];
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum QuoteFormat {
+    /// Raw quote bytes.
+    Binary,
+    /// Cairo file defining the quote bytes.
+    Cairo,
+    /// Cairo file defining the parsed quote as a struct literal.
+    CairoStruct,
+}
+
+/// Cairo counterparts of the parsed quote, matching `--format cairo-struct` data.
+pub fn type_defs(types: &CairoTypes) -> Vec<TypeDef> {
+    let bytes = || "Span<u8>".to_owned();
+
+    vec![
+        TypeDef::structure(
+            types.name("Quote"),
+            vec![
+                ("header", types.name("QuoteHeader")),
+                ("body", types.name("QuoteBody")),
+                ("signature", types.name("QuoteSignatureData")),
+            ],
+        ),
+        TypeDef::structure(
+            types.name("QuoteHeader"),
+            vec![
+                ("version", "u16".to_owned()),
+                ("att_key_type", "u16".to_owned()),
+                ("tee_type", "u32".to_owned()),
+                ("qe_svn", "u16".to_owned()),
+                ("pce_svn", "u16".to_owned()),
+                ("qe_vendor_id", bytes()),
+                ("user_data", bytes()),
+            ],
+        ),
+        TypeDef::enumeration(
+            types.name("QuoteBody"),
+            vec![
+                ("SgxQeReportBody", Some(types.name("EnclaveReport"))),
+                ("Td10QuoteBody", Some(types.name("TD10ReportBody"))),
+            ],
+        ),
+        TypeDef::structure(
+            types.name("QuoteSignatureData"),
+            vec![
+                ("quote_signature", bytes()),
+                ("ecdsa_attestation_key", bytes()),
+                ("qe_cert_data", types.name("QeReportCertData")),
+            ],
+        ),
+        TypeDef::structure(
+            types.name("QeReportCertData"),
+            vec![
+                ("qe_report", types.name("EnclaveReport")),
+                ("qe_report_signature", bytes()),
+                ("qe_auth_data", bytes()),
+                ("pck_cert_chain", "Span<Span<u8>>".to_owned()),
+            ],
+        ),
+        TypeDef::structure(
+            types.name("EnclaveReport"),
+            vec![
+                ("cpu_svn", bytes()),
+                ("misc_select", bytes()),
+                ("attributes", bytes()),
+                ("mrenclave", bytes()),
+                ("mrsigner", bytes()),
+                ("isv_prod_id", "u16".to_owned()),
+                ("isv_svn", "u16".to_owned()),
+                ("report_data", bytes()),
+            ],
+        ),
+        TypeDef::structure(
+            types.name("TD10ReportBody"),
+            [
+                "tee_tcb_svn",
+                "mrseam",
+                "mrsignerseam",
+                "seam_attributes",
+                "td_attributes",
+                "xfam",
+                "mrtd",
+                "mrconfigid",
+                "mrowner",
+                "mrownerconfig",
+                "rtmr0",
+                "rtmr1",
+                "rtmr2",
+                "rtmr3",
+                "report_data",
+            ]
+            .into_iter()
+            .map(|field| (field, bytes()))
+            .collect(),
+        ),
+    ]
 }
 
 impl QuoteCommand {
     pub fn run(self) -> Result<()> {
-        let raw_bytes = std::fs::read(&self.input)?;
+        if !matches!(self.format, QuoteFormat::Cairo) && (self.split || self.name.is_some()) {
+            eyre::bail!("Options --split and --name require --format cairo");
+        }
+        if let Some(name) = &self.name
+            && !is_valid_item_name(name)
+        {
+            eyre::bail!("Invalid Cairo identifier: {name}");
+        }
+
+        let raw_bytes = read_input(
+            self.input.as_deref(),
+            self.bundle.as_deref(),
+            BundleItem::Quote,
+        )?;
         let mut quote = Quote::from_bytes(&raw_bytes)?;
 
         // Sanity check
@@ -26,19 +193,22 @@ impl QuoteCommand {
             eyre::bail!("Quote serde roundtrip failed");
         }
 
+        let mut certs = vec![];
+
         match &mut quote.signature.cert_data {
             CertData::QeReportCertData(qe_report) => match qe_report.qe_cert_data.get_mut() {
                 CertData::Certificates(payload) => {
                     let mut transformed = vec![];
 
                     for pem in Pem::iter_from_buffer(payload) {
-                        let mut pem = pem?;
+                        let pem = pem?;
 
                         if pem.label != "CERTIFICATE" {
                             eyre::bail!("Unexpected PEM label: {}", pem.label);
                         }
 
-                        transformed.append(&mut pem.contents);
+                        transformed.extend_from_slice(&pem.contents);
+                        certs.push(pem.contents);
                     }
 
                     payload.clear();
@@ -49,8 +219,578 @@ impl QuoteCommand {
             _ => eyre::bail!("Unexpected cert data type"),
         }
 
-        std::fs::write(self.output, quote.to_bytes())?;
+        // Only parse the PCK chain when its validity is checked
+        if self.freshness.is_enabled() {
+            self.freshness
+                .check(&Validity::certificates("PCK chain", &certs)?)?;
+        }
+
+        match self.format {
+            QuoteFormat::Binary => std::fs::write(self.output, quote.to_bytes())?,
+            QuoteFormat::Cairo if self.split => {
+                let parts = split_quote(&quote, certs, self.name.as_deref())?;
+
+                let mut output_file = std::fs::File::create(&self.output)?;
+                for (name, bytes) in &parts.items {
+                    write_cairo_encoded(&mut output_file, name, bytes, &self.options)?;
+                    writeln!(output_file)?;
+                }
+
+                write_cairo_chain_fn(
+                    &mut output_file,
+                    &parts.chain_fn_name,
+                    &parts.chain_names,
+                    &self.options,
+                )?;
+            }
+            QuoteFormat::CairoStruct => {
+                let types = self.types.resolve("quote", TYPES_MODULE, &TYPE_NAMES)?;
+                let certs = order_chain(certs)?;
+                let output = self.template.render(
+                    TemplateKind::Quote,
+                    &quote_model(&quote, &certs)?,
+                    quote_struct(&types, &quote, &certs)?,
+                    DateFormat::default(),
+                    StringFormat::default(),
+                    TcbStatusFormat::default(),
+                )?;
+                std::fs::write(&self.output, output)?;
+            }
+            QuoteFormat::Cairo => {
+                let mut output_file = std::fs::File::create(&self.output)?;
+                write_cairo_encoded(
+                    &mut output_file,
+                    self.name.as_deref().unwrap_or("DATA"),
+                    &quote.to_bytes(),
+                    &self.options,
+                )?;
+            }
+        }
 
         Ok(())
     }
 }
+
+struct QuoteParts {
+    items: Vec<(String, Vec<u8>)>,
+    chain_fn_name: String,
+    chain_names: Vec<String>,
+}
+
+/// Splits a transformed quote into named byte items, with the PCK chain ordered leaf to root.
+fn split_quote(quote: &Quote, certs: Vec<Vec<u8>>, prefix: Option<&str>) -> Result<QuoteParts> {
+    let prefixed = |name: &str| match prefix {
+        Some(prefix) => format!("{prefix}_{name}"),
+        None => name.to_owned(),
+    };
+
+    let CertData::QeReportCertData(qe_report) = &quote.signature.cert_data else {
+        eyre::bail!("Unexpected cert data type");
+    };
+
+    let mut items = vec![
+        (prefixed("HEADER"), quote.header.to_bytes().to_vec()),
+        (prefixed("BODY"), quote.body.clone()),
+        (prefixed("SIGNATURE"), quote.signature.sig.to_vec()),
+        (prefixed("ATTESTATION_KEY"), quote.signature.key.to_vec()),
+        (prefixed("QE_REPORT"), qe_report.qe_report.to_vec()),
+        (
+            prefixed("QE_REPORT_SIGNATURE"),
+            qe_report.qe_report_signature.to_vec(),
+        ),
+        (prefixed("QE_AUTH_DATA"), qe_report.qe_auth_data.clone()),
+    ];
+    if !quote.rest.is_empty() {
+        items.push((prefixed("REST"), quote.rest.clone()));
+    }
+
+    let certs = order_chain(certs)?;
+    let chain_names = chain_names(certs.len())
+        .into_iter()
+        .map(|name| prefixed(&format!("PCK_{name}")))
+        .collect::<Vec<_>>();
+    items.extend(chain_names.iter().cloned().zip(certs));
+
+    Ok(QuoteParts {
+        items,
+        chain_fn_name: prefixed("PCK_CHAIN").to_lowercase(),
+        chain_names,
+    })
+}
+
+/// Generates a Cairo function returning the parsed quote as a struct literal.
+fn quote_struct(types: &CairoTypes, quote: &Quote, certs: &[Vec<u8>]) -> Result<Generated> {
+    let imports = match quote.header.tee_type {
+        TeeType::Sgx => &SGX_TYPE_NAMES[..],
+        TeeType::Tdx => &TYPE_NAMES[..],
+    };
+
+    let data = quote_data(types, quote, certs)?;
+
+    Ok(Generated {
+        date_imports: String::new(),
+        type_imports: render_use(types.module(), &types.imports(imports)),
+        date_let_type: None,
+        type_defs: String::new(),
+        data: Function {
+            name: "data".to_owned(),
+            ret: types.name("Quote"),
+            lets: vec![],
+            body: data,
+        }
+        .render(),
+        signed_body: String::new(),
+        types_module: types.module().to_owned(),
+        types: types.names(imports),
+    })
+}
+
+/// Returns the parsed quote as a struct literal.
+fn quote_data(types: &CairoTypes, quote: &Quote, certs: &[Vec<u8>]) -> Result<Expr> {
+    let CertData::QeReportCertData(qe_report_cert_data) = &quote.signature.cert_data else {
+        eyre::bail!("Unexpected cert data type");
+    };
+    let qe_report = EnclaveReport::from_bytes(&qe_report_cert_data.qe_report)?;
+
+    let header = &quote.header;
+    let body = match header.tee_type {
+        TeeType::Sgx => Expr::call(
+            format!("{}::SgxQeReportBody", types.name("QuoteBody")),
+            vec![enclave_report(
+                types,
+                &EnclaveReport::from_bytes(&quote.body)?,
+            )],
+        ),
+        TeeType::Tdx => Expr::call(
+            format!("{}::Td10QuoteBody", types.name("QuoteBody")),
+            vec![td10_report(types, &Td10Report::from_bytes(&quote.body)?)],
+        ),
+    };
+
+    Ok(Expr::structure(
+        types.name("Quote"),
+        vec![
+            (
+                "header",
+                Expr::structure(
+                    types.name("QuoteHeader"),
+                    vec![
+                        ("version", Expr::int(header.version)),
+                        ("att_key_type", Expr::int(header.att_key_type)),
+                        (
+                            "tee_type",
+                            Expr::raw(format!(
+                                "{:#010x}",
+                                u32::from_le_bytes(header.raw[4..8].try_into()?)
+                            )),
+                        ),
+                        ("qe_svn", Expr::int(header.qe_svn)),
+                        ("pce_svn", Expr::int(header.pce_svn)),
+                        ("qe_vendor_id", Expr::bytes(&header.qe_vendor_id).span()),
+                        ("user_data", Expr::bytes(&header.user_data).span()),
+                    ],
+                ),
+            ),
+            ("body", body),
+            (
+                "signature",
+                Expr::structure(
+                    types.name("QuoteSignatureData"),
+                    vec![
+                        ("quote_signature", Expr::bytes(&quote.signature.sig).span()),
+                        (
+                            "ecdsa_attestation_key",
+                            Expr::bytes(&quote.signature.key).span(),
+                        ),
+                        (
+                            "qe_cert_data",
+                            Expr::structure(
+                                types.name("QeReportCertData"),
+                                vec![
+                                    ("qe_report", enclave_report(types, &qe_report)),
+                                    (
+                                        "qe_report_signature",
+                                        Expr::bytes(&qe_report_cert_data.qe_report_signature)
+                                            .span(),
+                                    ),
+                                    (
+                                        "qe_auth_data",
+                                        Expr::bytes(&qe_report_cert_data.qe_auth_data).span(),
+                                    ),
+                                    (
+                                        "pck_cert_chain",
+                                        Expr::array(
+                                            certs
+                                                .iter()
+                                                .map(|cert| Expr::bytes(cert).span())
+                                                .collect(),
+                                        )
+                                        .span(),
+                                    ),
+                                ],
+                            ),
+                        ),
+                    ],
+                ),
+            ),
+        ],
+    ))
+}
+
+/// Parsed quote exposed to templates, with byte fields as hex strings.
+fn quote_model(quote: &Quote, certs: &[Vec<u8>]) -> Result<serde_json::Value> {
+    let CertData::QeReportCertData(qe_report_cert_data) = &quote.signature.cert_data else {
+        eyre::bail!("Unexpected cert data type");
+    };
+
+    let header = &quote.header;
+    let body = match header.tee_type {
+        TeeType::Sgx => enclave_report_model(&EnclaveReport::from_bytes(&quote.body)?),
+        TeeType::Tdx => td10_report_model(&Td10Report::from_bytes(&quote.body)?),
+    };
+
+    Ok(serde_json::json!({
+        "header": {
+            "version": header.version,
+            "att_key_type": header.att_key_type,
+            "tee_type": match header.tee_type {
+                TeeType::Sgx => "sgx",
+                TeeType::Tdx => "tdx",
+            },
+            "qe_svn": header.qe_svn,
+            "pce_svn": header.pce_svn,
+            "qe_vendor_id": hex::encode(header.qe_vendor_id),
+            "user_data": hex::encode(header.user_data),
+        },
+        "body": body,
+        "signature": {
+            "quote_signature": hex::encode(quote.signature.sig),
+            "ecdsa_attestation_key": hex::encode(quote.signature.key),
+            "qe_cert_data": {
+                "qe_report": enclave_report_model(
+                    &EnclaveReport::from_bytes(&qe_report_cert_data.qe_report)?
+                ),
+                "qe_report_signature": hex::encode(qe_report_cert_data.qe_report_signature),
+                "qe_auth_data": hex::encode(&qe_report_cert_data.qe_auth_data),
+                "pck_cert_chain": certs.iter().map(hex::encode).collect::<Vec<_>>(),
+            },
+        },
+    }))
+}
+
+fn enclave_report_model(report: &EnclaveReport) -> serde_json::Value {
+    serde_json::json!({
+        "cpu_svn": hex::encode(report.cpu_svn),
+        "misc_select": hex::encode(report.misc_select),
+        "attributes": hex::encode(report.attributes),
+        "mrenclave": hex::encode(report.mrenclave),
+        "mrsigner": hex::encode(report.mrsigner),
+        "isv_prod_id": report.isv_prod_id,
+        "isv_svn": report.isv_svn,
+        "report_data": hex::encode(report.report_data),
+    })
+}
+
+fn td10_report_model(report: &Td10Report) -> serde_json::Value {
+    serde_json::json!({
+        "tee_tcb_svn": hex::encode(report.tee_tcb_svn),
+        "mrseam": hex::encode(report.mrseam),
+        "mrsignerseam": hex::encode(report.mrsignerseam),
+        "seam_attributes": hex::encode(report.seam_attributes),
+        "td_attributes": hex::encode(report.td_attributes),
+        "xfam": hex::encode(report.xfam),
+        "mrtd": hex::encode(report.mrtd),
+        "mrconfigid": hex::encode(report.mrconfigid),
+        "mrowner": hex::encode(report.mrowner),
+        "mrownerconfig": hex::encode(report.mrownerconfig),
+        "rtmr0": hex::encode(report.rtmr0),
+        "rtmr1": hex::encode(report.rtmr1),
+        "rtmr2": hex::encode(report.rtmr2),
+        "rtmr3": hex::encode(report.rtmr3),
+        "report_data": hex::encode(report.report_data),
+    })
+}
+
+fn enclave_report(types: &CairoTypes, report: &EnclaveReport) -> Expr {
+    Expr::structure(
+        types.name("EnclaveReport"),
+        vec![
+            ("cpu_svn", Expr::bytes(&report.cpu_svn).span()),
+            ("misc_select", Expr::bytes(&report.misc_select).span()),
+            ("attributes", Expr::bytes(&report.attributes).span()),
+            ("mrenclave", Expr::bytes(&report.mrenclave).span()),
+            ("mrsigner", Expr::bytes(&report.mrsigner).span()),
+            ("isv_prod_id", Expr::int(report.isv_prod_id)),
+            ("isv_svn", Expr::int(report.isv_svn)),
+            ("report_data", Expr::bytes(&report.report_data).span()),
+        ],
+    )
+}
+
+fn td10_report(types: &CairoTypes, report: &Td10Report) -> Expr {
+    Expr::structure(
+        types.name("TD10ReportBody"),
+        vec![
+            ("tee_tcb_svn", Expr::bytes(&report.tee_tcb_svn).span()),
+            ("mrseam", Expr::bytes(&report.mrseam).span()),
+            ("mrsignerseam", Expr::bytes(&report.mrsignerseam).span()),
+            (
+                "seam_attributes",
+                Expr::bytes(&report.seam_attributes).span(),
+            ),
+            ("td_attributes", Expr::bytes(&report.td_attributes).span()),
+            ("xfam", Expr::bytes(&report.xfam).span()),
+            ("mrtd", Expr::bytes(&report.mrtd).span()),
+            ("mrconfigid", Expr::bytes(&report.mrconfigid).span()),
+            ("mrowner", Expr::bytes(&report.mrowner).span()),
+            ("mrownerconfig", Expr::bytes(&report.mrownerconfig).span()),
+            ("rtmr0", Expr::bytes(&report.rtmr0).span()),
+            ("rtmr1", Expr::bytes(&report.rtmr1).span()),
+            ("rtmr2", Expr::bytes(&report.rtmr2).span()),
+            ("rtmr3", Expr::bytes(&report.rtmr3).span()),
+            ("report_data", Expr::bytes(&report.report_data).span()),
+        ],
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        constants::{ENCLAVE_REPORT_LEN, HEADER_LEN, SGX_TEE_TYPE, TD10_REPORT_LEN, TDX_TEE_TYPE},
+        test_utils::{Pki, assert_matches_type_defs, run_preprocess},
+    };
+
+    /// Quote of the given TEE type, with the PCK chain of `pki` and bytes counting up in every
+    /// field.
+    fn quote_bytes(pki: &Pki, tee_type: TeeType) -> Vec<u8> {
+        let counting = |len: usize| (0..len).map(|ind| ind as u8).collect::<Vec<_>>();
+
+        let mut header = counting(HEADER_LEN);
+        header[4..8].copy_from_slice(
+            &match tee_type {
+                TeeType::Sgx => SGX_TEE_TYPE,
+                TeeType::Tdx => TDX_TEE_TYPE,
+            }
+            .to_le_bytes(),
+        );
+        let body = counting(match tee_type {
+            TeeType::Sgx => ENCLAVE_REPORT_LEN,
+            TeeType::Tdx => TD10_REPORT_LEN,
+        });
+
+        let pck_chain = pki.chain_pem().into_bytes();
+        let mut qe_report_cert_data = counting(ENCLAVE_REPORT_LEN + 64);
+        qe_report_cert_data.extend_from_slice(&32u16.to_le_bytes());
+        qe_report_cert_data.extend_from_slice(&counting(32));
+        qe_report_cert_data.extend_from_slice(&5u16.to_le_bytes());
+        qe_report_cert_data.extend_from_slice(&(pck_chain.len() as u32).to_le_bytes());
+        qe_report_cert_data.extend_from_slice(&pck_chain);
+
+        let mut signature = counting(128);
+        signature.extend_from_slice(&6u16.to_le_bytes());
+        signature.extend_from_slice(&(qe_report_cert_data.len() as u32).to_le_bytes());
+        signature.extend_from_slice(&qe_report_cert_data);
+
+        let mut quote = header;
+        quote.extend_from_slice(&body);
+        quote.extend_from_slice(&(signature.len() as u32).to_le_bytes());
+        quote.extend_from_slice(&signature);
+        quote
+    }
+
+    #[test]
+    fn splits_quote_into_named_parts() {
+        let pki = Pki::new("Test");
+        let quote = Quote::from_bytes(&quote_bytes(&pki, TeeType::Sgx)).unwrap();
+        let [leaf, intermediate, root] = pki.chain_der();
+
+        let parts = split_quote(
+            &quote,
+            vec![root.clone(), leaf.clone(), intermediate.clone()],
+            Some("QUOTE"),
+        )
+        .unwrap();
+
+        let names = parts
+            .items
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            names,
+            [
+                "QUOTE_HEADER",
+                "QUOTE_BODY",
+                "QUOTE_SIGNATURE",
+                "QUOTE_ATTESTATION_KEY",
+                "QUOTE_QE_REPORT",
+                "QUOTE_QE_REPORT_SIGNATURE",
+                "QUOTE_QE_AUTH_DATA",
+                "QUOTE_PCK_LEAF",
+                "QUOTE_PCK_INTERMEDIATE",
+                "QUOTE_PCK_ROOT",
+            ]
+        );
+        let lens = parts.items[..7].iter().map(|(_, bytes)| bytes.len());
+        assert!(lens.eq([
+            HEADER_LEN,
+            ENCLAVE_REPORT_LEN,
+            64,
+            64,
+            ENCLAVE_REPORT_LEN,
+            64,
+            32
+        ]));
+        assert_eq!(parts.items[0].1, quote.header.to_bytes());
+        assert_eq!(
+            parts.items[7..]
+                .iter()
+                .map(|(_, der)| der)
+                .collect::<Vec<_>>(),
+            [&leaf, &intermediate, &root]
+        );
+
+        assert_eq!(parts.chain_fn_name, "quote_pck_chain");
+        assert_eq!(
+            parts.chain_names,
+            ["QUOTE_PCK_LEAF", "QUOTE_PCK_INTERMEDIATE", "QUOTE_PCK_ROOT"]
+        );
+    }
+
+    #[test]
+    fn names_parts_without_prefix() {
+        let pki = Pki::new("Test");
+        let quote = Quote::from_bytes(&quote_bytes(&pki, TeeType::Sgx)).unwrap();
+
+        let parts = split_quote(&quote, pki.chain_der().to_vec(), None).unwrap();
+        assert_eq!(parts.items[0].0, "HEADER");
+        assert_eq!(parts.chain_fn_name, "pck_chain");
+        assert_eq!(
+            parts.chain_names,
+            ["PCK_LEAF", "PCK_INTERMEDIATE", "PCK_ROOT"]
+        );
+    }
+
+    #[test]
+    fn emits_body_of_quote_tee_type() {
+        let pki = Pki::new("Test");
+        let types = CairoTypesArgs::default()
+            .resolve("quote", "crate::types::quote", &TYPE_NAMES)
+            .unwrap();
+
+        for (tee_type, tee_type_value, body, imports) in [
+            (
+                TeeType::Sgx,
+                "0x00000000",
+                "body: QuoteBody::SgxQeReportBody(\n            EnclaveReport {\n",
+                "QuoteSignatureData,\n};",
+            ),
+            (
+                TeeType::Tdx,
+                "0x00000081",
+                "body: QuoteBody::Td10QuoteBody(\n            TD10ReportBody {\n",
+                "QuoteSignatureData,\n    TD10ReportBody,\n};",
+            ),
+        ] {
+            let quote = Quote::from_bytes(&quote_bytes(&pki, tee_type)).unwrap();
+            let generated = quote_struct(&types, &quote, &pki.chain_der()).unwrap();
+            let output = generated.type_imports + &generated.data;
+
+            assert!(output.contains(imports), "{output}");
+            assert!(output.contains(&format!("tee_type: {tee_type_value},")));
+            assert!(output.contains(body), "{output}");
+            // Header fields are little-endian
+            assert!(output.contains("version: 256,\n            att_key_type: 770,\n"));
+            assert_eq!(
+                output
+                    .matches("].span(),\n                    array![")
+                    .count(),
+                2
+            );
+        }
+    }
+
+    #[test]
+    fn split_and_name_require_cairo_format() {
+        let quote = quote_bytes(&Pki::new("Test"), TeeType::Sgx);
+
+        for format in ["binary", "cairo-struct"] {
+            for option in [&["--split"][..], &["--name", "QUOTE"]] {
+                let mut args = vec!["--format", format];
+                args.extend(option);
+                let error = run_preprocess(&quote, &args, QuoteCommand::run).unwrap_err();
+                assert_eq!(
+                    error.to_string(),
+                    "Options --split and --name require --format cairo"
+                );
+            }
+        }
+
+        let args = ["--format", "cairo", "--split", "--name", "QUOTE"];
+        run_preprocess(&quote, &args, QuoteCommand::run).unwrap();
+    }
+
+    #[test]
+    fn only_parses_pck_chain_with_at() {
+        let pki = Pki::new("Test");
+        let mut quote = quote_bytes(&pki, TeeType::Sgx);
+
+        // Same length PEM whose contents are not a certificate
+        let leaf = pki.leaf.pem();
+        let garbage = leaf
+            .lines()
+            .map(|line| match line.starts_with("-----") {
+                true => line.to_owned(),
+                false => "A".repeat(line.len()),
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        let start = quote
+            .windows(leaf.len())
+            .position(|window| window == leaf.as_bytes())
+            .unwrap();
+        quote[start..start + garbage.len()].copy_from_slice(garbage.as_bytes());
+
+        run_preprocess(&quote, &["--format", "cairo"], QuoteCommand::run).unwrap();
+        let args = ["--format", "cairo", "--at", "now"];
+        let error = run_preprocess(&quote, &args, QuoteCommand::run).unwrap_err();
+        assert!(
+            error.to_string().starts_with("Failed to parse certificate"),
+            "{error}"
+        );
+    }
+
+    #[test]
+    fn type_defs_match_data() {
+        let pki = Pki::new("Test");
+        let command = QuoteCommand::try_parse_from([
+            "quote",
+            "--input",
+            "quote.dat",
+            "--output",
+            "out.cairo",
+        ])
+        .unwrap();
+        let types = command
+            .types
+            .resolve("quote", TYPES_MODULE, &TYPE_NAMES)
+            .unwrap();
+        let type_defs = type_defs(&types);
+
+        for (tee_type, type_names) in [
+            (TeeType::Sgx, &SGX_TYPE_NAMES[..]),
+            (TeeType::Tdx, &TYPE_NAMES[..]),
+        ] {
+            let quote = Quote::from_bytes(&quote_bytes(&pki, tee_type)).unwrap();
+            let used = assert_matches_type_defs(
+                &quote_data(&types, &quote, &pki.chain_der()).unwrap(),
+                &type_defs,
+            );
+            assert_eq!(
+                used,
+                type_names.iter().map(|name| name.to_string()).collect()
+            );
+        }
+    }
+}
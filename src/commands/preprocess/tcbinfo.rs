@@ -4,25 +4,108 @@ use clap::Parser;
 use eyre::Result;
 use serde::{Deserialize, Serialize};
 
+use crate::{
+    bundle::{Bundle, BundleItem, read_input},
+    cairo::Encoding,
+    cairo_types::{CairoTypes, CairoTypesArgs},
+    diff::Diff,
+    emitter::{
+        DateFormat, Expr, Function, Let, StringFormat, TCB_STATUS_TYPE, TcbStatusArgs,
+        TcbStatusFormat, TypeDef, date_type, render_use, tcb_date_lets, tcb_date_var,
+    },
+    freshness::{FreshnessArgs, Validity},
+    quote::{Quote, TeeType},
+    schema::{check_hex, parse_json},
+    signed_body::SignedBody,
+    store::CollateralStore,
+    template::{Generated, TemplateArgs, TemplateKind},
+    verify::VerifyArgs,
+};
+
+/// Module the generated types are imported from unless overridden.
+pub const TYPES_MODULE: &str = "crate::types::tcbinfo";
+
+pub const TYPE_NAMES: [&str; 10] = [
+    "TcbComponent",
+    "TcbInfoV3",
+    "TcbInfoV3Inner",
+    "TcbInfoV3TcbLevel",
+    "TcbInfoV3TcbLevelItem",
+    TCB_STATUS_TYPE,
+    "TdxModule",
+    "TdxModuleIdentities",
+    "TdxModuleIdentitiesTcbLevel",
+    "TdxModuleIdentitiesTcbLevelItem",
+];
+
 #[derive(Debug, Parser)]
 pub struct TcbinfoCommand {
-    /// Path to the input JSON file.
-    #[clap(long)]
-    input: PathBuf,
+    /// Path to the input JSON file. Required unless reading from `--bundle` or `--store`.
+    #[clap(long, required_unless_present_any = ["bundle", "store"])]
+    input: Option<PathBuf>,
+    /// Path to an attestation bundle to read the tcbinfo from instead of `--input`, along with its
+    /// issuer chain unless given with `--issuer-chain`.
+    #[clap(long, conflicts_with = "input")]
+    bundle: Option<PathBuf>,
+    /// Path to a collateral store to pick the tcbinfo matching `--quote` from instead of `--input`.
+    #[clap(long, conflicts_with_all = ["input", "bundle"], requires = "quote")]
+    store: Option<PathBuf>,
+    /// TCB evaluation data number of the tcbinfo picked from `--store`. Defaults to the highest one.
+    #[clap(long, requires = "store")]
+    tcb_evaluation_data_number: Option<u32>,
+    /// Path to the quote to pick the tcbinfo for from `--store`.
+    #[clap(long, requires = "store")]
+    quote: Option<PathBuf>,
     /// Path to the output Cairo file.
     #[clap(long)]
     output: PathBuf,
+    /// Cairo representation of issue, next update and TCB dates.
+    #[clap(long, value_enum, default_value_t = DateFormat::OffsetDateTime)]
+    date_format: DateFormat,
+    /// Cairo representation of IDs, TCB statuses, component categories and types, and advisory
+    /// IDs.
+    #[clap(long, value_enum, default_value_t = StringFormat::ByteArray)]
+    string_format: StringFormat,
+    #[clap(flatten)]
+    tcb_status: TcbStatusArgs,
+    /// Also emit the byte-exact signed `tcbInfo` object as `SIGNED_BODY`, along with its SHA-256
+    /// digest and the `r` and `s` components of its signature.
+    #[clap(long)]
+    signed_body: bool,
+    /// Cairo representation of the signed body. Only used with `--signed-body`.
+    #[clap(long, value_enum, default_value_t = Encoding::U8)]
+    signed_body_encoding: Encoding,
+    #[clap(flatten)]
+    verify: VerifyArgs,
+    #[clap(flatten)]
+    freshness: FreshnessArgs,
+    #[clap(flatten)]
+    types: CairoTypesArgs,
+    #[clap(flatten)]
+    template: TemplateArgs,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
-#[serde(rename_all = "camelCase")]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
 struct TcbInfoJson {
     tcb_info: TcbInfoInnerJson,
     signature: String,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+/// Just enough of any TCB Info version to pick the model to parse it with.
+#[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
+struct TcbInfoVersionJson {
+    tcb_info: TcbInfoVersionInnerJson,
+}
+
+#[derive(Debug, Deserialize)]
+struct TcbInfoVersionInnerJson {
+    version: u32,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
 struct TcbInfoInnerJson {
     id: String,
     version: u32,
@@ -40,7 +123,7 @@ struct TcbInfoInnerJson {
 }
 
 #[derive(Debug, Deserialize, Serialize)]
-#[serde(rename_all = "camelCase")]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
 struct TdxModuleJson {
     mrsigner: String,
     attributes: String,
@@ -48,7 +131,7 @@ struct TdxModuleJson {
 }
 
 #[derive(Debug, Deserialize, Serialize)]
-#[serde(rename_all = "camelCase")]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
 struct TdxModuleIdentitiesJson {
     id: String,
     mrsigner: String,
@@ -58,7 +141,7 @@ struct TdxModuleIdentitiesJson {
 }
 
 #[derive(Debug, Deserialize, Serialize)]
-#[serde(rename_all = "camelCase")]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
 struct TdxModuleIdentitiesTcbLevelJson {
     tcb: TdxModuleIdentitiesTcbJson,
     tcb_date: String,
@@ -68,13 +151,13 @@ struct TdxModuleIdentitiesTcbLevelJson {
 }
 
 #[derive(Debug, Deserialize, Serialize)]
-#[serde(rename_all = "camelCase")]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
 struct TdxModuleIdentitiesTcbJson {
     isvsvn: u8,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
-#[serde(rename_all = "camelCase")]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
 struct TcbLevelJson {
     tcb: TcbJson,
     tcb_date: String,
@@ -84,15 +167,16 @@ struct TcbLevelJson {
 }
 
 #[derive(Debug, Deserialize, Serialize)]
-#[serde(rename_all = "camelCase")]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
 struct TcbJson {
     sgxtcbcomponents: Vec<TcbComponentJson>,
     pcesvn: u16,
-    tdxtcbcomponents: Vec<TcbComponentJson>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tdxtcbcomponents: Option<Vec<TcbComponentJson>>,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
-#[serde(rename_all = "camelCase")]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
 struct TcbComponentJson {
     svn: u8,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -101,490 +185,1210 @@ struct TcbComponentJson {
     type_: Option<String>,
 }
 
-impl TcbinfoCommand {
-    pub fn run(self) -> Result<()> {
-        let json_content = std::fs::read_to_string(&self.input)?;
-        let tcb_info: TcbInfoJson = serde_json::from_str(&json_content)?;
-
-        let mut output = String::new();
-
-        // Add imports
-        output.push_str("use time::{DateTrait, Month, OffsetDateTimeTrait, TimeTrait};\n");
-        output.push_str("use crate::types::tcbinfo::{\n");
-        output.push_str("    TcbComponent, TcbInfoV3, TcbInfoV3Inner, TcbInfoV3TcbLevel, TcbInfoV3TcbLevelItem, TdxModule,\n");
-        output.push_str("    TdxModuleIdentities, TdxModuleIdentitiesTcbLevel, TdxModuleIdentitiesTcbLevelItem,\n");
-        output.push_str("};\n\n");
-
-        output.push_str("pub fn data() -> TcbInfoV3 {\n");
-
-        // Parse and generate issue_date
-        let issue_date = parse_datetime(&tcb_info.tcb_info.issue_date)?;
-        output.push_str(&format!("    // {}\n", tcb_info.tcb_info.issue_date));
-        output.push_str("    let issue_date = OffsetDateTimeTrait::new_utc(\n");
-        output.push_str(&format!(
-            "        DateTrait::from_calendar_date({}, Month::{}, {}).unwrap(),\n",
-            issue_date.year,
-            month_name(issue_date.month),
-            issue_date.day
-        ));
-        output.push_str(&format!(
-            "        TimeTrait::from_hms_milli({}, {}, {}, {}).unwrap(),\n",
-            issue_date.hour, issue_date.minute, issue_date.second, issue_date.millisecond
-        ));
-        output.push_str("    );\n\n");
-
-        // Parse and generate next_update
-        let next_update = parse_datetime(&tcb_info.tcb_info.next_update)?;
-        output.push_str(&format!("    // {}\n", tcb_info.tcb_info.next_update));
-        output.push_str("    let next_update = OffsetDateTimeTrait::new_utc(\n");
-        output.push_str(&format!(
-            "        DateTrait::from_calendar_date({}, Month::{}, {}).unwrap(),\n",
-            next_update.year,
-            month_name(next_update.month),
-            next_update.day
-        ));
-        output.push_str(&format!(
-            "        TimeTrait::from_hms_milli({}, {}, {}, {}).unwrap(),\n",
-            next_update.hour, next_update.minute, next_update.second, next_update.millisecond
-        ));
-        output.push_str("    );\n\n");
+/// TCB Info v2, still served by PCS for SGX. Normalized to the v3 model before generating code.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+struct TcbInfoV2Json {
+    tcb_info: TcbInfoV2InnerJson,
+    signature: String,
+}
 
-        // Collect unique tcb_dates
-        let mut unique_dates = std::collections::HashSet::new();
-        for tcb_level in &tcb_info.tcb_info.tcb_levels {
-            unique_dates.insert(&tcb_level.tcb_date);
-        }
-        if let Some(tdx_module_identities) = &tcb_info.tcb_info.tdx_module_identities {
-            for identity in tdx_module_identities {
-                for tcb_level in &identity.tcb_levels {
-                    unique_dates.insert(&tcb_level.tcb_date);
-                }
-            }
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+struct TcbInfoV2InnerJson {
+    version: u32,
+    issue_date: String,
+    next_update: String,
+    fmspc: String,
+    pce_id: String,
+    tcb_type: u8,
+    tcb_evaluation_data_number: u32,
+    tcb_levels: Vec<TcbLevelV2Json>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+struct TcbLevelV2Json {
+    tcb: TcbV2Json,
+    tcb_date: String,
+    tcb_status: String,
+    #[serde(rename = "advisoryIDs")]
+    advisory_ids: Option<Vec<String>>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TcbV2Json {
+    sgxtcbcomp01svn: u8,
+    sgxtcbcomp02svn: u8,
+    sgxtcbcomp03svn: u8,
+    sgxtcbcomp04svn: u8,
+    sgxtcbcomp05svn: u8,
+    sgxtcbcomp06svn: u8,
+    sgxtcbcomp07svn: u8,
+    sgxtcbcomp08svn: u8,
+    sgxtcbcomp09svn: u8,
+    sgxtcbcomp10svn: u8,
+    sgxtcbcomp11svn: u8,
+    sgxtcbcomp12svn: u8,
+    sgxtcbcomp13svn: u8,
+    sgxtcbcomp14svn: u8,
+    sgxtcbcomp15svn: u8,
+    sgxtcbcomp16svn: u8,
+    pcesvn: u16,
+}
+
+impl From<TcbInfoV2Json> for TcbInfoJson {
+    fn from(v2: TcbInfoV2Json) -> Self {
+        let inner = v2.tcb_info;
+        Self {
+            tcb_info: TcbInfoInnerJson {
+                // v2 predates TDX, so every v2 TCB Info is an SGX one
+                id: "SGX".to_owned(),
+                version: inner.version,
+                issue_date: inner.issue_date,
+                next_update: inner.next_update,
+                fmspc: inner.fmspc,
+                pce_id: inner.pce_id,
+                tcb_type: inner.tcb_type,
+                tcb_evaluation_data_number: inner.tcb_evaluation_data_number,
+                tdx_module: None,
+                tdx_module_identities: None,
+                tcb_levels: inner.tcb_levels.into_iter().map(Into::into).collect(),
+            },
+            signature: v2.signature,
         }
+    }
+}
 
-        // Sort dates and generate variables
-        let mut sorted_dates: Vec<_> = unique_dates.into_iter().collect();
-        sorted_dates.sort();
-        sorted_dates.reverse(); // Most recent first
-
-        for date_str in &sorted_dates {
-            let date = parse_datetime(date_str)?;
-            let var_name = format!("tcb_date_{}_{:02}_{:02}", date.year, date.month, date.day);
-            output.push_str(&format!("    // {}\n", date_str));
-            output.push_str(&format!(
-                "    let {} = OffsetDateTimeTrait::new_utc(\n",
-                var_name
-            ));
-            output.push_str(&format!(
-                "        DateTrait::from_calendar_date({}, Month::{}, {}).unwrap(),\n",
-                date.year,
-                month_name(date.month),
-                date.day
-            ));
-            output.push_str(&format!(
-                "        TimeTrait::from_hms_milli({}, {}, {}, {}).unwrap(),\n",
-                date.hour, date.minute, date.second, date.millisecond
-            ));
-            output.push_str("    );\n\n");
+impl From<TcbLevelV2Json> for TcbLevelJson {
+    fn from(level: TcbLevelV2Json) -> Self {
+        let tcb = level.tcb;
+        let svns = [
+            tcb.sgxtcbcomp01svn,
+            tcb.sgxtcbcomp02svn,
+            tcb.sgxtcbcomp03svn,
+            tcb.sgxtcbcomp04svn,
+            tcb.sgxtcbcomp05svn,
+            tcb.sgxtcbcomp06svn,
+            tcb.sgxtcbcomp07svn,
+            tcb.sgxtcbcomp08svn,
+            tcb.sgxtcbcomp09svn,
+            tcb.sgxtcbcomp10svn,
+            tcb.sgxtcbcomp11svn,
+            tcb.sgxtcbcomp12svn,
+            tcb.sgxtcbcomp13svn,
+            tcb.sgxtcbcomp14svn,
+            tcb.sgxtcbcomp15svn,
+            tcb.sgxtcbcomp16svn,
+        ];
+
+        Self {
+            tcb: TcbJson {
+                sgxtcbcomponents: svns
+                    .into_iter()
+                    .map(|svn| TcbComponentJson {
+                        svn,
+                        category: None,
+                        type_: None,
+                    })
+                    .collect(),
+                pcesvn: tcb.pcesvn,
+                tdxtcbcomponents: None,
+            },
+            tcb_date: level.tcb_date,
+            tcb_status: level.tcb_status,
+            advisory_ids: level.advisory_ids,
         }
+    }
+}
 
-        // Start generating the struct
-        output.push_str("    TcbInfoV3 {\n");
-        output.push_str("        tcb_info: TcbInfoV3Inner {\n");
-        output.push_str(&format!("            id: \"{}\",\n", tcb_info.tcb_info.id));
-        output.push_str(&format!(
-            "            version: {},\n",
-            tcb_info.tcb_info.version
-        ));
-        output.push_str("            issue_date,\n");
-        output.push_str("            next_update,\n");
-
-        // fmspc
-        let fmspc_bytes = hex::decode(&tcb_info.tcb_info.fmspc)?;
-        output.push_str("            fmspc: [");
-        for (i, byte) in fmspc_bytes.iter().enumerate() {
-            if i > 0 {
-                output.push_str(", ");
-            }
-            output.push_str(&format!("0x{:02x}", byte));
+impl TcbInfoJson {
+    /// Checks the length of every hex-encoded field.
+    fn check_lengths(&self) -> Result<()> {
+        let inner = &self.tcb_info;
+        check_hex("tcbInfo.fmspc", &inner.fmspc, 6)?;
+        check_hex("tcbInfo.pceId", &inner.pce_id, 2)?;
+        if let Some(tdx_module) = &inner.tdx_module {
+            check_hex("tcbInfo.tdxModule.mrsigner", &tdx_module.mrsigner, 48)?;
+            check_hex("tcbInfo.tdxModule.attributes", &tdx_module.attributes, 8)?;
+            check_hex(
+                "tcbInfo.tdxModule.attributesMask",
+                &tdx_module.attributes_mask,
+                8,
+            )?;
         }
-        output.push_str("].span(),\n");
-
-        // pce_id
-        let pce_id_bytes = hex::decode(&tcb_info.tcb_info.pce_id)?;
-        output.push_str("            pce_id: [");
-        for (i, byte) in pce_id_bytes.iter().enumerate() {
-            if i > 0 {
-                output.push_str(", ");
-            }
-            output.push_str(&format!("0x{:02x}", byte));
+        for (ind, identity) in inner.tdx_module_identities.iter().flatten().enumerate() {
+            let path = format!("tcbInfo.tdxModuleIdentities[{ind}]");
+            check_hex(&format!("{path}.mrsigner"), &identity.mrsigner, 48)?;
+            check_hex(&format!("{path}.attributes"), &identity.attributes, 8)?;
+            check_hex(
+                &format!("{path}.attributesMask"),
+                &identity.attributes_mask,
+                8,
+            )?;
         }
-        output.push_str("].span(),\n");
 
-        output.push_str(&format!(
-            "            tcb_type: {},\n",
-            tcb_info.tcb_info.tcb_type
-        ));
-        output.push_str(&format!(
-            "            tcb_evaluation_data_number: {},\n",
-            tcb_info.tcb_info.tcb_evaluation_data_number
-        ));
+        check_hex("signature", &self.signature, 64)
+    }
+}
 
-        // tdx_module
-        if let Some(tdx_module) = &tcb_info.tcb_info.tdx_module {
-            output.push_str("            tdx_module: Option::Some(\n");
-            output.push_str("                TdxModule {\n");
-
-            // mrsigner
-            let mrsigner_bytes = hex::decode(&tdx_module.mrsigner)?;
-            output.push_str("                    mrsigner: array![\n");
-            for (i, chunk) in mrsigner_bytes.chunks(12).enumerate() {
-                if i > 0 {
-                    output.push_str(",\n");
+impl TcbInfoInnerJson {
+    /// Checks that the TDX-only fields are present exactly when `id` is `TDX`.
+    fn validate_tee_type(&self) -> Result<()> {
+        let tdx_levels = self
+            .tcb_levels
+            .iter()
+            .filter(|tcb_level| tcb_level.tcb.tdxtcbcomponents.is_some())
+            .count();
+
+        match TeeType::parse(&self.id)? {
+            TeeType::Sgx => {
+                if self.tdx_module.is_some() || self.tdx_module_identities.is_some() {
+                    eyre::bail!("SGX TCB Info must not have tdxModule or tdxModuleIdentities");
                 }
-                output.push_str("                        ");
-                for (j, byte) in chunk.iter().enumerate() {
-                    if j > 0 {
-                        output.push_str(", ");
-                    }
-                    output.push_str(&format!("0x{:02x}", byte));
+                if tdx_levels > 0 {
+                    eyre::bail!("SGX TCB Info must not have tdxtcbcomponents");
                 }
             }
-            output.push_str(",\n                    ]\n                        .span(),\n");
-
-            // attributes
-            let attributes_bytes = hex::decode(&tdx_module.attributes)?;
-            output.push_str("                    attributes: array![");
-            for (i, byte) in attributes_bytes.iter().enumerate() {
-                if i > 0 {
-                    output.push_str(", ");
+            TeeType::Tdx => {
+                if self.tdx_module.is_none() {
+                    eyre::bail!("TDX TCB Info is missing tdxModule");
                 }
-                output.push_str(&format!("0x{:02x}", byte));
-            }
-            output.push_str("].span(),\n");
-
-            // attributes_mask
-            let attributes_mask_bytes = hex::decode(&tdx_module.attributes_mask)?;
-            output.push_str("                    attributes_mask: array![");
-            for (i, byte) in attributes_mask_bytes.iter().enumerate() {
-                if i > 0 {
-                    output.push_str(", ");
-                }
-                let is_uppercase = tdx_module
-                    .attributes_mask
-                    .chars()
-                    .any(|c| c.is_ascii_uppercase());
-                if is_uppercase {
-                    output.push_str(&format!("0x{:02X}", byte));
-                } else {
-                    output.push_str(&format!("0x{:02x}", byte));
+                if tdx_levels != self.tcb_levels.len() {
+                    eyre::bail!("TDX TCB Info is missing tdxtcbcomponents in some TCB levels");
                 }
             }
-            output.push_str("].span(),\n");
-
-            output.push_str("                },\n");
-            output.push_str("            ),\n");
-        } else {
-            output.push_str("            tdx_module: Option::None,\n");
         }
 
-        // tdx_module_identities
-        if let Some(identities) = &tcb_info.tcb_info.tdx_module_identities {
-            output.push_str("            tdx_module_identities: Option::Some(\n");
-            output.push_str("                array![\n");
-
-            for identity in identities {
-                output.push_str("                    TdxModuleIdentities {\n");
-                output.push_str(&format!(
-                    "                        id: \"{}\",\n",
-                    identity.id
-                ));
-
-                // mrsigner
-                let mrsigner_bytes = hex::decode(&identity.mrsigner)?;
-                output.push_str("                        mrsigner: array![\n");
-                for (i, chunk) in mrsigner_bytes.chunks(12).enumerate() {
-                    if i > 0 {
-                        output.push_str(",\n");
-                    }
-                    output.push_str("                            ");
-                    for (j, byte) in chunk.iter().enumerate() {
-                        if j > 0 {
-                            output.push_str(", ");
-                        }
-                        output.push_str(&format!("0x{:02x}", byte));
-                    }
-                }
-                output.push_str(
-                    ",\n                        ]\n                            .span(),\n",
-                );
-
-                // attributes
-                let attributes_bytes = hex::decode(&identity.attributes)?;
-                output.push_str("                        attributes: array![");
-                for (i, byte) in attributes_bytes.iter().enumerate() {
-                    if i > 0 {
-                        output.push_str(", ");
-                    }
-                    output.push_str(&format!("0x{:02x}", byte));
-                }
-                output.push_str("].span(),\n");
-
-                // attributes_mask
-                let attributes_mask_bytes = hex::decode(&identity.attributes_mask)?;
-                output.push_str("                        attributes_mask: array![");
-                for (i, byte) in attributes_mask_bytes.iter().enumerate() {
-                    if i > 0 {
-                        output.push_str(", ");
-                    }
-                    let is_uppercase = identity
-                        .attributes_mask
-                        .chars()
-                        .any(|c| c.is_ascii_uppercase());
-                    if is_uppercase {
-                        output.push_str(&format!("0x{:02X}", byte));
-                    } else {
-                        output.push_str(&format!("0x{:02x}", byte));
-                    }
-                }
-                output.push_str("]\n                            .span(),\n");
-
-                // tcb_levels
-                output.push_str("                        tcb_levels: array![\n");
-                for tcb_level in &identity.tcb_levels {
-                    output.push_str(
-                        "                            TdxModuleIdentitiesTcbLevelItem {\n",
-                    );
-                    output.push_str(&format!("                                tcb: TdxModuleIdentitiesTcbLevel {{ isvsvn: {} }},\n", tcb_level.tcb.isvsvn));
-
-                    let date = parse_datetime(&tcb_level.tcb_date)?;
-                    let var_name =
-                        format!("tcb_date_{}_{:02}_{:02}", date.year, date.month, date.day);
-                    output.push_str(&format!(
-                        "                                tcb_date: {},\n",
-                        var_name
-                    ));
+        Ok(())
+    }
+}
 
-                    output.push_str(&format!(
-                        "                                tcb_status: \"{}\",\n",
-                        tcb_level.tcb_status
-                    ));
+/// Parses TCB Info of any supported version into the v3 model.
+fn parse_tcb_info(json: &str) -> Result<TcbInfoJson> {
+    let TcbInfoVersionJson { tcb_info } = parse_json(json)?;
+    let tcb_info: TcbInfoJson = match tcb_info.version {
+        2 => parse_json::<TcbInfoV2Json>(json)?.into(),
+        3 => parse_json(json)?,
+        version => eyre::bail!("Unsupported TCB Info version: {version}"),
+    };
+    tcb_info.tcb_info.validate_tee_type()?;
+    tcb_info.check_lengths()?;
+
+    Ok(tcb_info)
+}
 
-                    if let Some(advisory_ids) = &tcb_level.advisory_ids {
-                        output.push_str(
-                            "                                advisory_ids: Option::Some(array![",
-                        );
-                        for (i, id) in advisory_ids.iter().enumerate() {
-                            if i > 0 {
-                                output.push_str(", ");
-                            }
-                            output.push_str(&format!("\"{}\"", id));
-                        }
-                        output.push_str("].span()),\n");
-                    } else {
-                        output.push_str(
-                            "                                advisory_ids: Option::None,\n",
-                        );
-                    }
+/// Signed body of a TCB Info document of any supported version, after validating it.
+pub fn signed_body(json: &str) -> Result<SignedBody> {
+    let tcb_info = parse_tcb_info(json)?;
+    SignedBody::extract(json, "tcbInfo", &tcb_info.signature)
+}
 
-                    output.push_str("                            },\n");
-                }
-                output.push_str("                        ],\n");
+/// Semantic changes between two TCB Info documents of any supported version.
+pub fn diff(old_json: &str, new_json: &str) -> Result<Diff> {
+    let old = parse_tcb_info(old_json)?.tcb_info;
+    let new = parse_tcb_info(new_json)?.tcb_info;
+
+    let mut diff = Diff::default();
+    diff.value("tcbInfo.id", &old.id, &new.id)?;
+    diff.value("tcbInfo.version", &old.version, &new.version)?;
+    diff.value(
+        "tcbInfo.fmspc",
+        &old.fmspc.to_lowercase(),
+        &new.fmspc.to_lowercase(),
+    )?;
+    diff.value(
+        "tcbInfo.pceId",
+        &old.pce_id.to_lowercase(),
+        &new.pce_id.to_lowercase(),
+    )?;
+    diff.date("tcbInfo.issueDate", &old.issue_date, &new.issue_date)?;
+    diff.date("tcbInfo.nextUpdate", &old.next_update, &new.next_update)?;
+    diff.value(
+        "tcbInfo.tcbEvaluationDataNumber",
+        &old.tcb_evaluation_data_number,
+        &new.tcb_evaluation_data_number,
+    )?;
+    diff.value("tcbInfo.tdxModule", &old.tdx_module, &new.tdx_module)?;
+
+    diff.keyed(
+        "tcbInfo.tdxModuleIdentities",
+        old.tdx_module_identities.as_deref().unwrap_or_default(),
+        new.tdx_module_identities.as_deref().unwrap_or_default(),
+        |identity| identity.id.clone(),
+        |diff, path, old, new| {
+            diff.value(&format!("{path}.mrsigner"), &old.mrsigner, &new.mrsigner)?;
+            diff.value(
+                &format!("{path}.attributes"),
+                &old.attributes,
+                &new.attributes,
+            )?;
+            diff.value(
+                &format!("{path}.attributesMask"),
+                &old.attributes_mask,
+                &new.attributes_mask,
+            )?;
+            diff.keyed(
+                &format!("{path}.tcbLevels"),
+                &old.tcb_levels,
+                &new.tcb_levels,
+                |tcb_level| format!("isvsvn={}", tcb_level.tcb.isvsvn),
+                |diff, path, old, new| {
+                    diff_tcb_level(
+                        diff,
+                        path,
+                        (&old.tcb_date, &old.tcb_status, &old.advisory_ids),
+                        (&new.tcb_date, &new.tcb_status, &new.advisory_ids),
+                    )
+                },
+            )
+        },
+    )?;
+
+    diff.keyed(
+        "tcbInfo.tcbLevels",
+        &old.tcb_levels,
+        &new.tcb_levels,
+        tcb_level_key,
+        |diff, path, old, new| {
+            diff_tcb_level(
+                diff,
+                path,
+                (&old.tcb_date, &old.tcb_status, &old.advisory_ids),
+                (&new.tcb_date, &new.tcb_status, &new.advisory_ids),
+            )
+        },
+    )?;
+
+    Ok(diff)
+}
 
-                output.push_str("                    },\n");
+/// Identifies a TCB level by its SVNs, e.g. `sgx=15,15,2,...;pcesvn=13;tdx=5,0,...`.
+fn tcb_level_key(tcb_level: &TcbLevelJson) -> String {
+    let svns = |components: &[TcbComponentJson]| {
+        components
+            .iter()
+            .map(|component| component.svn.to_string())
+            .collect::<Vec<_>>()
+            .join(",")
+    };
+
+    let mut key = format!(
+        "sgx={};pcesvn={}",
+        svns(&tcb_level.tcb.sgxtcbcomponents),
+        tcb_level.tcb.pcesvn
+    );
+    if let Some(components) = &tcb_level.tcb.tdxtcbcomponents {
+        key.push_str(&format!(";tdx={}", svns(components)));
+    }
+
+    key
+}
+
+/// Compares the date, status and advisory IDs of two TCB levels with the same key.
+fn diff_tcb_level(
+    diff: &mut Diff,
+    path: &str,
+    (old_date, old_status, old_advisory_ids): (&String, &String, &Option<Vec<String>>),
+    (new_date, new_status, new_advisory_ids): (&String, &String, &Option<Vec<String>>),
+) -> Result<()> {
+    diff.date(&format!("{path}.tcbDate"), old_date, new_date)?;
+    diff.value(&format!("{path}.tcbStatus"), old_status, new_status)?;
+    diff.set(
+        &format!("{path}.advisoryIDs"),
+        old_advisory_ids.as_deref().unwrap_or_default(),
+        new_advisory_ids.as_deref().unwrap_or_default(),
+    );
+
+    Ok(())
+}
+
+/// Cairo counterparts of the JSON models above, matching the generated data.
+pub fn type_defs(
+    types: &CairoTypes,
+    date_format: DateFormat,
+    string_format: StringFormat,
+    tcb_status_format: TcbStatusFormat,
+) -> Vec<TypeDef> {
+    let date = date_format.cairo_type().to_owned();
+    let bytes = || "Span<u8>".to_owned();
+    let string = || string_format.cairo_type().to_owned();
+    let tcb_status = || tcb_status_format.cairo_type(string_format, &types.name(TCB_STATUS_TYPE));
+    let advisory_ids = || format!("Option<Span<{}>>", string_format.cairo_type());
+
+    let mut type_defs = vec![
+        TypeDef::structure(
+            types.name("TcbInfoV3"),
+            vec![
+                ("tcb_info", types.name("TcbInfoV3Inner")),
+                ("signature", bytes()),
+            ],
+        ),
+        TypeDef::structure(
+            types.name("TcbInfoV3Inner"),
+            vec![
+                ("id", string()),
+                ("version", "u32".to_owned()),
+                ("issue_date", date.clone()),
+                ("next_update", date.clone()),
+                ("fmspc", bytes()),
+                ("pce_id", bytes()),
+                ("tcb_type", "u8".to_owned()),
+                ("tcb_evaluation_data_number", "u32".to_owned()),
+                ("tdx_module", format!("Option<{}>", types.name("TdxModule"))),
+                (
+                    "tdx_module_identities",
+                    format!("Option<Array<{}>>", types.name("TdxModuleIdentities")),
+                ),
+                (
+                    "tcb_levels",
+                    format!("Array<{}>", types.name("TcbInfoV3TcbLevelItem")),
+                ),
+            ],
+        ),
+        TypeDef::structure(
+            types.name("TdxModule"),
+            vec![
+                ("mrsigner", bytes()),
+                ("attributes", bytes()),
+                ("attributes_mask", bytes()),
+            ],
+        ),
+        TypeDef::structure(
+            types.name("TdxModuleIdentities"),
+            vec![
+                ("id", string()),
+                ("mrsigner", bytes()),
+                ("attributes", bytes()),
+                ("attributes_mask", bytes()),
+                (
+                    "tcb_levels",
+                    format!("Array<{}>", types.name("TdxModuleIdentitiesTcbLevelItem")),
+                ),
+            ],
+        ),
+        TypeDef::structure(
+            types.name("TdxModuleIdentitiesTcbLevelItem"),
+            vec![
+                ("tcb", types.name("TdxModuleIdentitiesTcbLevel")),
+                ("tcb_date", date.clone()),
+                ("tcb_status", tcb_status()),
+                ("advisory_ids", advisory_ids()),
+            ],
+        ),
+        TypeDef::structure(
+            types.name("TdxModuleIdentitiesTcbLevel"),
+            vec![("isvsvn", "u8".to_owned())],
+        ),
+        TypeDef::structure(
+            types.name("TcbInfoV3TcbLevelItem"),
+            vec![
+                ("tcb", types.name("TcbInfoV3TcbLevel")),
+                ("tcb_date", date),
+                ("tcb_status", tcb_status()),
+                ("advisory_ids", advisory_ids()),
+            ],
+        ),
+        TypeDef::structure(
+            types.name("TcbInfoV3TcbLevel"),
+            vec![
+                (
+                    "sgxtcbcomponents",
+                    format!("Array<{}>", types.name("TcbComponent")),
+                ),
+                ("pcesvn", "u16".to_owned()),
+                (
+                    "tdxtcbcomponents",
+                    format!("Option<Array<{}>>", types.name("TcbComponent")),
+                ),
+            ],
+        ),
+        TypeDef::structure(
+            types.name("TcbComponent"),
+            vec![
+                ("svn", "u8".to_owned()),
+                ("category", format!("Option<{}>", string())),
+                ("type_", format!("Option<{}>", string())),
+            ],
+        ),
+    ];
+    if tcb_status_format == TcbStatusFormat::Enum {
+        type_defs.push(TypeDef::tcb_status(types.name(TCB_STATUS_TYPE)));
+    }
+    if !date_format.implements_serde() {
+        TypeDef::drop_serde(&mut type_defs, date_format.cairo_type());
+    }
+
+    type_defs
+}
+
+impl TcbinfoCommand {
+    pub fn run(self) -> Result<()> {
+        let types = self.types.resolve("tcbinfo", TYPES_MODULE, &TYPE_NAMES)?;
+
+        let bundle = self.bundle.as_deref().map(Bundle::read).transpose()?;
+        let json_content = match (&self.store, &self.quote) {
+            (Some(store), Some(quote)) => {
+                let quote = Quote::from_bytes(&std::fs::read(quote)?)?;
+                std::fs::read_to_string(
+                    CollateralStore::open(store)?
+                        .tcb_info_for_quote(&quote, self.tcb_evaluation_data_number)?,
+                )?
             }
+            _ => match &bundle {
+                Some(bundle) => String::from_utf8(bundle.get(BundleItem::Tcbinfo)?)?,
+                None => String::from_utf8(read_input(
+                    self.input.as_deref(),
+                    None,
+                    BundleItem::Tcbinfo,
+                )?)?,
+            },
+        };
+        let tcb_info = parse_tcb_info(&json_content)?;
+        let signed_body = SignedBody::extract(&json_content, "tcbInfo", &tcb_info.signature)?;
+        self.verify.verify(
+            &signed_body,
+            bundle.as_ref(),
+            BundleItem::TcbinfoIssuerChain,
+        )?;
+        let inner = &tcb_info.tcb_info;
+
+        let mut validities = vec![Validity::collateral(
+            "TCB Info",
+            &inner.issue_date,
+            &inner.next_update,
+        )?];
+        if let Some(issuer_chain) = self
+            .verify
+            .issuer_chain(bundle.as_ref(), BundleItem::TcbinfoIssuerChain)?
+        {
+            validities.extend(Validity::certificates("Issuer chain", &issuer_chain)?);
+        }
+        self.freshness.check(&validities)?;
 
-            output.push_str("                ],\n");
-            output.push_str("            ),\n");
-        } else {
-            output.push_str("            tdx_module_identities: Option::None,\n");
+        let tcb_status_type = types.name(TCB_STATUS_TYPE);
+
+        let mut imports = TYPE_NAMES.to_vec();
+        if !self.tcb_status.imports_enum() {
+            imports.retain(|name| *name != TCB_STATUS_TYPE);
         }
 
-        // tcb_levels
-        output.push_str("            tcb_levels: array![\n");
-        for tcb_level in &tcb_info.tcb_info.tcb_levels {
-            output.push_str("                TcbInfoV3TcbLevelItem {\n");
-            output.push_str("                    tcb: TcbInfoV3TcbLevel {\n");
-
-            // sgxtcbcomponents
-            output.push_str("                        sgxtcbcomponents: array![\n");
-            for component in &tcb_level.tcb.sgxtcbcomponents {
-                output.push_str("                            TcbComponent {\n");
-                output.push_str(&format!(
-                    "                                svn: {},\n",
-                    component.svn
-                ));
-
-                if let Some(category) = &component.category {
-                    output.push_str(&format!(
-                        "                                category: Option::Some(\"{}\"),\n",
-                        category
-                    ));
-                } else {
-                    output.push_str("                                category: Option::None,\n");
-                }
+        // Collect tcb_dates from both platform and TDX module levels
+        let mut tcb_dates = inner
+            .tcb_levels
+            .iter()
+            .map(|tcb_level| tcb_level.tcb_date.as_str())
+            .collect::<Vec<_>>();
+        for identity in inner.tdx_module_identities.iter().flatten() {
+            tcb_dates.extend(
+                identity
+                    .tcb_levels
+                    .iter()
+                    .map(|tcb_level| tcb_level.tcb_date.as_str()),
+            );
+        }
+
+        let mut lets = vec![
+            Let::datetime("issue_date", &inner.issue_date, self.date_format)?,
+            Let::datetime("next_update", &inner.next_update, self.date_format)?,
+        ];
+        lets.extend(tcb_date_lets(tcb_dates, self.date_format)?);
+
+        let data = self.data(&types, &tcb_info)?;
+
+        let generated = Generated {
+            date_imports: self.date_format.imports().unwrap_or_default().to_owned(),
+            type_imports: render_use(types.module(), &types.imports(&imports)),
+            date_let_type: date_type(self.date_format),
+            type_defs: self
+                .tcb_status
+                .type_def(&tcb_status_type)
+                .map(|type_def| type_def.render() + "\n")
+                .unwrap_or_default(),
+            data: Function {
+                name: "data".to_owned(),
+                ret: types.name("TcbInfoV3"),
+                lets,
+                body: data,
+            }
+            .render(),
+            signed_body: if self.signed_body {
+                signed_body.render(self.signed_body_encoding)?
+            } else {
+                String::new()
+            },
+            types_module: types.module().to_owned(),
+            types: types.names(&TYPE_NAMES),
+        };
+
+        let output = self.template.render(
+            TemplateKind::Tcbinfo,
+            &tcb_info,
+            generated,
+            self.date_format,
+            self.string_format,
+            self.tcb_status.tcb_status_format,
+        )?;
+        std::fs::write(&self.output, output)?;
 
-                if let Some(type_) = &component.type_ {
-                    output.push_str(&format!(
-                        "                                type_: Option::Some(\"{}\"),\n",
-                        type_
+        Ok(())
+    }
+
+    /// Returns the tcbinfo as a struct literal, referencing the dates bound by `tcb_date_lets`.
+    fn data(&self, types: &CairoTypes, tcb_info: &TcbInfoJson) -> Result<Expr> {
+        let inner = &tcb_info.tcb_info;
+        let tcb_status_type = types.name(TCB_STATUS_TYPE);
+        let tcb_status = |status: &str| {
+            Expr::tcb_status(
+                status,
+                self.tcb_status.tcb_status_format,
+                self.string_format,
+                &tcb_status_type,
+            )
+        };
+
+        // tdx_module
+        let tdx_module = match &inner.tdx_module {
+            Some(tdx_module) => Some(Expr::structure(
+                types.name("TdxModule"),
+                vec![
+                    (
+                        "mrsigner",
+                        Expr::bytes(&hex::decode(&tdx_module.mrsigner)?).span(),
+                    ),
+                    (
+                        "attributes",
+                        Expr::bytes(&hex::decode(&tdx_module.attributes)?).span(),
+                    ),
+                    (
+                        "attributes_mask",
+                        Expr::bytes(&hex::decode(&tdx_module.attributes_mask)?).span(),
+                    ),
+                ],
+            )),
+            None => None,
+        };
+
+        // tdx_module_identities
+        let tdx_module_identities = match &inner.tdx_module_identities {
+            Some(identities) => {
+                let mut items = vec![];
+                for identity in identities {
+                    let mut tcb_levels = vec![];
+                    for tcb_level in &identity.tcb_levels {
+                        tcb_levels.push(Expr::structure(
+                            types.name("TdxModuleIdentitiesTcbLevelItem"),
+                            vec![
+                                (
+                                    "tcb",
+                                    Expr::structure(
+                                        types.name("TdxModuleIdentitiesTcbLevel"),
+                                        vec![("isvsvn", Expr::int(tcb_level.tcb.isvsvn))],
+                                    ),
+                                ),
+                                ("tcb_date", tcb_date_var(&tcb_level.tcb_date)?),
+                                ("tcb_status", tcb_status(&tcb_level.tcb_status)?),
+                                (
+                                    "advisory_ids",
+                                    advisory_ids(&tcb_level.advisory_ids, self.string_format)?,
+                                ),
+                            ],
+                        ));
+                    }
+
+                    items.push(Expr::structure(
+                        types.name("TdxModuleIdentities"),
+                        vec![
+                            ("id", Expr::string(&identity.id, self.string_format)?),
+                            (
+                                "mrsigner",
+                                Expr::bytes(&hex::decode(&identity.mrsigner)?).span(),
+                            ),
+                            (
+                                "attributes",
+                                Expr::bytes(&hex::decode(&identity.attributes)?).span(),
+                            ),
+                            (
+                                "attributes_mask",
+                                Expr::bytes(&hex::decode(&identity.attributes_mask)?).span(),
+                            ),
+                            ("tcb_levels", Expr::array(tcb_levels)),
+                        ],
                     ));
-                } else {
-                    output.push_str("                                type_: Option::None,\n");
                 }
 
-                output.push_str("                            },\n");
+                Some(Expr::array(items))
             }
-            output.push_str("                        ],\n");
+            None => None,
+        };
 
-            output.push_str(&format!(
-                "                        pcesvn: {},\n",
-                tcb_level.tcb.pcesvn
+        // tcb_levels
+        let mut tcb_levels = vec![];
+        for tcb_level in &inner.tcb_levels {
+            tcb_levels.push(Expr::structure(
+                types.name("TcbInfoV3TcbLevelItem"),
+                vec![
+                    (
+                        "tcb",
+                        Expr::structure(
+                            types.name("TcbInfoV3TcbLevel"),
+                            vec![
+                                (
+                                    "sgxtcbcomponents",
+                                    tcb_components(
+                                        types,
+                                        &tcb_level.tcb.sgxtcbcomponents,
+                                        self.string_format,
+                                    )?,
+                                ),
+                                ("pcesvn", Expr::int(tcb_level.tcb.pcesvn)),
+                                (
+                                    "tdxtcbcomponents",
+                                    Expr::option(
+                                        tcb_level
+                                            .tcb
+                                            .tdxtcbcomponents
+                                            .as_deref()
+                                            .map(|components| {
+                                                tcb_components(
+                                                    types,
+                                                    components,
+                                                    self.string_format,
+                                                )
+                                            })
+                                            .transpose()?,
+                                    ),
+                                ),
+                            ],
+                        ),
+                    ),
+                    ("tcb_date", tcb_date_var(&tcb_level.tcb_date)?),
+                    ("tcb_status", tcb_status(&tcb_level.tcb_status)?),
+                    (
+                        "advisory_ids",
+                        advisory_ids(&tcb_level.advisory_ids, self.string_format)?,
+                    ),
+                ],
             ));
+        }
 
-            // tdxtcbcomponents
-            output.push_str("                        tdxtcbcomponents: Option::Some(\n");
-            output.push_str("                            array![\n");
-            for component in &tcb_level.tcb.tdxtcbcomponents {
-                output.push_str("                                TcbComponent {\n");
-                output.push_str(&format!(
-                    "                                    svn: {},\n",
-                    component.svn
-                ));
-
-                if let Some(category) = &component.category {
-                    output.push_str(&format!(
-                        "                                    category: Option::Some(\"{}\"),\n",
-                        category
-                    ));
-                } else {
-                    output
-                        .push_str("                                    category: Option::None,\n");
-                }
+        Ok(Expr::structure(
+            types.name("TcbInfoV3"),
+            vec![
+                (
+                    "tcb_info",
+                    Expr::structure(
+                        types.name("TcbInfoV3Inner"),
+                        vec![
+                            ("id", Expr::string(&inner.id, self.string_format)?),
+                            ("version", Expr::int(inner.version)),
+                            ("issue_date", Expr::raw("issue_date")),
+                            ("next_update", Expr::raw("next_update")),
+                            (
+                                "fmspc",
+                                Expr::fixed_bytes(&hex::decode(&inner.fmspc)?).span(),
+                            ),
+                            (
+                                "pce_id",
+                                Expr::fixed_bytes(&hex::decode(&inner.pce_id)?).span(),
+                            ),
+                            ("tcb_type", Expr::int(inner.tcb_type)),
+                            (
+                                "tcb_evaluation_data_number",
+                                Expr::int(inner.tcb_evaluation_data_number),
+                            ),
+                            ("tdx_module", Expr::option(tdx_module)),
+                            ("tdx_module_identities", Expr::option(tdx_module_identities)),
+                            ("tcb_levels", Expr::array(tcb_levels)),
+                        ],
+                    ),
+                ),
+                (
+                    "signature",
+                    Expr::bytes(&hex::decode(&tcb_info.signature)?).span(),
+                ),
+            ],
+        ))
+    }
+}
 
-                if let Some(type_) = &component.type_ {
-                    output.push_str(&format!(
-                        "                                    type_: Option::Some(\"{}\"),\n",
-                        type_
-                    ));
-                } else {
-                    output.push_str("                                    type_: Option::None,\n");
-                }
+fn tcb_components(
+    types: &CairoTypes,
+    components: &[TcbComponentJson],
+    format: StringFormat,
+) -> Result<Expr> {
+    let mut items = vec![];
+    for component in components {
+        items.push(Expr::structure(
+            types.name("TcbComponent"),
+            vec![
+                ("svn", Expr::int(component.svn)),
+                ("category", optional_string(&component.category, format)?),
+                ("type_", optional_string(&component.type_, format)?),
+            ],
+        ));
+    }
 
-                output.push_str("                                },\n");
-            }
-            output.push_str("                            ],\n");
-            output.push_str("                        ),\n");
+    Ok(Expr::array(items))
+}
 
-            output.push_str("                    },\n");
+fn optional_string(value: &Option<String>, format: StringFormat) -> Result<Expr> {
+    Ok(Expr::option(
+        value
+            .as_deref()
+            .map(|value| Expr::string(value, format))
+            .transpose()?,
+    ))
+}
 
-            let date = parse_datetime(&tcb_level.tcb_date)?;
-            let var_name = format!("tcb_date_{}_{:02}_{:02}", date.year, date.month, date.day);
-            output.push_str(&format!("                    tcb_date: {},\n", var_name));
+fn advisory_ids(advisory_ids: &Option<Vec<String>>, format: StringFormat) -> Result<Expr> {
+    let Some(advisory_ids) = advisory_ids else {
+        return Ok(Expr::none());
+    };
 
-            output.push_str(&format!(
-                "                    tcb_status: \"{}\",\n",
-                tcb_level.tcb_status
-            ));
+    let items = advisory_ids
+        .iter()
+        .map(|id| Expr::string(id, format))
+        .collect::<Result<Vec<_>>>()?;
+    Ok(Expr::some(Expr::array(items).span()))
+}
 
-            if let Some(advisory_ids) = &tcb_level.advisory_ids {
-                output.push_str("                    advisory_ids: Option::Some(array![");
-                for (i, id) in advisory_ids.iter().enumerate() {
-                    if i > 0 {
-                        output.push_str(", ");
-                    }
-                    output.push_str(&format!("\"{}\"", id));
-                }
-                output.push_str("].span()),\n");
-            } else {
-                output.push_str("                    advisory_ids: Option::None,\n");
-            }
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        bundle::BUNDLE_VERSION,
+        diff::DiffFormat,
+        test_utils::{Pki, assert_matches_type_defs, run_command, run_preprocess, tcb_info_json},
+    };
+
+    /// TCB Info v2 with a single TCB level, whose `sgxtcbcompNNsvn` is `NN` and pcesvn 13.
+    fn tcb_info_v2_json() -> String {
+        let svns = (1..=16)
+            .map(|index| format!(r#""sgxtcbcomp{index:02}svn":{index}"#))
+            .collect::<Vec<_>>()
+            .join(",");
+
+        format!(
+            r#"{{"tcbInfo":{{"version":2,"issueDate":"2024-03-18T10:35:22Z","nextUpdate":"2024-04-17T10:35:22Z","fmspc":"00906ED50000","pceId":"0000","tcbType":0,"tcbEvaluationDataNumber":16,"tcbLevels":[{{"tcb":{{{svns},"pcesvn":13}},"tcbDate":"2024-03-13T00:00:00Z","tcbStatus":"OutOfDate","advisoryIDs":["INTEL-SA-00334"]}}]}},"signature":"{}"}}"#,
+            "00".repeat(64)
+        )
+    }
+
+    /// Runs `tcbinfo` on `json` with `args`, returning the generated Cairo. The signature of
+    /// `json` is not verified.
+    fn generate(json: &str, args: &[&str]) -> Result<String> {
+        let args = [&["--allow-unverified"], args].concat();
+        run_preprocess(json.as_bytes(), &args, TcbinfoCommand::run)
+    }
+
+    #[test]
+    fn normalizes_v2_to_v3_model() {
+        let tcb_info = parse_tcb_info(&tcb_info_v2_json()).unwrap().tcb_info;
+
+        assert_eq!(tcb_info.id, "SGX");
+        assert_eq!(tcb_info.version, 2);
+        assert_eq!(tcb_info.fmspc, "00906ED50000");
+        assert!(tcb_info.tdx_module.is_none());
+        assert!(tcb_info.tdx_module_identities.is_none());
+
+        let [tcb_level] = tcb_info.tcb_levels.as_slice() else {
+            panic!("expected a single TCB level");
+        };
+        let svns = tcb_level
+            .tcb
+            .sgxtcbcomponents
+            .iter()
+            .map(|component| component.svn)
+            .collect::<Vec<_>>();
+        assert_eq!(svns, (1..=16).collect::<Vec<_>>());
+        assert!(
+            tcb_level
+                .tcb
+                .sgxtcbcomponents
+                .iter()
+                .all(|component| { component.category.is_none() && component.type_.is_none() })
+        );
+        assert_eq!(tcb_level.tcb.pcesvn, 13);
+        assert!(tcb_level.tcb.tdxtcbcomponents.is_none());
+        assert_eq!(tcb_level.tcb_status, "OutOfDate");
+        assert_eq!(
+            tcb_level.advisory_ids.as_deref(),
+            Some(&["INTEL-SA-00334".to_owned()][..])
+        );
+    }
+
+    #[test]
+    fn emits_v2_with_fields_missing_from_v2() {
+        let cairo = generate(&tcb_info_v2_json(), &[]).unwrap();
+
+        assert!(cairo.contains("            id: \"SGX\",\n            version: 2,\n"));
+        assert!(cairo.contains(
+            "            tdx_module: Option::None,\n            tdx_module_identities: Option::None,\n"
+        ));
+        assert!(cairo.contains(
+            "TcbComponent {\n                                svn: 16,\n                                \
+             category: Option::None,\n                                type_: Option::None,\n"
+        ));
+        assert!(
+            cairo
+                .contains("pcesvn: 13,\n                        tdxtcbcomponents: Option::None,\n")
+        );
+    }
 
-            output.push_str("                },\n");
+    #[test]
+    fn rejects_unsupported_versions() {
+        for version in [1, 4] {
+            let json =
+                tcb_info_v2_json().replace(r#""version":2"#, &format!(r#""version":{version}"#));
+            assert_eq!(
+                generate(&json, &[]).unwrap_err().to_string(),
+                format!("Unsupported TCB Info version: {version}")
+            );
         }
-        output.push_str("            ],\n");
+    }
 
-        output.push_str("        },\n");
+    /// TDX TCB Info v3 with a single TCB level and TDX module.
+    fn tcb_info_v3_tdx() -> serde_json::Value {
+        let components = vec![serde_json::json!({"svn": 2}); 16];
+        serde_json::json!({
+            "tcbInfo": {
+                "id": "TDX",
+                "version": 3,
+                "issueDate": "2024-03-18T10:35:22Z",
+                "nextUpdate": "2024-04-17T10:35:22Z",
+                "fmspc": "00806F050000",
+                "pceId": "0000",
+                "tcbType": 0,
+                "tcbEvaluationDataNumber": 16,
+                "tdxModule": {
+                    "mrsigner": "00".repeat(48),
+                    "attributes": "0000000000000000",
+                    "attributesMask": "FFFFFFFFFFFFFFFF"
+                },
+                "tcbLevels": [{
+                    "tcb": {
+                        "sgxtcbcomponents": components,
+                        "pcesvn": 13,
+                        "tdxtcbcomponents": components
+                    },
+                    "tcbDate": "2024-03-13T00:00:00Z",
+                    "tcbStatus": "UpToDate"
+                }]
+            },
+            "signature": "00".repeat(64)
+        })
+    }
 
-        // signature
-        let signature_bytes = hex::decode(&tcb_info.signature)?;
-        output.push_str("        signature: array![");
-        for (i, chunk) in signature_bytes.chunks(16).enumerate() {
-            if i > 0 {
-                output.push(',');
-            }
-            output.push_str("\n            ");
-            for (j, byte) in chunk.iter().enumerate() {
-                if j > 0 {
-                    output.push_str(", ");
-                }
-                output.push_str(&format!("0x{:02x}", byte));
-            }
+    /// SGX TCB Info v3, i.e. `tcb_info_v3_tdx` without any TDX-only field.
+    fn tcb_info_v3_sgx() -> serde_json::Value {
+        let mut json = tcb_info_v3_tdx();
+        let tcb_info = &mut json["tcbInfo"];
+        tcb_info["id"] = "SGX".into();
+        tcb_info.as_object_mut().unwrap().remove("tdxModule");
+        tcb_info["tcbLevels"][0]["tcb"]
+            .as_object_mut()
+            .unwrap()
+            .remove("tdxtcbcomponents");
+        json
+    }
+
+    #[test]
+    fn accepts_sgx_v3_without_tdx_fields() {
+        let cairo = generate(&tcb_info_v3_sgx().to_string(), &[]).unwrap();
+
+        assert!(cairo.contains("            id: \"SGX\",\n            version: 3,\n"));
+        assert!(cairo.contains("            tdx_module: Option::None,\n"));
+        assert!(cairo.contains("tdxtcbcomponents: Option::None,\n"));
+
+        let cairo = generate(&tcb_info_v3_tdx().to_string(), &[]).unwrap();
+        assert!(cairo.contains("            tdx_module: Option::Some(\n"));
+        assert!(cairo.contains("tdxtcbcomponents: Option::Some(\n"));
+    }
+
+    #[test]
+    fn rejects_tdx_without_tdx_fields() {
+        let mut json = tcb_info_v3_tdx();
+        json["tcbInfo"].as_object_mut().unwrap().remove("tdxModule");
+        assert_eq!(
+            generate(&json.to_string(), &[]).unwrap_err().to_string(),
+            "TDX TCB Info is missing tdxModule"
+        );
+
+        let mut json = tcb_info_v3_tdx();
+        json["tcbInfo"]["tcbLevels"][0]["tcb"]
+            .as_object_mut()
+            .unwrap()
+            .remove("tdxtcbcomponents");
+        assert_eq!(
+            generate(&json.to_string(), &[]).unwrap_err().to_string(),
+            "TDX TCB Info is missing tdxtcbcomponents in some TCB levels"
+        );
+    }
+
+    #[test]
+    fn rejects_id_mismatching_tee_type() {
+        let mut json = tcb_info_v3_tdx();
+        json["tcbInfo"]["id"] = "SGX".into();
+        assert_eq!(
+            generate(&json.to_string(), &[]).unwrap_err().to_string(),
+            "SGX TCB Info must not have tdxModule or tdxModuleIdentities"
+        );
+
+        let mut json = tcb_info_v3_sgx();
+        json["tcbInfo"]["tcbLevels"][0]["tcb"]["tdxtcbcomponents"] =
+            tcb_info_v3_tdx()["tcbInfo"]["tcbLevels"][0]["tcb"]["tdxtcbcomponents"].take();
+        assert_eq!(
+            generate(&json.to_string(), &[]).unwrap_err().to_string(),
+            "SGX TCB Info must not have tdxtcbcomponents"
+        );
+
+        let mut json = tcb_info_v3_sgx();
+        json["tcbInfo"]["id"] = "SGX_TDX".into();
+        assert_eq!(
+            generate(&json.to_string(), &[]).unwrap_err().to_string(),
+            "Unknown TCB Info id: SGX_TDX, expected SGX or TDX"
+        );
+    }
+
+    #[test]
+    fn diff_reports_level_changes_by_svns() {
+        let old = tcb_info_v2_json();
+        let new = old
+            .replace(r#""OutOfDate""#, r#""UpToDate""#)
+            .replace(r#""INTEL-SA-00334""#, r#""INTEL-SA-00615""#);
+        let key = format!(
+            "sgx={};pcesvn=13",
+            (1..=16)
+                .map(|svn| svn.to_string())
+                .collect::<Vec<_>>()
+                .join(",")
+        );
+
+        assert_eq!(
+            diff(&old, &new).unwrap().render(DiffFormat::Text).unwrap(),
+            format!(
+                "~ tcbInfo.tcbLevels[{key}].tcbStatus: \"OutOfDate\" -> \"UpToDate\"\n\
+                 - tcbInfo.tcbLevels[{key}].advisoryIDs: \"INTEL-SA-00334\"\n\
+                 + tcbInfo.tcbLevels[{key}].advisoryIDs: \"INTEL-SA-00615\"\n"
+            )
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_documents_with_their_json_path() {
+        let json = tcb_info_v3_tdx().to_string();
+        for (from, to, expected) in [
+            (
+                r#""pcesvn":13"#,
+                r#""cpusvn":"00","pcesvn":13"#,
+                "Invalid JSON at tcbInfo.tcbLevels[0].tcb.cpusvn: unknown field `cpusvn`",
+            ),
+            (
+                r#""tcbType":0"#,
+                r#""tcbInfoUrl":"","tcbType":0"#,
+                "Invalid JSON at tcbInfo.tcbInfoUrl: unknown field `tcbInfoUrl`",
+            ),
+            (
+                r#"{"svn":2}],"tdxtcbcomponents""#,
+                r#"{"svn":"2"}],"tdxtcbcomponents""#,
+                "Invalid JSON at tcbInfo.tcbLevels[0].tcb.sgxtcbcomponents[15].svn: invalid type",
+            ),
+            (
+                r#""fmspc":"00806F050000""#,
+                r#""fmspc":"00806F0500""#,
+                "Invalid length at tcbInfo.fmspc: expected 6 bytes, got 5",
+            ),
+            (
+                r#""pceId":"0000""#,
+                r#""pceId":"00000""#,
+                "Invalid hex string at tcbInfo.pceId: ",
+            ),
+            (
+                r#""signature":"00"#,
+                r#""signature":""#,
+                "Invalid length at signature: expected 64 bytes, got 63",
+            ),
+        ] {
+            assert!(json.contains(from), "{from}");
+            let error = parse_tcb_info(&json.replacen(from, to, 1)).unwrap_err();
+            assert!(error.to_string().starts_with(expected), "{error}");
         }
-        output.push_str(",\n        ].span(),\n");
+    }
 
-        output.push_str("    }\n");
-        output.push_str("}\n");
+    const LEVELS: [(u16, &str, &str); 2] = [
+        (13, "2024-03-13T00:00:00Z", "UpToDate"),
+        (5, "2018-01-04T00:00:00Z", "OutOfDate"),
+    ];
+
+    fn write_bundle(dir: &std::path::Path, items: &[(BundleItem, &[u8])]) -> String {
+        let path = dir.join("bundle.json");
+        let mut bundle = Bundle {
+            version: BUNDLE_VERSION,
+            ..Default::default()
+        };
+        for (item, contents) in items {
+            bundle.insert(*item, contents);
+        }
+        bundle.write(&path).unwrap();
 
-        std::fs::write(&self.output, output)?;
+        path.to_str().unwrap().to_owned()
+    }
 
-        Ok(())
+    /// Bundle of TCB Info signed by `pki`, with its issuer chain and the given root CA.
+    fn write_signed_bundle(dir: &std::path::Path, pki: &Pki, root_ca: &[u8]) -> String {
+        write_bundle(
+            dir,
+            &[
+                (
+                    BundleItem::Tcbinfo,
+                    tcb_info_json(Some(pki), &LEVELS).as_bytes(),
+                ),
+                (BundleItem::TcbinfoIssuerChain, pki.chain_pem().as_bytes()),
+                (BundleItem::RootCa, root_ca),
+            ],
+        )
     }
-}
 
-struct DateTime {
-    year: i32,
-    month: u8,
-    day: u8,
-    hour: u8,
-    minute: u8,
-    second: u8,
-    millisecond: u16,
-}
+    #[test]
+    fn verifies_bundle_with_trusted_root() {
+        let pki = Pki::new("Test");
+        let dir = tempfile::tempdir().unwrap();
+        let bundle = write_signed_bundle(dir.path(), &pki, &pki.root_der());
+        let root_ca = dir.path().join("root.pem");
+        std::fs::write(&root_ca, pki.root.pem()).unwrap();
 
-fn parse_datetime(datetime_str: &str) -> Result<DateTime> {
-    // Parse ISO 8601 datetime string like "2025-02-13T03:39:00Z"
-    let datetime_str = datetime_str.trim_end_matches('Z');
-    let parts: Vec<&str> = datetime_str.split('T').collect();
-    if parts.len() != 2 {
-        return Err(eyre::eyre!("Invalid datetime format"));
+        let args = ["--bundle", &bundle, "--root-ca", root_ca.to_str().unwrap()];
+        run_command(&args, TcbinfoCommand::run).unwrap();
     }
 
-    let date_parts: Vec<&str> = parts[0].split('-').collect();
-    if date_parts.len() != 3 {
-        return Err(eyre::eyre!("Invalid date format"));
+    #[test]
+    fn rejects_self_rooted_bundle() {
+        let pki = Pki::new("Test");
+        let dir = tempfile::tempdir().unwrap();
+        let bundle = write_signed_bundle(dir.path(), &pki, &pki.root_der());
+
+        // Checked against the built-in Intel root
+        let err = run_command(&["--bundle", &bundle], TcbinfoCommand::run).unwrap_err();
+        assert!(
+            format!("{err:?}").contains("The root-ca bundle item is not the trusted root CA"),
+            "{err:?}"
+        );
+
+        // Or against the one given, which the bundle's own root must then be
+        let other_root = dir.path().join("other.der");
+        std::fs::write(&other_root, Pki::new("Other").root_der()).unwrap();
+        let args = [
+            "--bundle",
+            &bundle,
+            "--root-ca",
+            other_root.to_str().unwrap(),
+        ];
+        let err = run_command(&args, TcbinfoCommand::run).unwrap_err();
+        assert!(
+            format!("{err:?}").contains("The root-ca bundle item is not the trusted root CA"),
+            "{err:?}"
+        );
     }
 
-    let time_parts: Vec<&str> = parts[1].split(':').collect();
-    if time_parts.len() != 3 {
-        return Err(eyre::eyre!("Invalid time format"));
+    #[test]
+    fn requires_issuer_chain_without_bundle_item() {
+        let pki = Pki::new("Test");
+        let dir = tempfile::tempdir().unwrap();
+        let bundle = write_bundle(
+            dir.path(),
+            &[(
+                BundleItem::Tcbinfo,
+                tcb_info_json(Some(&pki), &LEVELS).as_bytes(),
+            )],
+        );
+
+        let err = run_command(&["--bundle", &bundle], TcbinfoCommand::run).unwrap_err();
+        assert!(
+            format!("{err:?}").contains("found as the tcbinfo-issuer-chain bundle item"),
+            "{err:?}"
+        );
     }
 
-    Ok(DateTime {
-        year: date_parts[0].parse()?,
-        month: date_parts[1].parse()?,
-        day: date_parts[2].parse()?,
-        hour: time_parts[0].parse()?,
-        minute: time_parts[1].parse()?,
-        second: time_parts[2].parse()?,
-        millisecond: 0,
-    })
-}
+    #[test]
+    fn type_defs_match_data() {
+        let command = TcbinfoCommand::try_parse_from([
+            "tcbinfo",
+            "--output",
+            "out.cairo",
+            "--input",
+            "in.json",
+            "--tcb-status-format",
+            "enum",
+        ])
+        .unwrap();
+        let types = command
+            .types
+            .resolve("tcbinfo", TYPES_MODULE, &TYPE_NAMES)
+            .unwrap();
+        let type_defs = type_defs(
+            &types,
+            command.date_format,
+            command.string_format,
+            command.tcb_status.tcb_status_format,
+        );
+
+        let mut json = tcb_info_v3_tdx();
+        json["tcbInfo"]["tdxModuleIdentities"] = serde_json::json!([{
+            "id": "TDX_01",
+            "mrsigner": "00".repeat(48),
+            "attributes": "0000000000000000",
+            "attributesMask": "FFFFFFFFFFFFFFFF",
+            "tcbLevels": [{
+                "tcb": {"isvsvn": 2},
+                "tcbDate": "2024-03-13T00:00:00Z",
+                "tcbStatus": "UpToDate"
+            }]
+        }]);
+
+        let tcb_info = parse_tcb_info(&json.to_string()).unwrap();
+        let used = assert_matches_type_defs(&command.data(&types, &tcb_info).unwrap(), &type_defs);
+        assert_eq!(used, TYPE_NAMES.map(str::to_owned).into());
+    }
+
+    /// Renders the text diff between TCB Infos with the given levels.
+    fn diff_text(old: &[(u16, &str, &str)], new: &[(u16, &str, &str)]) -> String {
+        diff(&tcb_info_json(None, old), &tcb_info_json(None, new))
+            .unwrap()
+            .render(DiffFormat::Text)
+            .unwrap()
+    }
+
+    #[test]
+    fn diff_ignores_level_order() {
+        let reordered = [LEVELS[1], LEVELS[0]];
+        assert_eq!(diff_text(&LEVELS, &reordered), "");
+    }
+
+    #[test]
+    fn diff_reports_added_and_removed_levels() {
+        let key = format!("sgx={};pcesvn", vec!["2"; 16].join(","));
+        let new = [LEVELS[0], (17, "2024-08-14T00:00:00Z", "UpToDate")];
+        let text = diff_text(&LEVELS, &new);
+
+        assert!(text.contains(&format!("\n+ tcbInfo.tcbLevels[{key}=17]: ")));
+        assert!(text.starts_with(&format!("- tcbInfo.tcbLevels[{key}=5]: ")));
+        assert_eq!(text.lines().count(), 2);
+    }
 
-fn month_name(month: u8) -> &'static str {
-    match month {
-        1 => "January",
-        2 => "February",
-        3 => "March",
-        4 => "April",
-        5 => "May",
-        6 => "June",
-        7 => "July",
-        8 => "August",
-        9 => "September",
-        10 => "October",
-        11 => "November",
-        12 => "December",
-        _ => panic!("Invalid month"),
+    #[test]
+    fn diff_compares_dates_as_instants() {
+        let same = [(13, "2024-03-13T01:00:00+01:00", "UpToDate"), LEVELS[1]];
+        assert_eq!(diff_text(&LEVELS, &same), "");
+
+        let later = [(13, "2024-03-13T00:00:00.5Z", "UpToDate"), LEVELS[1]];
+        let key = format!("sgx={};pcesvn=13", vec!["2"; 16].join(","));
+        assert_eq!(
+            diff_text(&LEVELS, &later),
+            format!(
+                "~ tcbInfo.tcbLevels[{key}].tcbDate: \"2024-03-13T00:00:00Z\" -> \"2024-03-13T00:00:00.5Z\"\n"
+            )
+        );
     }
 }
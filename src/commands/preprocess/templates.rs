@@ -0,0 +1,19 @@
+use std::path::PathBuf;
+
+use clap::Parser;
+use eyre::Result;
+
+use crate::template::write_default_templates;
+
+#[derive(Debug, Parser)]
+pub struct TemplatesCommand {
+    /// Directory to write the default templates into.
+    #[clap(long)]
+    output: PathBuf,
+}
+
+impl TemplatesCommand {
+    pub fn run(self) -> Result<()> {
+        write_default_templates(&self.output)
+    }
+}
@@ -0,0 +1,126 @@
+use eyre::Result;
+use time::{Month, OffsetDateTime, UtcOffset, format_description::well_known::Rfc3339};
+
+/// A UTC datetime with millisecond precision.
+pub struct DateTime {
+    pub year: i32,
+    pub month: Month,
+    pub day: u8,
+    pub hour: u8,
+    pub minute: u8,
+    pub second: u8,
+    pub millisecond: u16,
+    pub unix_timestamp: i64,
+}
+
+/// Parses an RFC 3339 datetime string like "2025-02-13T03:39:00Z" or
+/// "2025-02-13T04:39:00.123+01:00", normalizing it to UTC.
+pub fn parse_datetime(datetime_str: &str) -> Result<DateTime> {
+    let datetime = OffsetDateTime::parse(datetime_str, &Rfc3339)
+        .map_err(|err| eyre::eyre!("Invalid RFC 3339 datetime \"{datetime_str}\": {err}"))?
+        .to_offset(UtcOffset::UTC);
+
+    if datetime.nanosecond() % 1_000_000 != 0 {
+        eyre::bail!("Sub-millisecond precision is not supported: \"{datetime_str}\"");
+    }
+
+    Ok(DateTime {
+        year: datetime.year(),
+        month: datetime.month(),
+        day: datetime.day(),
+        hour: datetime.hour(),
+        minute: datetime.minute(),
+        second: datetime.second(),
+        millisecond: datetime.millisecond(),
+        unix_timestamp: datetime.unix_timestamp(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// UTC fields of `datetime_str` as `(year, month, day, hour, minute, second, millisecond)`.
+    fn fields(datetime_str: &str) -> (i32, u8, u8, u8, u8, u8, u16) {
+        let datetime = parse_datetime(datetime_str).unwrap();
+        (
+            datetime.year,
+            datetime.month.into(),
+            datetime.day,
+            datetime.hour,
+            datetime.minute,
+            datetime.second,
+            datetime.millisecond,
+        )
+    }
+
+    fn error(datetime_str: &str) -> String {
+        match parse_datetime(datetime_str) {
+            Ok(_) => panic!("\"{datetime_str}\" should be rejected"),
+            Err(error) => error.to_string(),
+        }
+    }
+
+    #[test]
+    fn normalizes_offsets_to_utc() {
+        assert_eq!(fields("2025-02-13T03:39:00Z"), (2025, 2, 13, 3, 39, 0, 0));
+        assert_eq!(
+            fields("2025-02-13T04:39:00+01:00"),
+            (2025, 2, 13, 3, 39, 0, 0)
+        );
+        assert_eq!(
+            fields("2025-02-13T03:39:00-00:00"),
+            (2025, 2, 13, 3, 39, 0, 0)
+        );
+        // Crossing day, month and year boundaries
+        assert_eq!(
+            fields("2024-12-31T22:30:00-02:00"),
+            (2025, 1, 1, 0, 30, 0, 0)
+        );
+        assert_eq!(
+            fields("2024-03-01T01:00:00+05:30"),
+            (2024, 2, 29, 19, 30, 0, 0)
+        );
+    }
+
+    #[test]
+    fn counts_unix_seconds_from_the_utc_instant() {
+        let datetime = parse_datetime("2025-02-13T04:39:00.5+01:00").unwrap();
+        assert_eq!(datetime.unix_timestamp, 1739417940);
+        assert_eq!(
+            parse_datetime("1970-01-01T00:00:00Z")
+                .unwrap()
+                .unix_timestamp,
+            0
+        );
+    }
+
+    #[test]
+    fn keeps_milliseconds() {
+        assert_eq!(
+            fields("2025-02-13T03:39:00.5Z"),
+            (2025, 2, 13, 3, 39, 0, 500)
+        );
+        assert_eq!(
+            fields("2025-02-13T03:39:00.123Z"),
+            (2025, 2, 13, 3, 39, 0, 123)
+        );
+        assert_eq!(
+            fields("2025-02-13T03:39:00.120000Z"),
+            (2025, 2, 13, 3, 39, 0, 120)
+        );
+        assert_eq!(
+            fields("2025-02-13T04:39:59.999+01:00"),
+            (2025, 2, 13, 3, 39, 59, 999)
+        );
+    }
+
+    #[test]
+    fn rejects_sub_millisecond_precision_and_invalid_input() {
+        assert!(error("2025-02-13T03:39:00.1234Z").starts_with("Sub-millisecond precision"));
+
+        for invalid in ["2025-02-13", "2025-02-13T03:39:00", "2025-02-30T03:39:00Z"] {
+            assert!(error(invalid).starts_with("Invalid RFC 3339 datetime"));
+        }
+    }
+}
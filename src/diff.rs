@@ -0,0 +1,248 @@
+use std::collections::BTreeSet;
+
+use clap::ValueEnum;
+use eyre::Result;
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::datetime::parse_datetime;
+
+/// Output format of a diff.
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum DiffFormat {
+    /// One line per change, prefixed with `+`, `-` or `~`.
+    Text,
+    /// JSON array of changes.
+    Json,
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeKind {
+    Added,
+    Removed,
+    Changed,
+}
+
+/// Single change between two documents, located by a path of JSON field names where list items
+/// are identified by their key, e.g. `enclaveIdentity.tcbLevels[isvsvn=8].tcbStatus`, or
+/// `tcbInfo.tcbLevels[sgx=15,15,2,...;pcesvn=13].tcbStatus` with every SVN spelled out.
+#[derive(Debug, Serialize)]
+pub struct Change {
+    pub path: String,
+    pub kind: ChangeKind,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub old: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub new: Option<Value>,
+}
+
+/// Changes collected while walking two documents side by side.
+#[derive(Debug, Default)]
+pub struct Diff {
+    changes: Vec<Change>,
+}
+
+impl Diff {
+    /// Records a change at `path` if `old` and `new` serialize differently.
+    pub fn value<T>(&mut self, path: &str, old: &T, new: &T) -> Result<()>
+    where
+        T: Serialize,
+    {
+        let (old, new) = (serde_json::to_value(old)?, serde_json::to_value(new)?);
+        if old != new {
+            self.changes.push(Change {
+                path: path.to_owned(),
+                kind: ChangeKind::Changed,
+                old: Some(old),
+                new: Some(new),
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Records a change at `path` if the RFC 3339 dates `old` and `new` are different instants,
+    /// regardless of their UTC offsets.
+    pub fn date(&mut self, path: &str, old: &str, new: &str) -> Result<()> {
+        let instant =
+            |date_str| parse_datetime(date_str).map(|date| (date.unix_timestamp, date.millisecond));
+        if instant(old)? != instant(new)? {
+            self.changes.push(Change {
+                path: path.to_owned(),
+                kind: ChangeKind::Changed,
+                old: Some(Value::String(old.to_owned())),
+                new: Some(Value::String(new.to_owned())),
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Records the strings added to or removed from the set at `path`.
+    pub fn set(&mut self, path: &str, old: &[String], new: &[String]) {
+        let old = old.iter().collect::<BTreeSet<_>>();
+        let new = new.iter().collect::<BTreeSet<_>>();
+
+        for removed in old.difference(&new) {
+            self.changes.push(Change {
+                path: path.to_owned(),
+                kind: ChangeKind::Removed,
+                old: Some(Value::String((*removed).clone())),
+                new: None,
+            });
+        }
+        for added in new.difference(&old) {
+            self.changes.push(Change {
+                path: path.to_owned(),
+                kind: ChangeKind::Added,
+                old: None,
+                new: Some(Value::String((*added).clone())),
+            });
+        }
+    }
+
+    /// Matches the items of two lists by `key`, recording removed and added items as a whole and
+    /// comparing matching ones with `compare`, which gets the path of the item.
+    pub fn keyed<T, K, C>(
+        &mut self,
+        path: &str,
+        old: &[T],
+        new: &[T],
+        key: K,
+        mut compare: C,
+    ) -> Result<()>
+    where
+        T: Serialize,
+        K: Fn(&T) -> String,
+        C: FnMut(&mut Self, &str, &T, &T) -> Result<()>,
+    {
+        for old_item in old {
+            let item_key = key(old_item);
+            let item_path = format!("{path}[{item_key}]");
+            match new.iter().find(|new_item| key(new_item) == item_key) {
+                Some(new_item) => compare(self, &item_path, old_item, new_item)?,
+                None => self.changes.push(Change {
+                    path: item_path,
+                    kind: ChangeKind::Removed,
+                    old: Some(serde_json::to_value(old_item)?),
+                    new: None,
+                }),
+            }
+        }
+
+        for new_item in new {
+            let item_key = key(new_item);
+            if !old.iter().any(|old_item| key(old_item) == item_key) {
+                self.changes.push(Change {
+                    path: format!("{path}[{item_key}]"),
+                    kind: ChangeKind::Added,
+                    old: None,
+                    new: Some(serde_json::to_value(new_item)?),
+                });
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn render(&self, format: DiffFormat) -> Result<String> {
+        match format {
+            DiffFormat::Json => {
+                let mut output = serde_json::to_string_pretty(&self.changes)?;
+                output.push('\n');
+                Ok(output)
+            }
+            DiffFormat::Text => {
+                let mut output = String::new();
+                for change in &self.changes {
+                    let line = match (change.kind, &change.old, &change.new) {
+                        (ChangeKind::Changed, Some(old), Some(new)) => {
+                            format!("~ {}: {old} -> {new}", change.path)
+                        }
+                        (ChangeKind::Added, _, Some(new)) => format!("+ {}: {new}", change.path),
+                        (ChangeKind::Removed, Some(old), _) => format!("- {}: {old}", change.path),
+                        _ => unreachable!("changes always carry their values"),
+                    };
+                    output.push_str(&line);
+                    output.push('\n');
+                }
+
+                Ok(output)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn strings(values: &[&str]) -> Vec<String> {
+        values.iter().map(|value| (*value).to_owned()).collect()
+    }
+
+    /// Diff of two lists of `(id, status)` items keyed by id.
+    fn keyed_diff(old: &[(&str, &str)], new: &[(&str, &str)]) -> Diff {
+        let mut diff = Diff::default();
+        diff.keyed(
+            "levels",
+            old,
+            new,
+            |(id, _)| format!("id={id}"),
+            |diff, path, old, new| diff.value(&format!("{path}.status"), &old.1, &new.1),
+        )
+        .unwrap();
+        diff
+    }
+
+    #[test]
+    fn records_only_changed_values() {
+        let mut diff = Diff::default();
+        diff.value("same", &1, &1).unwrap();
+        diff.value("changed", &"a", &"b").unwrap();
+
+        assert_eq!(
+            diff.render(DiffFormat::Text).unwrap(),
+            "~ changed: \"a\" -> \"b\"\n"
+        );
+    }
+
+    #[test]
+    fn compares_sets_regardless_of_order() {
+        let mut diff = Diff::default();
+        diff.set("ids", &strings(&["a", "b"]), &strings(&["b", "a"]));
+        assert_eq!(diff.render(DiffFormat::Text).unwrap(), "");
+
+        diff.set("ids", &strings(&["a", "b"]), &strings(&["b", "c"]));
+        assert_eq!(
+            diff.render(DiffFormat::Text).unwrap(),
+            "- ids: \"a\"\n+ ids: \"c\"\n"
+        );
+    }
+
+    #[test]
+    fn matches_list_items_by_key() {
+        let diff = keyed_diff(
+            &[("1", "UpToDate"), ("2", "UpToDate")],
+            &[("3", "UpToDate"), ("1", "OutOfDate")],
+        );
+
+        assert_eq!(
+            diff.render(DiffFormat::Text).unwrap(),
+            "~ levels[id=1].status: \"UpToDate\" -> \"OutOfDate\"\n\
+             - levels[id=2]: [\"2\",\"UpToDate\"]\n\
+             + levels[id=3]: [\"3\",\"UpToDate\"]\n"
+        );
+    }
+
+    #[test]
+    fn renders_json_without_missing_values() {
+        let diff = keyed_diff(&[("1", "UpToDate")], &[]);
+
+        assert_eq!(
+            diff.render(DiffFormat::Json).unwrap(),
+            "[\n  {\n    \"path\": \"levels[id=1]\",\n    \"kind\": \"removed\",\n    \"old\": [\n      \"1\",\n      \"UpToDate\"\n    ]\n  }\n]\n"
+        );
+    }
+}
@@ -0,0 +1,1016 @@
+use std::{collections::BTreeMap, fmt::Display};
+
+use clap::{Args, ValueEnum};
+use eyre::Result;
+
+use crate::datetime::{DateTime, parse_datetime};
+
+/// Maximum line width before an expression is broken over multiple lines.
+const MAX_WIDTH: usize = 100;
+
+const INDENT: &str = "    ";
+
+/// Cairo representation of collateral dates.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum DateFormat {
+    /// `OffsetDateTime` values from the Cairo `time` library.
+    #[default]
+    OffsetDateTime,
+    /// `u64` unix seconds, comparable with `get_block_timestamp()`.
+    Unix,
+}
+
+impl DateFormat {
+    /// `use` statement needed by the generated dates, if any.
+    pub const fn imports(&self) -> Option<&'static str> {
+        match self {
+            Self::OffsetDateTime => {
+                Some("use time::{DateTrait, Month, OffsetDateTimeTrait, TimeTrait};\n")
+            }
+            Self::Unix => None,
+        }
+    }
+
+    /// `use` statement needed by type definitions holding dates, if any.
+    pub const fn type_imports(&self) -> Option<&'static str> {
+        match self {
+            Self::OffsetDateTime => Some("use time::OffsetDateTime;\n"),
+            Self::Unix => None,
+        }
+    }
+
+    /// Cairo type of the generated dates.
+    pub const fn cairo_type(&self) -> &'static str {
+        match self {
+            Self::OffsetDateTime => "OffsetDateTime",
+            Self::Unix => "u64",
+        }
+    }
+
+    /// Whether the Cairo type of the generated dates implements `Serde`. `OffsetDateTime` from the
+    /// Cairo `time` library does not, so the types holding one cannot derive it.
+    pub const fn implements_serde(&self) -> bool {
+        match self {
+            Self::OffsetDateTime => false,
+            Self::Unix => true,
+        }
+    }
+}
+
+/// Cairo representation of collateral strings such as IDs and TCB statuses.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum StringFormat {
+    /// `ByteArray` string literals of any length.
+    #[default]
+    ByteArray,
+    /// `felt252` short string literals, limited to 31 bytes.
+    ShortString,
+}
+
+impl StringFormat {
+    /// Maximum length of a Cairo short string in bytes.
+    const SHORT_STRING_MAX_LEN: usize = 31;
+
+    /// Cairo type of the generated strings.
+    pub const fn cairo_type(&self) -> &'static str {
+        match self {
+            Self::ByteArray => "ByteArray",
+            Self::ShortString => "felt252",
+        }
+    }
+
+    const fn delimiter(&self) -> char {
+        match self {
+            Self::ByteArray => '"',
+            Self::ShortString => '\'',
+        }
+    }
+}
+
+/// Default name of the enum representing TCB statuses.
+pub const TCB_STATUS_TYPE: &str = "TcbStatus";
+
+/// TCB statuses defined by Intel for TCB info and QE identity TCB levels.
+const TCB_STATUSES: [&str; 7] = [
+    "UpToDate",
+    "SWHardeningNeeded",
+    "ConfigurationNeeded",
+    "ConfigurationAndSWHardeningNeeded",
+    "OutOfDate",
+    "OutOfDateConfigurationNeeded",
+    "Revoked",
+];
+
+/// Cairo representation of TCB statuses.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum TcbStatusFormat {
+    /// Strings in the selected string format.
+    #[default]
+    String,
+    /// Variants of a `TcbStatus` enum, rejecting statuses unknown to Intel's specification.
+    Enum,
+}
+
+impl TcbStatusFormat {
+    /// Cairo type of the generated statuses, given the resolved name of the enum.
+    pub fn cairo_type(&self, string_format: StringFormat, enum_name: &str) -> String {
+        match self {
+            Self::String => string_format.cairo_type().to_owned(),
+            Self::Enum => enum_name.to_owned(),
+        }
+    }
+}
+
+/// Options for the representation of TCB statuses.
+#[derive(Debug, Clone, Args)]
+pub struct TcbStatusArgs {
+    /// Cairo representation of TCB statuses.
+    #[clap(long, value_enum, default_value_t = TcbStatusFormat::String)]
+    pub tcb_status_format: TcbStatusFormat,
+    /// Define the `TcbStatus` enum in the output file instead of importing it. Only used with
+    /// `--tcb-status-format enum`.
+    #[clap(long)]
+    pub define_tcb_status: bool,
+}
+
+impl TcbStatusArgs {
+    /// Whether the `TcbStatus` enum must be imported along with the other types.
+    pub fn imports_enum(&self) -> bool {
+        self.tcb_status_format == TcbStatusFormat::Enum && !self.define_tcb_status
+    }
+
+    /// Definition of the `TcbStatus` enum to include in the output, if requested.
+    pub fn type_def(&self, enum_name: &str) -> Option<TypeDef> {
+        (self.tcb_status_format == TcbStatusFormat::Enum && self.define_tcb_status)
+            .then(|| TypeDef::tcb_status(enum_name))
+    }
+}
+
+/// A Cairo expression rendered with consistent indentation and line breaking.
+#[derive(Debug, Clone)]
+pub enum Expr {
+    /// Verbatim expression, such as a literal or a variable name.
+    Raw(String),
+    /// Function call or enum variant with arguments, e.g. `Option::Some(value)`.
+    Call { path: String, args: Vec<Expr> },
+    /// Struct literal. Fields whose value is a variable of the same name use the shorthand form.
+    Struct {
+        path: String,
+        fields: Vec<(String, Expr)>,
+    },
+    /// `array![...]` or fixed-size `[...]` literal. When broken over multiple lines, single-line
+    /// items are packed `per_line` to a line.
+    Array {
+        items: Vec<Expr>,
+        per_line: usize,
+        fixed: bool,
+    },
+    /// `.span()` called on the inner expression.
+    Span(Box<Expr>),
+}
+
+impl Expr {
+    pub fn raw<S>(value: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self::Raw(value.into())
+    }
+
+    pub fn int<T>(value: T) -> Self
+    where
+        T: Display,
+    {
+        Self::Raw(value.to_string())
+    }
+
+    /// String literal in the given format, escaping the delimiter, backslashes and non-printable
+    /// characters. Cairo strings are limited to ASCII.
+    pub fn string(value: &str, format: StringFormat) -> Result<Self> {
+        if !value.is_ascii() {
+            eyre::bail!("Non-ASCII string cannot be represented in Cairo: {value:?}");
+        }
+        if format == StringFormat::ShortString && value.len() > StringFormat::SHORT_STRING_MAX_LEN {
+            eyre::bail!(
+                "String longer than {} bytes cannot be a short string, use the byte-array string \
+                format instead: {value:?}",
+                StringFormat::SHORT_STRING_MAX_LEN
+            );
+        }
+
+        let delimiter = format.delimiter();
+        let mut literal = String::from(delimiter);
+        for char in value.chars() {
+            match char {
+                '\\' => literal.push_str("\\\\"),
+                char if char == delimiter => {
+                    literal.push('\\');
+                    literal.push(char);
+                }
+                ' '..='~' => literal.push(char),
+                char => literal.push_str(&format!("\\x{:02x}", char as u8)),
+            }
+        }
+        literal.push(delimiter);
+
+        Ok(Self::Raw(literal))
+    }
+
+    /// TCB status in the given format. Enum variants are named after the status.
+    pub fn tcb_status(
+        status: &str,
+        format: TcbStatusFormat,
+        string_format: StringFormat,
+        enum_name: &str,
+    ) -> Result<Self> {
+        match format {
+            TcbStatusFormat::String => Self::string(status, string_format),
+            TcbStatusFormat::Enum => {
+                if !TCB_STATUSES.contains(&status) {
+                    eyre::bail!(
+                        "Unknown TCB status: {status}, expected one of {}",
+                        TCB_STATUSES.join(", ")
+                    );
+                }
+
+                Ok(Self::raw(format!("{enum_name}::{status}")))
+            }
+        }
+    }
+
+    pub fn call<S>(path: S, args: Vec<Expr>) -> Self
+    where
+        S: Into<String>,
+    {
+        Self::Call {
+            path: path.into(),
+            args,
+        }
+    }
+
+    pub fn structure<S>(path: S, fields: Vec<(&str, Expr)>) -> Self
+    where
+        S: Into<String>,
+    {
+        Self::Struct {
+            path: path.into(),
+            fields: fields
+                .into_iter()
+                .map(|(name, value)| (name.to_owned(), value))
+                .collect(),
+        }
+    }
+
+    pub fn array(items: Vec<Expr>) -> Self {
+        Self::Array {
+            items,
+            per_line: 1,
+            fixed: false,
+        }
+    }
+
+    /// `array![...]` of hex byte literals.
+    pub fn bytes(bytes: &[u8]) -> Self {
+        Self::Array {
+            items: bytes
+                .iter()
+                .map(|byte| Self::Raw(format!("0x{byte:02x}")))
+                .collect(),
+            per_line: 16,
+            fixed: false,
+        }
+    }
+
+    /// Fixed-size `[...]` of hex byte literals.
+    pub fn fixed_bytes(bytes: &[u8]) -> Self {
+        match Self::bytes(bytes) {
+            Self::Array {
+                items, per_line, ..
+            } => Self::Array {
+                items,
+                per_line,
+                fixed: true,
+            },
+            _ => unreachable!(),
+        }
+    }
+
+    pub fn some(value: Expr) -> Self {
+        Self::call("Option::Some", vec![value])
+    }
+
+    pub fn none() -> Self {
+        Self::raw("Option::None")
+    }
+
+    pub fn option(value: Option<Expr>) -> Self {
+        match value {
+            Some(value) => Self::some(value),
+            None => Self::none(),
+        }
+    }
+
+    /// `OffsetDateTime` in UTC built from calendar date and time.
+    pub fn datetime(date: &DateTime) -> Self {
+        Self::call(
+            "OffsetDateTimeTrait::new_utc",
+            vec![
+                Self::raw(format!(
+                    "DateTrait::from_calendar_date({}, Month::{}, {}).unwrap()",
+                    date.year, date.month, date.day
+                )),
+                Self::raw(format!(
+                    "TimeTrait::from_hms_milli({}, {}, {}, {}).unwrap()",
+                    date.hour, date.minute, date.second, date.millisecond
+                )),
+            ],
+        )
+    }
+
+    /// Date in the given format. Unix timestamps are truncated to whole seconds.
+    pub fn date(date: &DateTime, format: DateFormat) -> Result<Self> {
+        match format {
+            DateFormat::OffsetDateTime => Ok(Self::datetime(date)),
+            DateFormat::Unix => {
+                let Ok(timestamp) = u64::try_from(date.unix_timestamp) else {
+                    eyre::bail!("Date before unix epoch: {}", date.unix_timestamp);
+                };
+
+                Ok(Self::int(timestamp))
+            }
+        }
+    }
+
+    pub fn span(self) -> Self {
+        Self::Span(Box::new(self))
+    }
+
+    /// Renders the expression as if it started at the given indentation level. The first line is
+    /// not indented.
+    pub fn render(&self, level: usize) -> String {
+        match self {
+            Self::Raw(value) => value.clone(),
+            Self::Span(inner) => format!("{}.span()", inner.render(level)),
+            Self::Call { path, args } => {
+                let args = args
+                    .iter()
+                    .map(|arg| arg.render(level + 1))
+                    .collect::<Vec<_>>();
+
+                let inline = format!("{path}({})", args.join(", "));
+                if fits(level, &inline) {
+                    return inline;
+                }
+
+                let mut result = format!("{path}(\n");
+                for arg in args {
+                    push_line(&mut result, level + 1, &format!("{arg},"));
+                }
+                result.push_str(&INDENT.repeat(level));
+                result.push(')');
+                result
+            }
+            Self::Struct { path, fields } => {
+                if fields.is_empty() {
+                    return format!("{path} {{}}");
+                }
+
+                let fields = fields
+                    .iter()
+                    .map(|(name, value)| match value {
+                        Self::Raw(raw) if raw == name => name.clone(),
+                        _ => format!("{name}: {}", value.render(level + 1)),
+                    })
+                    .collect::<Vec<_>>();
+
+                let inline = format!("{path} {{ {} }}", fields.join(", "));
+                if fields.len() == 1 && fits(level, &inline) {
+                    return inline;
+                }
+
+                let mut result = format!("{path} {{\n");
+                for field in fields {
+                    push_line(&mut result, level + 1, &format!("{field},"));
+                }
+                result.push_str(&INDENT.repeat(level));
+                result.push('}');
+                result
+            }
+            Self::Array {
+                items,
+                per_line,
+                fixed,
+            } => {
+                let (open, close) = if *fixed { ("[", "]") } else { ("array![", "]") };
+
+                let items = items
+                    .iter()
+                    .map(|item| item.render(level + 1))
+                    .collect::<Vec<_>>();
+
+                let inline = format!("{open}{}{close}", items.join(", "));
+                if fits(level, &inline) {
+                    return inline;
+                }
+
+                let per_line = if items.iter().any(|item| item.contains('\n')) {
+                    1
+                } else {
+                    *per_line
+                };
+
+                let mut result = format!("{open}\n");
+                for line in items.chunks(per_line) {
+                    push_line(&mut result, level + 1, &format!("{},", line.join(", ")));
+                }
+                result.push_str(&INDENT.repeat(level));
+                result.push_str(close);
+                result
+            }
+        }
+    }
+}
+
+/// A `let` binding preceding the returned expression of a [`Function`].
+pub struct Let {
+    pub comment: Option<String>,
+    pub name: String,
+    pub ty: Option<String>,
+    pub value: Expr,
+}
+
+impl Let {
+    /// Binds a datetime parsed from an RFC 3339 string, keeping the original string as comment.
+    pub fn datetime(name: &str, datetime_str: &str, format: DateFormat) -> Result<Self> {
+        Ok(Self {
+            comment: Some(datetime_str.to_owned()),
+            name: name.to_owned(),
+            ty: date_type(format),
+            value: Expr::date(&parse_datetime(datetime_str)?, format)?,
+        })
+    }
+}
+
+/// A public function with `let` bindings followed by a returned expression.
+pub struct Function {
+    pub name: String,
+    pub ret: String,
+    pub lets: Vec<Let>,
+    pub body: Expr,
+}
+
+impl Function {
+    pub fn render(&self) -> String {
+        let mut result = format!("pub fn {}() -> {} {{\n", self.name, self.ret);
+
+        for binding in &self.lets {
+            if let Some(comment) = &binding.comment {
+                push_line(&mut result, 1, &format!("// {comment}"));
+            }
+            let ty = match &binding.ty {
+                Some(ty) => format!(": {ty}"),
+                None => String::new(),
+            };
+            push_line(
+                &mut result,
+                1,
+                &format!("let {}{ty} = {};", binding.name, binding.value.render(1)),
+            );
+            result.push('\n');
+        }
+
+        push_line(&mut result, 1, &self.body.render(1));
+        result.push_str("}\n");
+        result
+    }
+}
+
+/// A Cairo type definition deriving `Drop` and, unless dropped with [`TypeDef::drop_serde`],
+/// `Serde`, with public fields.
+pub enum TypeDef {
+    Struct {
+        name: String,
+        fields: Vec<(String, String)>,
+        serde: bool,
+    },
+    /// Enum whose variants optionally wrap a value of the given type.
+    Enum {
+        name: String,
+        variants: Vec<(String, Option<String>)>,
+        serde: bool,
+    },
+}
+
+impl TypeDef {
+    /// Enum with a unit variant for each known TCB status.
+    pub fn tcb_status<S>(name: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self::enumeration(
+            name,
+            TCB_STATUSES.iter().map(|status| (*status, None)).collect(),
+        )
+    }
+
+    pub fn structure<S>(name: S, fields: Vec<(&str, String)>) -> Self
+    where
+        S: Into<String>,
+    {
+        Self::Struct {
+            name: name.into(),
+            fields: fields
+                .into_iter()
+                .map(|(name, ty)| (name.to_owned(), ty))
+                .collect(),
+            serde: true,
+        }
+    }
+
+    pub fn enumeration<S>(name: S, variants: Vec<(&str, Option<String>)>) -> Self
+    where
+        S: Into<String>,
+    {
+        Self::Enum {
+            name: name.into(),
+            variants: variants
+                .into_iter()
+                .map(|(name, ty)| (name.to_owned(), ty))
+                .collect(),
+            serde: true,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            Self::Struct { name, .. } | Self::Enum { name, .. } => name,
+        }
+    }
+
+    /// Stops deriving `Serde` for the types holding a value of type `ty`, directly or through
+    /// other types of `type_defs`.
+    pub fn drop_serde(type_defs: &mut [TypeDef], ty: &str) {
+        let mut unserializable = vec![ty.to_owned()];
+        while let Some(type_def) = type_defs.iter_mut().find(|type_def| {
+            type_def.derives_serde()
+                && type_def.field_types().iter().any(|field_ty| {
+                    field_ty
+                        .split(|char: char| !char.is_alphanumeric() && char != '_')
+                        .any(|name| unserializable.iter().any(|ty| ty == name))
+                })
+        }) {
+            match type_def {
+                Self::Struct { serde, .. } | Self::Enum { serde, .. } => *serde = false,
+            }
+            unserializable.push(type_def.name().to_owned());
+        }
+    }
+
+    fn derives_serde(&self) -> bool {
+        match self {
+            Self::Struct { serde, .. } | Self::Enum { serde, .. } => *serde,
+        }
+    }
+
+    fn field_types(&self) -> Vec<&str> {
+        match self {
+            Self::Struct { fields, .. } => fields.iter().map(|(_, ty)| ty.as_str()).collect(),
+            Self::Enum { variants, .. } => variants
+                .iter()
+                .filter_map(|(_, ty)| ty.as_deref())
+                .collect(),
+        }
+    }
+
+    /// Renders the definition. Enums without any payload also derive `Copy` and `PartialEq`.
+    pub fn render(&self) -> String {
+        let mut derives = match self {
+            Self::Enum { variants, .. } if variants.iter().all(|(_, ty)| ty.is_none()) => {
+                vec!["Copy", "Drop", "PartialEq"]
+            }
+            _ => vec!["Drop"],
+        };
+        if self.derives_serde() {
+            derives.push("Serde");
+        }
+        let mut result = format!("#[derive({})]\n", derives.join(", "));
+
+        match self {
+            Self::Struct { name, fields, .. } => {
+                result.push_str(&format!("pub struct {name} {{\n"));
+                for (field, ty) in fields {
+                    push_line(&mut result, 1, &format!("pub {field}: {ty},"));
+                }
+            }
+            Self::Enum { name, variants, .. } => {
+                result.push_str(&format!("pub enum {name} {{\n"));
+                for (variant, ty) in variants {
+                    match ty {
+                        Some(ty) => push_line(&mut result, 1, &format!("{variant}: {ty},")),
+                        None => push_line(&mut result, 1, &format!("{variant},")),
+                    }
+                }
+            }
+        }
+
+        result.push_str("}\n");
+        result
+    }
+}
+
+/// Renders a `use` statement importing `items` from `path`.
+pub fn render_use<S: AsRef<str>>(path: &str, items: &[S]) -> String {
+    let items = items.iter().map(AsRef::as_ref).collect::<Vec<_>>();
+    let inline = format!("use {path}::{{{}}};\n", items.join(", "));
+    if inline.len() <= MAX_WIDTH + 1 {
+        return inline;
+    }
+
+    let mut result = format!("use {path}::{{\n");
+    let mut line = String::new();
+    for item in &items {
+        if !line.is_empty() && INDENT.len() + line.len() + item.len() + 2 > MAX_WIDTH {
+            push_line(&mut result, 1, line.trim_end());
+            line.clear();
+        }
+        line.push_str(item);
+        line.push_str(", ");
+    }
+    push_line(&mut result, 1, line.trim_end_matches(' '));
+    result.push_str("};\n");
+    result
+}
+
+/// Name of the variable holding a TCB date, shared by every level using the same instant. The
+/// time of day is only spelled out when it is not midnight.
+pub fn tcb_date_var_name(date: &DateTime) -> String {
+    let mut name = format!(
+        "tcb_date_{}_{:02}_{:02}",
+        date.year, date.month as u8, date.day
+    );
+    if (date.hour, date.minute, date.second, date.millisecond) != (0, 0, 0, 0) {
+        name.push_str(&format!(
+            "_{:02}_{:02}_{:02}",
+            date.hour, date.minute, date.second
+        ));
+    }
+    if date.millisecond != 0 {
+        name.push_str(&format!("_{:03}", date.millisecond));
+    }
+
+    name
+}
+
+/// Bindings for each distinct TCB date, most recent first. Names sort chronologically, as they are
+/// zero-padded and midnight omits the time.
+pub fn tcb_date_lets<'a, I>(dates: I, format: DateFormat) -> Result<Vec<Let>>
+where
+    I: IntoIterator<Item = &'a str>,
+{
+    let mut unique_dates = BTreeMap::new();
+    for date_str in dates {
+        let date = parse_datetime(date_str)?;
+        unique_dates
+            .entry(tcb_date_var_name(&date))
+            .or_insert((date_str, date));
+    }
+
+    unique_dates
+        .into_iter()
+        .rev()
+        .map(|(name, (date_str, date))| {
+            Ok(Let {
+                comment: Some(date_str.to_owned()),
+                name,
+                ty: date_type(format),
+                value: Expr::date(&date, format)?,
+            })
+        })
+        .collect()
+}
+
+/// Variable expression referencing the binding of a TCB date.
+pub fn tcb_date_var(datetime_str: &str) -> Result<Expr> {
+    Ok(Expr::raw(tcb_date_var_name(&parse_datetime(datetime_str)?)))
+}
+
+/// Type annotation of `let` bindings holding dates, if not inferred.
+pub fn date_type(format: DateFormat) -> Option<String> {
+    match format {
+        DateFormat::OffsetDateTime => None,
+        DateFormat::Unix => Some("u64".to_owned()),
+    }
+}
+
+fn fits(level: usize, inline: &str) -> bool {
+    !inline.contains('\n') && INDENT.len() * level + inline.len() <= MAX_WIDTH
+}
+
+fn push_line(result: &mut String, level: usize, line: &str) {
+    result.push_str(&INDENT.repeat(level));
+    result.push_str(line);
+    result.push('\n');
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn keeps_short_expressions_on_one_line() {
+        let expr = Expr::structure(
+            "TcbLevel",
+            vec![
+                ("isvsvn", Expr::int(8)),
+                ("tcb_date", Expr::raw("tcb_date")),
+            ],
+        );
+        assert_eq!(
+            expr.render(0),
+            "TcbLevel {\n    isvsvn: 8,\n    tcb_date,\n}"
+        );
+
+        let expr = Expr::structure("Tcb", vec![("isvsvn", Expr::int(8))]);
+        assert_eq!(expr.render(0), "Tcb { isvsvn: 8 }");
+
+        let expr = Expr::option(Some(Expr::bytes(&[0x0a, 0xff]).span()));
+        assert_eq!(expr.render(0), "Option::Some(array![0x0a, 0xff].span())");
+        assert_eq!(Expr::option(None).render(0), "Option::None");
+        assert_eq!(Expr::fixed_bytes(&[1, 2]).render(0), "[0x01, 0x02]");
+    }
+
+    #[test]
+    fn packs_long_arrays_and_indents_nested_ones() {
+        let bytes = (0..20).collect::<Vec<u8>>();
+        let expected = "array![\n    \
+                        0x00, 0x01, 0x02, 0x03, 0x04, 0x05, 0x06, 0x07, 0x08, 0x09, 0x0a, 0x0b, \
+                        0x0c, 0x0d, 0x0e, 0x0f,\n    \
+                        0x10, 0x11, 0x12, 0x13,\n]";
+        assert_eq!(Expr::bytes(&bytes).render(0), expected);
+
+        let expr = Expr::array(vec![Expr::bytes(&bytes).span(), Expr::bytes(&[1]).span()]);
+        assert_eq!(
+            expr.render(1),
+            format!(
+                "array![\n        {},\n        array![0x01].span(),\n    ]",
+                expected.replace("\n", "\n        ") + ".span()"
+            )
+        );
+    }
+
+    #[test]
+    fn renders_datetimes_and_functions() {
+        let binding = Let::datetime(
+            "issue_date",
+            "2024-03-18T10:35:22Z",
+            DateFormat::OffsetDateTime,
+        )
+        .unwrap();
+        let function = Function {
+            name: "data".to_owned(),
+            ret: "Dates".to_owned(),
+            lets: vec![binding],
+            body: Expr::structure("Dates", vec![("issue_date", Expr::raw("issue_date"))]),
+        };
+
+        assert_eq!(
+            function.render(),
+            "pub fn data() -> Dates {\n    \
+             // 2024-03-18T10:35:22Z\n    \
+             let issue_date = OffsetDateTimeTrait::new_utc(\n        \
+             DateTrait::from_calendar_date(2024, Month::March, 18).unwrap(),\n        \
+             TimeTrait::from_hms_milli(10, 35, 22, 0).unwrap(),\n    \
+             );\n\n    \
+             Dates { issue_date }\n\
+             }\n"
+        );
+    }
+
+    #[test]
+    fn wraps_long_imports() {
+        assert_eq!(
+            render_use("crate::types", &["A", "B"]),
+            "use crate::types::{A, B};\n"
+        );
+
+        let items = ["EnclaveIdentityV2", "EnclaveIdentityV2Inner"].repeat(3);
+        assert_eq!(
+            render_use("crate::types::enclave_identity", &items),
+            "use crate::types::enclave_identity::{\n    \
+             EnclaveIdentityV2, EnclaveIdentityV2Inner, EnclaveIdentityV2, EnclaveIdentityV2Inner,\n    \
+             EnclaveIdentityV2, EnclaveIdentityV2Inner,\n\
+             };\n"
+        );
+    }
+
+    #[test]
+    fn binds_each_tcb_date_once_most_recent_first() {
+        let lets = tcb_date_lets(
+            [
+                "2023-08-09T00:00:00Z",
+                "2024-03-13T00:00:00Z",
+                "2024-03-13T00:00:00Z",
+            ],
+            DateFormat::OffsetDateTime,
+        )
+        .unwrap();
+
+        let names = lets
+            .iter()
+            .map(|binding| binding.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(names, ["tcb_date_2024_03_13", "tcb_date_2023_08_09"]);
+        assert_eq!(
+            tcb_date_var("2024-03-13T00:00:00Z").unwrap().render(0),
+            "tcb_date_2024_03_13"
+        );
+    }
+
+    #[test]
+    fn renders_unix_dates_as_typed_seconds() {
+        let binding = Let::datetime("issue_date", "2024-03-18T10:35:22.999Z", DateFormat::Unix);
+        let binding = binding.unwrap();
+        assert_eq!(binding.ty.as_deref(), Some("u64"));
+        assert_eq!(binding.value.render(0), "1710758122");
+
+        let error = Let::datetime("issue_date", "1969-12-31T23:59:59Z", DateFormat::Unix);
+        assert_eq!(
+            error.err().unwrap().to_string(),
+            "Date before unix epoch: -1"
+        );
+
+        let lets = tcb_date_lets(["2024-03-13T00:00:00Z"], DateFormat::Unix).unwrap();
+        let function = Function {
+            name: "data".to_owned(),
+            ret: "u64".to_owned(),
+            lets,
+            body: Expr::raw("tcb_date_2024_03_13"),
+        };
+        assert!(
+            function
+                .render()
+                .contains("let tcb_date_2024_03_13: u64 = 1710288000;\n")
+        );
+    }
+
+    #[test]
+    fn renders_type_definitions() {
+        let structure = TypeDef::structure("Tcb", vec![("isvsvn", "u16".to_owned())]);
+        assert_eq!(
+            structure.render(),
+            "#[derive(Drop, Serde)]\npub struct Tcb {\n    pub isvsvn: u16,\n}\n"
+        );
+
+        let enumeration = TypeDef::enumeration(
+            "Body",
+            vec![("Sgx", Some("EnclaveReport".to_owned())), ("None", None)],
+        );
+        assert_eq!(
+            enumeration.render(),
+            "#[derive(Drop, Serde)]\npub enum Body {\n    Sgx: EnclaveReport,\n    None,\n}\n"
+        );
+    }
+
+    #[test]
+    fn escapes_strings_in_both_formats() {
+        let literal = |value: &str, format| Expr::string(value, format).unwrap().render(0);
+
+        assert_eq!(
+            literal("UpToDate", StringFormat::ByteArray),
+            r#""UpToDate""#
+        );
+        assert_eq!(literal("UpToDate", StringFormat::ShortString), "'UpToDate'");
+        assert_eq!(
+            literal(r#"a"b'c\d"#, StringFormat::ByteArray),
+            r#""a\"b'c\\d""#
+        );
+        assert_eq!(
+            literal(r#"a"b'c\d"#, StringFormat::ShortString),
+            r#"'a"b\'c\\d'"#
+        );
+        for format in [StringFormat::ByteArray, StringFormat::ShortString] {
+            assert_eq!(
+                &literal("a\nb\t\0\x7f", format)[1..19],
+                r"a\x0ab\x09\x00\x7f"
+            );
+        }
+    }
+
+    #[test]
+    fn limits_short_strings_to_31_bytes() {
+        let value = "A".repeat(31);
+        assert_eq!(
+            Expr::string(&value, StringFormat::ShortString)
+                .unwrap()
+                .render(0),
+            format!("'{value}'")
+        );
+
+        let value = "A".repeat(32);
+        assert_eq!(
+            Expr::string(&value, StringFormat::ShortString)
+                .unwrap_err()
+                .to_string(),
+            format!(
+                "String longer than 31 bytes cannot be a short string, use the byte-array string \
+                 format instead: \"{value}\""
+            )
+        );
+        assert!(Expr::string(&value, StringFormat::ByteArray).is_ok());
+    }
+
+    #[test]
+    fn rejects_non_ascii_strings() {
+        for format in [StringFormat::ByteArray, StringFormat::ShortString] {
+            assert_eq!(
+                Expr::string("Up-To-Date\u{e9}", format)
+                    .unwrap_err()
+                    .to_string(),
+                "Non-ASCII string cannot be represented in Cairo: \"Up-To-Date\u{e9}\""
+            );
+        }
+    }
+
+    #[test]
+    fn defines_the_tcb_status_enum_when_requested() {
+        let args = |tcb_status_format, define_tcb_status| TcbStatusArgs {
+            tcb_status_format,
+            define_tcb_status,
+        };
+
+        assert!(!args(TcbStatusFormat::String, false).imports_enum());
+        assert!(
+            args(TcbStatusFormat::String, true)
+                .type_def("TcbStatus")
+                .is_none()
+        );
+        assert!(args(TcbStatusFormat::Enum, false).imports_enum());
+        assert!(
+            args(TcbStatusFormat::Enum, false)
+                .type_def("TcbStatus")
+                .is_none()
+        );
+        assert!(!args(TcbStatusFormat::Enum, true).imports_enum());
+
+        let type_def = args(TcbStatusFormat::Enum, true)
+            .type_def("Status")
+            .unwrap();
+        assert_eq!(
+            type_def.render(),
+            "#[derive(Copy, Drop, PartialEq, Serde)]\n\
+             pub enum Status {\n    \
+             UpToDate,\n    \
+             SWHardeningNeeded,\n    \
+             ConfigurationNeeded,\n    \
+             ConfigurationAndSWHardeningNeeded,\n    \
+             OutOfDate,\n    \
+             OutOfDateConfigurationNeeded,\n    \
+             Revoked,\n\
+             }\n"
+        );
+        assert_eq!(
+            Expr::tcb_status(
+                "Revoked",
+                TcbStatusFormat::Enum,
+                StringFormat::ByteArray,
+                "Status"
+            )
+            .unwrap()
+            .render(0),
+            "Status::Revoked"
+        );
+    }
+
+    #[test]
+    fn tcb_date_lets_key_by_full_timestamp() {
+        let lets = tcb_date_lets(
+            [
+                "2024-03-13T00:00:00Z",
+                "2024-03-13T12:00:00Z",
+                "2024-03-13T12:00:00.250Z",
+                "2024-03-13T13:00:00+01:00",
+                "2023-08-09T00:00:00Z",
+            ],
+            DateFormat::Unix,
+        )
+        .unwrap();
+
+        let lets = lets
+            .iter()
+            .map(|binding| (binding.name.as_str(), binding.value.render(0)))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            lets,
+            [
+                ("tcb_date_2024_03_13_12_00_00_250", "1710331200".to_owned()),
+                ("tcb_date_2024_03_13_12_00_00", "1710331200".to_owned()),
+                ("tcb_date_2024_03_13", "1710288000".to_owned()),
+                ("tcb_date_2023_08_09", "1691539200".to_owned()),
+            ]
+        );
+        assert_eq!(
+            tcb_date_var("2024-03-13T12:00:00Z").unwrap().render(0),
+            "tcb_date_2024_03_13_12_00_00"
+        );
+    }
+}
@@ -0,0 +1,245 @@
+use clap::Args;
+use eyre::Result;
+use time::OffsetDateTime;
+use x509_parser::prelude::{FromDer, X509Certificate};
+
+use crate::datetime::parse_datetime;
+
+/// Options for checking the validity windows of collateral and certificates at a reference time.
+#[derive(Debug, Clone, Args)]
+pub struct FreshnessArgs {
+    /// Reference time at which the input and its certificates must be valid, as an RFC 3339
+    /// datetime or `now`. The remaining validity of each item is reported. Not checked unless
+    /// given.
+    #[clap(long)]
+    at: Option<String>,
+    /// Generate Cairo even if some item is expired or not yet valid at `--at`.
+    #[clap(long)]
+    allow_stale: bool,
+}
+
+/// Validity window of a collateral object or certificate, in unix seconds.
+#[derive(Debug)]
+pub struct Validity {
+    name: String,
+    not_before: i64,
+    not_after: i64,
+}
+
+impl Validity {
+    /// Collateral is valid from its issue date until its next update.
+    pub fn collateral(name: &str, issue_date: &str, next_update: &str) -> Result<Self> {
+        Ok(Self {
+            name: name.to_owned(),
+            not_before: parse_datetime(issue_date)?.unix_timestamp,
+            not_after: parse_datetime(next_update)?.unix_timestamp,
+        })
+    }
+
+    /// Validity of each DER-encoded certificate of `chain`, named after its subject.
+    pub fn certificates(chain_name: &str, chain: &[Vec<u8>]) -> Result<Vec<Self>> {
+        let mut validities = vec![];
+        for der in chain {
+            let (_, cert) = X509Certificate::from_der(der)
+                .map_err(|err| eyre::eyre!("Failed to parse certificate: {err}"))?;
+            validities.push(Self {
+                name: format!("{chain_name} certificate {}", cert.subject()),
+                not_before: cert.validity().not_before.timestamp(),
+                not_after: cert.validity().not_after.timestamp(),
+            });
+        }
+
+        Ok(validities)
+    }
+}
+
+impl FreshnessArgs {
+    /// Whether a reference time is given with `--at`, without which nothing is checked.
+    pub const fn is_enabled(&self) -> bool {
+        self.at.is_some()
+    }
+
+    /// Reports how long each item remains valid at `--at`. Items expired or not yet valid are
+    /// errors, or warnings with `--allow-stale`.
+    pub fn check(&self, items: &[Validity]) -> Result<()> {
+        let Some(at) = &self.at else {
+            return Ok(());
+        };
+        let at = match at.as_str() {
+            "now" => OffsetDateTime::now_utc().unix_timestamp(),
+            at => parse_datetime(at)?.unix_timestamp,
+        };
+
+        let mut stale = vec![];
+        for item in items {
+            if at < item.not_before {
+                stale.push(format!(
+                    "{} is not valid until {} after the reference time",
+                    item.name,
+                    format_duration(item.not_before - at)
+                ));
+            } else if at >= item.not_after {
+                stale.push(format!(
+                    "{} expired {} before the reference time",
+                    item.name,
+                    format_duration(at - item.not_after)
+                ));
+            } else {
+                eprintln!(
+                    "{} expires in {}",
+                    item.name,
+                    format_duration(item.not_after - at)
+                );
+            }
+        }
+
+        match (stale.is_empty(), self.allow_stale) {
+            (true, _) => Ok(()),
+            (false, true) => {
+                for message in stale {
+                    eprintln!("Warning: {message}");
+                }
+                Ok(())
+            }
+            (false, false) => eyre::bail!(
+                "{}, use --allow-stale to generate Cairo anyway",
+                stale.join("; ")
+            ),
+        }
+    }
+}
+
+/// Formats a non-negative number of seconds with its two most significant units.
+fn format_duration(seconds: i64) -> String {
+    let (days, hours, minutes) = (seconds / 86400, seconds / 3600 % 24, seconds / 60 % 60);
+    if days > 0 {
+        format!("{days}d {hours}h")
+    } else if hours > 0 {
+        format!("{hours}h {minutes}m")
+    } else {
+        format!("{minutes}m {}s", seconds % 60)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_utils::Pki;
+
+    fn args(at: &str, allow_stale: bool) -> FreshnessArgs {
+        FreshnessArgs {
+            at: Some(at.to_owned()),
+            allow_stale,
+        }
+    }
+
+    fn collateral() -> Validity {
+        Validity::collateral("TCB Info", "2024-03-18T10:35:22Z", "2024-04-17T10:35:22Z").unwrap()
+    }
+
+    #[test]
+    fn accepts_items_within_their_validity() {
+        for at in [
+            "2024-03-18T10:35:22Z",
+            "2024-04-01T00:00:00Z",
+            "2024-04-17T10:35:21Z",
+        ] {
+            args(at, false).check(&[collateral()]).unwrap();
+        }
+
+        let unchecked = FreshnessArgs {
+            at: None,
+            allow_stale: false,
+        };
+        unchecked.check(&[collateral()]).unwrap();
+    }
+
+    #[test]
+    fn rejects_expired_and_not_yet_valid_items() {
+        for (at, error) in [
+            (
+                "2024-04-17T10:35:22Z",
+                "TCB Info expired 0m 0s before the reference time",
+            ),
+            (
+                "2024-04-18T12:00:00+01:00",
+                "TCB Info expired 1d 0h before the reference time",
+            ),
+            (
+                "2024-03-18T10:35:21Z",
+                "TCB Info is not valid until 0m 1s after the reference time",
+            ),
+            (
+                "2024-03-18T08:00:00Z",
+                "TCB Info is not valid until 2h 35m after the reference time",
+            ),
+        ] {
+            assert_eq!(
+                args(at, false)
+                    .check(&[collateral()])
+                    .unwrap_err()
+                    .to_string(),
+                format!("{error}, use --allow-stale to generate Cairo anyway")
+            );
+            args(at, true).check(&[collateral()]).unwrap();
+        }
+    }
+
+    #[test]
+    fn reports_every_stale_item() {
+        let identity = Validity::collateral(
+            "Enclave identity",
+            "2024-04-17T10:35:22Z",
+            "2024-05-17T10:35:22Z",
+        )
+        .unwrap();
+        let items = [collateral(), identity];
+
+        assert_eq!(
+            args("2024-03-01T00:00:00Z", false)
+                .check(&items)
+                .unwrap_err()
+                .to_string(),
+            "TCB Info is not valid until 17d 10h after the reference time; Enclave identity is \
+             not valid until 47d 10h after the reference time, use --allow-stale to generate Cairo \
+             anyway"
+        );
+    }
+
+    #[test]
+    fn checks_at_current_time() {
+        let error = args("now", false).check(&[collateral()]).unwrap_err();
+        assert!(error.to_string().starts_with("TCB Info expired"));
+
+        let current =
+            Validity::collateral("TCB Info", "2024-03-18T10:35:22Z", "9999-12-31T00:00:00Z");
+        args("now", false).check(&[current.unwrap()]).unwrap();
+
+        assert!(args("yesterday", false).check(&[collateral()]).is_err());
+    }
+
+    #[test]
+    fn names_certificates_after_their_subject() {
+        let pki = Pki::new("Test");
+        let validities = Validity::certificates("Issuer chain", &pki.chain_der()).unwrap();
+
+        let names = validities
+            .iter()
+            .map(|validity| validity.name.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            names,
+            [
+                "Issuer chain certificate CN=Test Signing",
+                "Issuer chain certificate CN=Test Intermediate CA",
+                "Issuer chain certificate CN=Test Root CA",
+            ]
+        );
+        args("now", false).check(&validities).unwrap();
+        assert!(
+            args("1970-01-01T00:00:00Z", false)
+                .check(&validities)
+                .is_err()
+        );
+    }
+}
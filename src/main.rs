@@ -2,7 +2,7 @@ use clap::{Parser, Subcommand};
 use eyre::Result;
 
 mod commands;
-use commands::Preprocess;
+use commands::{Bundle, Codegen, Collateral, DiffCommand, Preprocess};
 
 mod quote;
 
@@ -10,6 +10,37 @@ mod constants;
 
 mod cairo;
 
+mod cairo_types;
+
+mod chain;
+
+mod datetime;
+
+mod emitter;
+
+mod signed_body;
+
+mod verify;
+
+mod freshness;
+
+mod bundle;
+
+mod pck;
+
+mod store;
+
+mod pcs;
+
+mod diff;
+
+mod schema;
+
+mod template;
+
+#[cfg(test)]
+mod test_utils;
+
 #[derive(Debug, Parser)]
 struct Cli {
     #[clap(subcommand)]
@@ -20,6 +51,14 @@ struct Cli {
 enum Subcommands {
     /// Pre-process test data from `dcap-rs` to be used in `dcap-cairo`.
     Preprocess(Preprocess),
+    /// Generate Cairo code independent of any input data.
+    Codegen(Codegen),
+    /// Package quotes and collateral into single-file attestation bundles.
+    Bundle(Bundle),
+    /// Manage a local store of collateral.
+    Collateral(Collateral),
+    /// Show the semantic changes between two tcbinfo or qeidentity files.
+    Diff(DiffCommand),
 }
 
 fn main() -> Result<()> {
@@ -29,5 +68,9 @@ fn main() -> Result<()> {
 
     match cli.command {
         Subcommands::Preprocess(cmd) => cmd.run(),
+        Subcommands::Codegen(cmd) => cmd.run(),
+        Subcommands::Bundle(cmd) => cmd.run(),
+        Subcommands::Collateral(cmd) => cmd.run(),
+        Subcommands::Diff(cmd) => cmd.run(),
     }
 }
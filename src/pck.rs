@@ -0,0 +1,159 @@
+use eyre::Result;
+use x509_parser::{
+    der_parser::der::parse_der,
+    pem::Pem,
+    prelude::{FromDer, X509Certificate},
+};
+
+use crate::{
+    chain::order_chain,
+    quote::{CertData, Quote},
+};
+
+/// OID of the Intel SGX extension of PCK certificates.
+const SGX_EXTENSION_OID: &str = "1.2.840.113741.1.13.1";
+
+/// OID of the FMSPC entry within the SGX extension.
+const FMSPC_OID: &str = "1.2.840.113741.1.13.1.4";
+
+/// DER-encoded PCK certificate chain embedded in `quote`, ordered from leaf to root.
+pub fn pck_chain(quote: &Quote) -> Result<Vec<Vec<u8>>> {
+    let CertData::QeReportCertData(qe_report) = &quote.signature.cert_data else {
+        eyre::bail!("Unexpected cert data type");
+    };
+    let cert_data = qe_report.qe_cert_data.borrow();
+    let CertData::Certificates(payload) = &*cert_data else {
+        eyre::bail!("Unexpected cert data type");
+    };
+
+    let mut certs = vec![];
+    for pem in Pem::iter_from_buffer(payload) {
+        let pem = pem?;
+        if pem.label != "CERTIFICATE" {
+            eyre::bail!("Unexpected PEM label: {}", pem.label);
+        }
+
+        certs.push(pem.contents);
+    }
+
+    order_chain(certs)
+}
+
+/// FMSPC found in the SGX extension of a DER-encoded PCK certificate.
+pub fn fmspc(pck_cert: &[u8]) -> Result<[u8; 6]> {
+    let (_, cert) = X509Certificate::from_der(pck_cert)
+        .map_err(|err| eyre::eyre!("Failed to parse PCK certificate: {err}"))?;
+    let Some(extension) = cert
+        .extensions()
+        .iter()
+        .find(|extension| extension.oid.to_id_string() == SGX_EXTENSION_OID)
+    else {
+        eyre::bail!("PCK certificate has no SGX extension");
+    };
+
+    let invalid = |err: String| eyre::eyre!("Invalid SGX extension: {err}");
+    let (_, entries) = parse_der(extension.value).map_err(|err| invalid(err.to_string()))?;
+
+    // The extension is a sequence of (OID, value) sequences
+    for entry in entries
+        .as_sequence()
+        .map_err(|err| invalid(err.to_string()))?
+    {
+        let [oid, value] = entry
+            .as_sequence()
+            .map_err(|err| invalid(err.to_string()))?
+            .as_slice()
+        else {
+            continue;
+        };
+
+        if oid.as_oid().map(|oid| oid.to_id_string()).as_deref() == Ok(FMSPC_OID) {
+            let value = value.as_slice().map_err(|err| invalid(err.to_string()))?;
+            return value
+                .try_into()
+                .map_err(|_| eyre::eyre!("Invalid FMSPC length: {}", value.len()));
+        }
+    }
+
+    eyre::bail!("PCK certificate has no FMSPC")
+}
+
+#[cfg(test)]
+mod tests {
+    use rcgen::{CertificateParams, CustomExtension, KeyPair};
+
+    use super::*;
+
+    /// Self-signed certificate with an SGX extension made of the given DER-encoded entries.
+    fn pck_cert(entries: &[u8]) -> Vec<u8> {
+        let mut extension = vec![0x30, entries.len() as u8];
+        extension.extend_from_slice(entries);
+
+        let mut params = CertificateParams::default();
+        params.custom_extensions = vec![CustomExtension::from_oid_content(
+            &[1, 2, 840, 113741, 1, 13, 1],
+            extension,
+        )];
+        params
+            .self_signed(&KeyPair::generate().unwrap())
+            .unwrap()
+            .der()
+            .to_vec()
+    }
+
+    /// DER-encoded entry of the SGX extension with the FMSPC OID and `value` as an octet string.
+    fn fmspc_entry(value: &[u8]) -> Vec<u8> {
+        let mut entry = vec![
+            0x30,
+            14 + value.len() as u8,
+            0x06,
+            0x0A,
+            0x2A,
+            0x86,
+            0x48,
+            0x86,
+            0xF8,
+            0x4D,
+            0x01,
+            0x0D,
+            0x01,
+            0x04,
+            0x04,
+            value.len() as u8,
+        ];
+        entry.extend_from_slice(value);
+        entry
+    }
+
+    #[test]
+    fn finds_fmspc_in_sgx_extension() {
+        let fmspc_value = [0x00, 0x90, 0x6E, 0xD5, 0x00, 0x00];
+
+        assert_eq!(
+            fmspc(&pck_cert(&fmspc_entry(&fmspc_value))).unwrap(),
+            fmspc_value
+        );
+    }
+
+    #[test]
+    fn rejects_missing_or_invalid_fmspc() {
+        assert_eq!(
+            fmspc(&pck_cert(&[])).unwrap_err().to_string(),
+            "PCK certificate has no FMSPC"
+        );
+        assert_eq!(
+            fmspc(&pck_cert(&fmspc_entry(&[0x00, 0x90])))
+                .unwrap_err()
+                .to_string(),
+            "Invalid FMSPC length: 2"
+        );
+
+        let cert = CertificateParams::default()
+            .self_signed(&KeyPair::generate().unwrap())
+            .unwrap();
+        assert_eq!(
+            fmspc(cert.der()).unwrap_err().to_string(),
+            "PCK certificate has no SGX extension"
+        );
+    }
+}
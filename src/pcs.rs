@@ -0,0 +1,171 @@
+use clap::ValueEnum;
+use eyre::Result;
+use ureq::Agent;
+
+use crate::{quote::TeeType, verify::url_decode};
+
+/// Base URL of the Intel Provisioning Certification Service.
+pub const INTEL_PCS_URL: &str = "https://api.trustedservices.intel.com";
+
+/// Where Intel publishes the CRL of the Intel SGX Root CA, which PCS itself does not serve.
+pub const INTEL_ROOT_CA_CRL_URL: &str =
+    "https://certificates.trustedservices.intel.com/IntelSGXRootCA.der";
+
+/// Issuer chain headers, from the most to the least recent API.
+const TCB_INFO_ISSUER_CHAIN_HEADERS: [&str; 2] =
+    ["TCB-Info-Issuer-Chain", "SGX-TCB-Info-Issuer-Chain"];
+const ENCLAVE_IDENTITY_ISSUER_CHAIN_HEADERS: [&str; 1] = ["SGX-Enclave-Identity-Issuer-Chain"];
+const PCK_CRL_ISSUER_CHAIN_HEADERS: [&str; 1] = ["SGX-PCK-CRL-Issuer-Chain"];
+
+/// First byte of any DER-encoded CRL.
+const DER_SEQUENCE_TAG: u8 = 0x30;
+
+/// CA issuing the PCK certificates covered by a PCK CRL.
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum PckCa {
+    Processor,
+    Platform,
+}
+
+/// Client of the v4 API of PCS, or of a PCCS exposing the same API.
+#[derive(Debug)]
+pub struct PcsClient {
+    base_url: String,
+    agent: Agent,
+}
+
+/// Collateral as served, along with its PEM issuer chain decoded from the response headers.
+#[derive(Debug)]
+pub struct Fetched {
+    pub body: Vec<u8>,
+    pub issuer_chain: Option<Vec<u8>>,
+}
+
+impl PcsClient {
+    pub fn new(base_url: &str) -> Self {
+        Self {
+            base_url: base_url.trim_end_matches('/').to_owned(),
+            agent: Agent::config_builder()
+                .http_status_as_error(false)
+                .build()
+                .into(),
+        }
+    }
+
+    pub fn tcb_info(
+        &self,
+        tee_type: TeeType,
+        fmspc: &[u8],
+        tcb_evaluation_data_number: Option<u32>,
+    ) -> Result<Fetched> {
+        let mut query = vec![("fmspc", hex::encode_upper(fmspc))];
+        query.extend(evaluation_query(tcb_evaluation_data_number));
+        self.get(
+            &format!("{}/tcb", api_path(tee_type)),
+            &query,
+            &TCB_INFO_ISSUER_CHAIN_HEADERS,
+        )
+    }
+
+    /// Identity of the quoting enclave of `tee_type`.
+    pub fn qe_identity(
+        &self,
+        tee_type: TeeType,
+        tcb_evaluation_data_number: Option<u32>,
+    ) -> Result<Fetched> {
+        self.get(
+            &format!("{}/qe/identity", api_path(tee_type)),
+            &evaluation_query(tcb_evaluation_data_number),
+            &ENCLAVE_IDENTITY_ISSUER_CHAIN_HEADERS,
+        )
+    }
+
+    /// DER-encoded PCK CRL.
+    pub fn pck_crl(&self, ca: PckCa) -> Result<Fetched> {
+        let ca = match ca {
+            PckCa::Processor => "processor",
+            PckCa::Platform => "platform",
+        };
+        self.get(
+            &format!("{}/pckcrl", api_path(TeeType::Sgx)),
+            &[("ca", ca.to_owned()), ("encoding", "der".to_owned())],
+            &PCK_CRL_ISSUER_CHAIN_HEADERS,
+        )
+    }
+
+    /// DER-encoded CRL of the root CA, from Intel for PCS and from the `rootcacrl` endpoint for a
+    /// PCCS. The root CA signs its own CRL, so no issuer chain comes with it.
+    pub fn root_ca_crl(&self) -> Result<Vec<u8>> {
+        let body = if self.base_url == INTEL_PCS_URL {
+            self.get_url(INTEL_ROOT_CA_CRL_URL, &[], &[])?.body
+        } else {
+            self.get(&format!("{}/rootcacrl", api_path(TeeType::Sgx)), &[], &[])?
+                .body
+        };
+
+        // PCCS serves it hex-encoded
+        if body.first() == Some(&DER_SEQUENCE_TAG) {
+            Ok(body)
+        } else {
+            hex::decode(body.trim_ascii())
+                .map_err(|err| eyre::eyre!("Invalid root CA CRL, neither DER nor hex: {err}"))
+        }
+    }
+
+    fn get(
+        &self,
+        path: &str,
+        query: &[(&str, String)],
+        issuer_chain_headers: &[&str],
+    ) -> Result<Fetched> {
+        self.get_url(
+            &format!("{}{path}", self.base_url),
+            query,
+            issuer_chain_headers,
+        )
+    }
+
+    fn get_url(
+        &self,
+        url: &str,
+        query: &[(&str, String)],
+        issuer_chain_headers: &[&str],
+    ) -> Result<Fetched> {
+        let mut request = self.agent.get(url);
+        for (key, value) in query {
+            request = request.query(*key, value);
+        }
+
+        let mut response = request
+            .call()
+            .map_err(|err| eyre::eyre!("Request to {url} failed: {err}"))?;
+        if !response.status().is_success() {
+            eyre::bail!("Request to {url} failed with status {}", response.status());
+        }
+
+        let issuer_chain = issuer_chain_headers
+            .iter()
+            .find_map(|header| response.headers().get(*header))
+            .map(|value| url_decode(value.to_str()?))
+            .transpose()?;
+
+        Ok(Fetched {
+            body: response.body_mut().read_to_vec()?,
+            issuer_chain,
+        })
+    }
+}
+
+const fn api_path(tee_type: TeeType) -> &'static str {
+    match tee_type {
+        TeeType::Sgx => "/sgx/certification/v4",
+        TeeType::Tdx => "/tdx/certification/v4",
+    }
+}
+
+fn evaluation_query(tcb_evaluation_data_number: Option<u32>) -> Vec<(&'static str, String)> {
+    tcb_evaluation_data_number
+        .map(|number| ("tcbEvaluationDataNumber", number.to_string()))
+        .into_iter()
+        .collect()
+}
@@ -1,5 +1,7 @@
 use std::cell::RefCell;
 
+use clap::ValueEnum;
+
 use eyre::Result;
 
 use crate::constants::{
@@ -55,7 +57,13 @@ impl Quote {
 
 #[derive(Debug)]
 pub struct Header {
+    pub version: u16,
+    pub att_key_type: u16,
     pub tee_type: TeeType,
+    pub qe_svn: u16,
+    pub pce_svn: u16,
+    pub qe_vendor_id: [u8; 16],
+    pub user_data: [u8; 20],
     pub raw: [u8; HEADER_LEN],
 }
 
@@ -74,7 +82,13 @@ impl Header {
         };
 
         Ok(Self {
+            version: u16::from_le_bytes(bytes[0..2].try_into()?),
+            att_key_type: u16::from_le_bytes(bytes[2..4].try_into()?),
             tee_type,
+            qe_svn: u16::from_le_bytes(bytes[8..10].try_into()?),
+            pce_svn: u16::from_le_bytes(bytes[10..12].try_into()?),
+            qe_vendor_id: bytes[12..28].try_into()?,
+            user_data: bytes[28..48].try_into()?,
             raw: bytes.try_into()?,
         })
     }
@@ -234,13 +248,107 @@ impl QeReportCertData {
     }
 }
 
+/// SGX enclave report, used both as the SGX quote body and as the QE report.
+#[derive(Debug)]
+pub struct EnclaveReport {
+    pub cpu_svn: [u8; 16],
+    pub misc_select: [u8; 4],
+    pub attributes: [u8; 16],
+    pub mrenclave: [u8; 32],
+    pub mrsigner: [u8; 32],
+    pub isv_prod_id: u16,
+    pub isv_svn: u16,
+    pub report_data: [u8; 64],
+}
+
+impl EnclaveReport {
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        if bytes.len() != ENCLAVE_REPORT_LEN {
+            eyre::bail!("Invalid enclave report length: {}", bytes.len());
+        }
+
+        Ok(Self {
+            cpu_svn: bytes[0..16].try_into()?,
+            misc_select: bytes[16..20].try_into()?,
+            attributes: bytes[48..64].try_into()?,
+            mrenclave: bytes[64..96].try_into()?,
+            mrsigner: bytes[128..160].try_into()?,
+            isv_prod_id: u16::from_le_bytes(bytes[256..258].try_into()?),
+            isv_svn: u16::from_le_bytes(bytes[258..260].try_into()?),
+            report_data: bytes[320..384].try_into()?,
+        })
+    }
+}
+
+/// TDX 1.0 TD report, used as the TDX quote body.
 #[derive(Debug)]
+pub struct Td10Report {
+    pub tee_tcb_svn: [u8; 16],
+    pub mrseam: [u8; 48],
+    pub mrsignerseam: [u8; 48],
+    pub seam_attributes: [u8; 8],
+    pub td_attributes: [u8; 8],
+    pub xfam: [u8; 8],
+    pub mrtd: [u8; 48],
+    pub mrconfigid: [u8; 48],
+    pub mrowner: [u8; 48],
+    pub mrownerconfig: [u8; 48],
+    pub rtmr0: [u8; 48],
+    pub rtmr1: [u8; 48],
+    pub rtmr2: [u8; 48],
+    pub rtmr3: [u8; 48],
+    pub report_data: [u8; 64],
+}
+
+impl Td10Report {
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        if bytes.len() != TD10_REPORT_LEN {
+            eyre::bail!("Invalid TD report length: {}", bytes.len());
+        }
+
+        Ok(Self {
+            tee_tcb_svn: bytes[0..16].try_into()?,
+            mrseam: bytes[16..64].try_into()?,
+            mrsignerseam: bytes[64..112].try_into()?,
+            seam_attributes: bytes[112..120].try_into()?,
+            td_attributes: bytes[120..128].try_into()?,
+            xfam: bytes[128..136].try_into()?,
+            mrtd: bytes[136..184].try_into()?,
+            mrconfigid: bytes[184..232].try_into()?,
+            mrowner: bytes[232..280].try_into()?,
+            mrownerconfig: bytes[280..328].try_into()?,
+            rtmr0: bytes[328..376].try_into()?,
+            rtmr1: bytes[376..424].try_into()?,
+            rtmr2: bytes[424..472].try_into()?,
+            rtmr3: bytes[472..520].try_into()?,
+            report_data: bytes[520..584].try_into()?,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
 pub enum TeeType {
     Sgx,
     Tdx,
 }
 
 impl TeeType {
+    /// Parses the `id` of a TCB Info.
+    pub fn parse(id: &str) -> Result<Self> {
+        match id {
+            "SGX" => Ok(Self::Sgx),
+            "TDX" => Ok(Self::Tdx),
+            id => eyre::bail!("Unknown TCB Info id: {id}, expected SGX or TDX"),
+        }
+    }
+
+    pub const fn name(&self) -> &'static str {
+        match self {
+            Self::Sgx => "SGX",
+            Self::Tdx => "TDX",
+        }
+    }
+
     const fn body_size(&self) -> usize {
         match self {
             Self::Sgx => ENCLAVE_REPORT_LEN,
@@ -248,3 +356,102 @@ impl TeeType {
         }
     }
 }
+
+/// Enclaves Intel publishes identities for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EnclaveId {
+    /// SGX quoting enclave.
+    Qe,
+    /// Quote verification enclave.
+    Qve,
+    /// TDX quoting enclave.
+    TdQe,
+}
+
+impl EnclaveId {
+    pub fn parse(id: &str) -> Result<Self> {
+        match id {
+            "QE" => Ok(Self::Qe),
+            "QVE" => Ok(Self::Qve),
+            "TD_QE" => Ok(Self::TdQe),
+            id => eyre::bail!("Unknown enclave identity id: {id}, expected QE, QVE or TD_QE"),
+        }
+    }
+
+    pub const fn name(&self) -> &'static str {
+        match self {
+            Self::Qe => "QE",
+            Self::Qve => "QVE",
+            Self::TdQe => "TD_QE",
+        }
+    }
+
+    /// TEE type of the quotes signed by this enclave, if it is a quoting enclave.
+    pub const fn tee_type(&self) -> Option<TeeType> {
+        match self {
+            Self::Qe => Some(TeeType::Sgx),
+            Self::Qve => None,
+            Self::TdQe => Some(TeeType::Tdx),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn counting(len: usize) -> Vec<u8> {
+        (0..len).map(|ind| ind as u8).collect()
+    }
+
+    #[test]
+    fn parses_header_fields() {
+        let mut bytes = counting(HEADER_LEN);
+        bytes[4..8].copy_from_slice(&TDX_TEE_TYPE.to_le_bytes());
+        let header = Header::from_bytes(&bytes).unwrap();
+
+        assert_eq!(header.version, 0x0100);
+        assert_eq!(header.att_key_type, 0x0302);
+        assert!(matches!(header.tee_type, TeeType::Tdx));
+        assert_eq!(header.qe_svn, 0x0908);
+        assert_eq!(header.pce_svn, 0x0b0a);
+        assert_eq!(header.qe_vendor_id[..], bytes[12..28]);
+        assert_eq!(header.user_data[..], bytes[28..48]);
+        assert_eq!(header.to_bytes(), bytes[..]);
+    }
+
+    #[test]
+    fn parses_report_fields_at_their_offsets() {
+        let bytes = counting(ENCLAVE_REPORT_LEN);
+        let report = EnclaveReport::from_bytes(&bytes).unwrap();
+        assert_eq!(report.misc_select[..], bytes[16..20]);
+        assert_eq!(report.attributes[..], bytes[48..64]);
+        assert_eq!(report.mrenclave[..], bytes[64..96]);
+        assert_eq!(report.mrsigner[..], bytes[128..160]);
+        assert_eq!(
+            report.isv_prod_id,
+            u16::from_le_bytes([bytes[256], bytes[257]])
+        );
+        assert_eq!(report.isv_svn, u16::from_le_bytes([bytes[258], bytes[259]]));
+        assert_eq!(report.report_data[..], bytes[320..384]);
+
+        let bytes = counting(TD10_REPORT_LEN);
+        let report = Td10Report::from_bytes(&bytes).unwrap();
+        assert_eq!(report.mrseam[..], bytes[16..64]);
+        assert_eq!(report.mrtd[..], bytes[136..184]);
+        assert_eq!(report.rtmr3[..], bytes[472..520]);
+        assert_eq!(report.report_data[..], bytes[520..584]);
+
+        assert_eq!(
+            EnclaveReport::from_bytes(&bytes).unwrap_err().to_string(),
+            "Invalid enclave report length: 584"
+        );
+    }
+
+    #[test]
+    fn maps_quoting_enclaves_to_tee_types() {
+        assert_eq!(EnclaveId::Qe.tee_type(), Some(TeeType::Sgx));
+        assert_eq!(EnclaveId::TdQe.tee_type(), Some(TeeType::Tdx));
+        assert_eq!(EnclaveId::Qve.tee_type(), None);
+    }
+}
@@ -0,0 +1,99 @@
+use eyre::Result;
+use serde::de::DeserializeOwned;
+
+/// Parses `json`, locating errors with the JSON path of the offending value, e.g.
+/// `tcbInfo.tcbLevels[3].tcb.sgxtcbcomponents[7].svn`.
+pub fn parse_json<T>(json: &str) -> Result<T>
+where
+    T: DeserializeOwned,
+{
+    let mut deserializer = serde_json::Deserializer::from_str(json);
+    let value = serde_path_to_error::deserialize(&mut deserializer)
+        .map_err(|err| eyre::eyre!("Invalid JSON at {}: {}", err.path(), err.inner()))?;
+    deserializer
+        .end()
+        .map_err(|err| eyre::eyre!("Invalid JSON: {err}"))?;
+
+    Ok(value)
+}
+
+/// Checks that `value`, found at `path`, is hex-encoded and decodes to `len` bytes.
+pub fn check_hex(path: &str, value: &str, len: usize) -> Result<()> {
+    let bytes =
+        hex::decode(value).map_err(|err| eyre::eyre!("Invalid hex string at {path}: {err}"))?;
+    if bytes.len() != len {
+        eyre::bail!(
+            "Invalid length at {path}: expected {len} bytes, got {}",
+            bytes.len()
+        );
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use serde::Deserialize;
+
+    use super::*;
+
+    #[derive(Debug, Deserialize)]
+    #[serde(deny_unknown_fields)]
+    struct Outer {
+        items: Vec<Inner>,
+    }
+
+    #[derive(Debug, Deserialize)]
+    #[serde(deny_unknown_fields)]
+    struct Inner {
+        svn: u8,
+    }
+
+    #[test]
+    fn locates_errors_by_json_path() {
+        let outer = parse_json::<Outer>(r#"{"items":[{"svn":1}]}"#).unwrap();
+        assert_eq!(outer.items[0].svn, 1);
+
+        let error = parse_json::<Outer>(r#"{"items":[{"svn":1},{"svn":"2"}]}"#).unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .starts_with("Invalid JSON at items[1].svn: invalid type: string \"2\""),
+            "{error}"
+        );
+
+        let error = parse_json::<Outer>(r#"{"items":[{"svn":1,"pcesvn":2}]}"#).unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .starts_with("Invalid JSON at items[0].pcesvn: unknown field `pcesvn`"),
+            "{error}"
+        );
+
+        let error = parse_json::<Outer>(r#"{"items":[]} {}"#).unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .starts_with("Invalid JSON: trailing characters")
+        );
+    }
+
+    #[test]
+    fn checks_hex_length() {
+        check_hex("tcbInfo.pceId", "0000", 2).unwrap();
+        check_hex("tcbInfo.pceId", "ABcd", 2).unwrap();
+
+        let error = check_hex("tcbInfo.pceId", "000000", 2).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Invalid length at tcbInfo.pceId: expected 2 bytes, got 3"
+        );
+
+        let error = check_hex("tcbInfo.pceId", "00z0", 2).unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .starts_with("Invalid hex string at tcbInfo.pceId: ")
+        );
+    }
+}
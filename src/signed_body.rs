@@ -0,0 +1,193 @@
+use std::{collections::HashSet, fmt, io::Write};
+
+use eyre::Result;
+use serde::{
+    Deserialize, Deserializer,
+    de::{self, MapAccess, Visitor},
+};
+use serde_json::value::RawValue;
+use sha2::{Digest, Sha256};
+
+use crate::cairo::{CairoBytesOptions, Container, Encoding, Visibility, write_cairo_encoded};
+
+/// The exact bytes of a signed collateral object along with its signature.
+#[derive(Debug)]
+pub struct SignedBody {
+    /// Byte-exact JSON text of the signed object, as found in the input file.
+    pub bytes: Vec<u8>,
+    /// SHA-256 digest of `bytes`.
+    pub digest: [u8; 32],
+    /// Hex-encoded `r || s` ECDSA P-256 signature, decoded on use so that malformed signatures
+    /// only fail the operations needing them.
+    signature: String,
+}
+
+impl SignedBody {
+    /// Extracts the value of the top-level `key` field of `json` without re-serializing it, as
+    /// Intel signs the raw bytes of that value. `signature` is the hex-encoded `r || s` signature.
+    pub fn extract(json: &str, key: &str, signature: &str) -> Result<Self> {
+        let TopLevelFields(fields) = serde_json::from_str(json)?;
+        let Some((_, value)) = fields.into_iter().find(|(name, _)| name == key) else {
+            eyre::bail!("Missing signed field: {key}");
+        };
+
+        let bytes = value.get().as_bytes().to_vec();
+        Ok(Self {
+            digest: Sha256::digest(&bytes).into(),
+            bytes,
+            signature: signature.to_owned(),
+        })
+    }
+
+    /// The `r || s` signature, each component being big-endian.
+    pub fn signature(&self) -> Result<[u8; 64]> {
+        let signature = hex::decode(&self.signature)?;
+        let Ok(signature) = <[u8; 64]>::try_from(signature.as_slice()) else {
+            eyre::bail!("Invalid signature length: {}", signature.len());
+        };
+
+        Ok(signature)
+    }
+
+    /// Renders the signed bytes as `SIGNED_BODY`, followed by `SIGNED_BODY_SHA256`, `SIGNATURE_R`
+    /// and `SIGNATURE_S` `u256` constants.
+    pub fn render(&self, encoding: Encoding) -> Result<String> {
+        let mut output = vec![];
+        write_cairo_encoded(
+            &mut output,
+            "SIGNED_BODY",
+            &self.bytes,
+            &CairoBytesOptions {
+                encoding,
+                visibility: Visibility::Pub,
+                container: Container::Const,
+            },
+        )?;
+
+        let signature = self.signature()?;
+        for (name, value) in [
+            ("SIGNED_BODY_SHA256", &self.digest[..]),
+            ("SIGNATURE_R", &signature[..32]),
+            ("SIGNATURE_S", &signature[32..]),
+        ] {
+            writeln!(output)?;
+            writeln!(output, "pub const {name}: u256 = 0x{};", hex::encode(value))?;
+        }
+
+        Ok(String::from_utf8(output)?)
+    }
+}
+
+/// Fields of a JSON object with their raw values, rejecting duplicate keys so that the signed
+/// bytes cannot differ from the parsed value.
+struct TopLevelFields<'a>(Vec<(String, &'a RawValue)>);
+
+impl<'de: 'a, 'a> Deserialize<'de> for TopLevelFields<'a> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct FieldsVisitor;
+
+        impl<'de> Visitor<'de> for FieldsVisitor {
+            type Value = TopLevelFields<'de>;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("a JSON object")
+            }
+
+            fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+            where
+                A: MapAccess<'de>,
+            {
+                let mut names = HashSet::new();
+                let mut fields = vec![];
+                while let Some((name, value)) = map.next_entry::<String, &RawValue>()? {
+                    if !names.insert(name.clone()) {
+                        return Err(de::Error::custom(format!("duplicate field: {name}")));
+                    }
+                    fields.push((name, value));
+                }
+
+                Ok(TopLevelFields(fields))
+            }
+        }
+
+        deserializer.deserialize_map(FieldsVisitor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BODY: &str = "{\"zeta\":1,  \"alpha\" : [ 1,2 ],\n  \"s\":\"\\u00e9\",\"n\":1.50}";
+
+    fn signature() -> String {
+        "11".repeat(32) + &"22".repeat(32)
+    }
+
+    #[test]
+    fn extracts_exact_bytes() {
+        let signature = signature();
+        for json in [
+            format!("{{\"tcbInfo\":{BODY},\"signature\":\"{signature}\"}}"),
+            format!("{{ \"signature\" : \"{signature}\",\n  \"tcbInfo\" :  {BODY}\n}}\n"),
+        ] {
+            let signed_body = SignedBody::extract(&json, "tcbInfo", &signature).unwrap();
+            assert_eq!(signed_body.bytes, BODY.as_bytes());
+            assert_eq!(signed_body.digest, <[u8; 32]>::from(Sha256::digest(BODY)));
+
+            let signature = signed_body.signature().unwrap();
+            assert_eq!(signature[..32], [0x11; 32]);
+            assert_eq!(signature[32..], [0x22; 32]);
+        }
+    }
+
+    #[test]
+    fn rejects_missing_or_duplicate_fields() {
+        let error = SignedBody::extract(r#"{"enclaveIdentity":{}}"#, "tcbInfo", &signature());
+        assert_eq!(
+            error.unwrap_err().to_string(),
+            "Missing signed field: tcbInfo"
+        );
+
+        let json = r#"{"tcbInfo":{},"tcbInfo":{"a":1}}"#;
+        let error = SignedBody::extract(json, "tcbInfo", &signature()).unwrap_err();
+        assert!(error.to_string().starts_with("duplicate field: tcbInfo"));
+    }
+
+    #[test]
+    fn rejects_malformed_signature_on_use() {
+        for (signature, error) in [
+            ("00", "Invalid signature length: 1"),
+            ("0g", "Invalid character 'g' at position 1"),
+        ] {
+            let signed_body = SignedBody::extract(r#"{"tcbInfo":{}}"#, "tcbInfo", signature);
+            let signed_body = signed_body.unwrap();
+            assert_eq!(signed_body.signature().unwrap_err().to_string(), error);
+            assert_eq!(
+                signed_body.render(Encoding::U8).unwrap_err().to_string(),
+                error
+            );
+        }
+    }
+
+    #[test]
+    fn renders_body_digest_and_signature() {
+        let signed_body = SignedBody::extract(r#"{"tcbInfo":{}}"#, "tcbInfo", &signature());
+
+        assert_eq!(
+            signed_body.unwrap().render(Encoding::U8).unwrap(),
+            format!(
+                "pub const SIGNED_BODY: [u8; 2] = [\n    0x7b, 0x7d,\n];\n\n\
+                 pub const SIGNED_BODY_SHA256: u256 = 0x{};\n\n\
+                 pub const SIGNATURE_R: u256 = 0x{};\n\n\
+                 pub const SIGNATURE_S: u256 = 0x{};\n",
+                hex::encode(Sha256::digest("{}")),
+                "11".repeat(32),
+                "22".repeat(32)
+            )
+        );
+    }
+}
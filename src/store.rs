@@ -0,0 +1,418 @@
+use std::path::{Component, Path, PathBuf};
+
+use eyre::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    pck::{fmspc, pck_chain},
+    quote::{EnclaveId, Quote, TeeType},
+    schema::check_hex,
+};
+
+/// Name of the index file at the root of a collateral store.
+const INDEX_FILE: &str = "index.json";
+
+/// Local directory of tcbinfo and qeidentity JSON files, indexed so that the collateral matching a
+/// quote can be picked automatically.
+#[derive(Debug)]
+pub struct CollateralStore {
+    dir: PathBuf,
+    index: Index,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+struct Index {
+    tcb_info: Vec<TcbInfoEntry>,
+    qe_identity: Vec<QeIdentityEntry>,
+}
+
+/// Index entry of a tcbinfo file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct TcbInfoEntry {
+    /// `SGX` or `TDX`.
+    pub tee_type: String,
+    /// Lowercase hex-encoded FMSPC.
+    pub fmspc: String,
+    pub tcb_evaluation_data_number: u32,
+    /// Path of the file, relative to the store directory.
+    pub file: String,
+}
+
+/// Index entry of a qeidentity file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct QeIdentityEntry {
+    /// `QE`, `QVE` or `TD_QE`.
+    pub id: String,
+    pub tcb_evaluation_data_number: u32,
+    /// Path of the file, relative to the store directory.
+    pub file: String,
+}
+
+/// Imported collateral, as indexed.
+#[derive(Debug)]
+pub enum StoreEntry {
+    TcbInfo(TcbInfoEntry),
+    QeIdentity(QeIdentityEntry),
+}
+
+/// Just enough of any tcbinfo or qeidentity version to index it.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CollateralJson {
+    tcb_info: Option<TcbInfoKeyJson>,
+    enclave_identity: Option<QeIdentityKeyJson>,
+    qe_identity: Option<QeIdentityKeyJson>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TcbInfoKeyJson {
+    // Missing from v2, which is SGX only
+    id: Option<String>,
+    fmspc: String,
+    tcb_evaluation_data_number: u32,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct QeIdentityKeyJson {
+    // Both missing from v1, which is QE only
+    id: Option<String>,
+    tcb_evaluation_data_number: Option<u32>,
+}
+
+impl CollateralStore {
+    /// Opens the store in `dir`, which is empty until something is imported into it.
+    pub fn open(dir: &Path) -> Result<Self> {
+        let index_path = dir.join(INDEX_FILE);
+        let index = if index_path.exists() {
+            serde_json::from_str(&std::fs::read_to_string(&index_path)?)
+                .map_err(|err| eyre::eyre!("Invalid store index {}: {err}", index_path.display()))?
+        } else {
+            Index::default()
+        };
+
+        Ok(Self {
+            dir: dir.to_owned(),
+            index,
+        })
+    }
+
+    pub fn tcb_infos(&self) -> &[TcbInfoEntry] {
+        &self.index.tcb_info
+    }
+
+    pub fn qe_identities(&self) -> &[QeIdentityEntry] {
+        &self.index.qe_identity
+    }
+
+    /// Copies a tcbinfo or qeidentity JSON document into the store, replacing any previous one
+    /// with the same key.
+    pub fn import(&mut self, json: &str) -> Result<StoreEntry> {
+        let collateral: CollateralJson = serde_json::from_str(json)?;
+        let entry = match collateral {
+            CollateralJson {
+                tcb_info: Some(tcb_info),
+                enclave_identity: None,
+                qe_identity: None,
+            } => {
+                // Both keys end up in the file name, so only accept known values
+                let tee_type = match &tcb_info.id {
+                    Some(id) => TeeType::parse(id)?,
+                    None => TeeType::Sgx,
+                };
+                check_hex("tcbInfo.fmspc", &tcb_info.fmspc, 6)?;
+                let fmspc = tcb_info.fmspc.to_lowercase();
+                StoreEntry::TcbInfo(TcbInfoEntry {
+                    file: format!(
+                        "tcbinfo/{}-{fmspc}-{}.json",
+                        tee_type.name().to_lowercase(),
+                        tcb_info.tcb_evaluation_data_number
+                    ),
+                    tee_type: tee_type.name().to_owned(),
+                    fmspc,
+                    tcb_evaluation_data_number: tcb_info.tcb_evaluation_data_number,
+                })
+            }
+            CollateralJson {
+                tcb_info: None,
+                enclave_identity: Some(identity),
+                qe_identity: None,
+            }
+            | CollateralJson {
+                tcb_info: None,
+                enclave_identity: None,
+                qe_identity: Some(identity),
+            } => {
+                let id = match &identity.id {
+                    Some(id) => EnclaveId::parse(id)?,
+                    None => EnclaveId::Qe,
+                };
+                let tcb_evaluation_data_number = identity.tcb_evaluation_data_number.unwrap_or(0);
+                StoreEntry::QeIdentity(QeIdentityEntry {
+                    file: format!(
+                        "qeidentity/{}-{tcb_evaluation_data_number}.json",
+                        id.name().to_lowercase()
+                    ),
+                    id: id.name().to_owned(),
+                    tcb_evaluation_data_number,
+                })
+            }
+            _ => eyre::bail!("Expected exactly one of tcbInfo, enclaveIdentity and qeIdentity"),
+        };
+
+        let file = match &entry {
+            StoreEntry::TcbInfo(entry) => {
+                let tcb_infos = &mut self.index.tcb_info;
+                tcb_infos.retain(|existing| existing.file != entry.file);
+                tcb_infos.push(entry.clone());
+                tcb_infos.sort_by(|a, b| a.file.cmp(&b.file));
+                &entry.file
+            }
+            StoreEntry::QeIdentity(entry) => {
+                let qe_identities = &mut self.index.qe_identity;
+                qe_identities.retain(|existing| existing.file != entry.file);
+                qe_identities.push(entry.clone());
+                qe_identities.sort_by(|a, b| a.file.cmp(&b.file));
+                &entry.file
+            }
+        };
+
+        let path = self.path(file)?;
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, json)?;
+
+        let mut index = serde_json::to_string_pretty(&self.index)?;
+        index.push('\n');
+        std::fs::write(self.dir.join(INDEX_FILE), index)?;
+
+        Ok(entry)
+    }
+
+    /// Path of the tcbinfo matching the TEE type and the FMSPC of the PCK certificate of `quote`,
+    /// with the given TCB evaluation data number or else the highest one.
+    pub fn tcb_info_for_quote(
+        &self,
+        quote: &Quote,
+        tcb_evaluation_data_number: Option<u32>,
+    ) -> Result<PathBuf> {
+        let tee_type = quote.header.tee_type.name();
+        let fmspc = hex::encode(fmspc(&pck_chain(quote)?[0])?);
+
+        let entry = self
+            .index
+            .tcb_info
+            .iter()
+            .filter(|entry| entry.tee_type == tee_type && entry.fmspc == fmspc)
+            .filter(|entry| {
+                tcb_evaluation_data_number
+                    .is_none_or(|number| entry.tcb_evaluation_data_number == number)
+            })
+            .max_by_key(|entry| entry.tcb_evaluation_data_number);
+        let Some(entry) = entry else {
+            eyre::bail!(
+                "No {tee_type} tcbinfo for FMSPC {fmspc}{} in store",
+                evaluation_suffix(tcb_evaluation_data_number)
+            );
+        };
+
+        self.path(&entry.file)
+    }
+
+    /// Path of the identity of the quoting enclave of `quote`, with the given TCB evaluation data
+    /// number or else the highest one.
+    pub fn qe_identity_for_quote(
+        &self,
+        quote: &Quote,
+        tcb_evaluation_data_number: Option<u32>,
+    ) -> Result<PathBuf> {
+        let id = match quote.header.tee_type {
+            TeeType::Sgx => "QE",
+            TeeType::Tdx => "TD_QE",
+        };
+
+        let entry = self
+            .index
+            .qe_identity
+            .iter()
+            .filter(|entry| entry.id == id)
+            .filter(|entry| {
+                tcb_evaluation_data_number
+                    .is_none_or(|number| entry.tcb_evaluation_data_number == number)
+            })
+            .max_by_key(|entry| entry.tcb_evaluation_data_number);
+        let Some(entry) = entry else {
+            eyre::bail!(
+                "No {id} identity{} in store",
+                evaluation_suffix(tcb_evaluation_data_number)
+            );
+        };
+
+        self.path(&entry.file)
+    }
+
+    /// Path of `file`, which must be relative and stay within the store directory.
+    fn path(&self, file: &str) -> Result<PathBuf> {
+        let file = Path::new(file);
+        if file.as_os_str().is_empty()
+            || !file
+                .components()
+                .all(|component| matches!(component, Component::Normal(_)))
+        {
+            eyre::bail!("Store file path escapes the store: {}", file.display());
+        }
+
+        Ok(self.dir.join(file))
+    }
+}
+
+fn evaluation_suffix(tcb_evaluation_data_number: Option<u32>) -> String {
+    tcb_evaluation_data_number
+        .map(|number| format!(" with TCB evaluation data number {number}"))
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TCB_INFO_V3: &str = r#"{"tcbInfo":{"id":"TDX","version":3,"fmspc":"00806F050000","tcbEvaluationDataNumber":17},"signature":"00"}"#;
+
+    #[test]
+    fn indexes_imported_collateral() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut store = CollateralStore::open(dir.path()).unwrap();
+
+        store.import(TCB_INFO_V3).unwrap();
+        // v2 has no id and is SGX only
+        store
+            .import(
+                r#"{"tcbInfo":{"version":2,"fmspc":"00906ED50000","tcbEvaluationDataNumber":16}}"#,
+            )
+            .unwrap();
+        store
+            .import(
+                r#"{"enclaveIdentity":{"id":"TD_QE","version":2,"tcbEvaluationDataNumber":17}}"#,
+            )
+            .unwrap();
+        // v1 has neither id nor TCB evaluation data number
+        store.import(r#"{"qeIdentity":{"version":1}}"#).unwrap();
+
+        let store = CollateralStore::open(dir.path()).unwrap();
+        assert_eq!(
+            store.tcb_infos(),
+            [
+                TcbInfoEntry {
+                    tee_type: "SGX".to_owned(),
+                    fmspc: "00906ed50000".to_owned(),
+                    tcb_evaluation_data_number: 16,
+                    file: "tcbinfo/sgx-00906ed50000-16.json".to_owned(),
+                },
+                TcbInfoEntry {
+                    tee_type: "TDX".to_owned(),
+                    fmspc: "00806f050000".to_owned(),
+                    tcb_evaluation_data_number: 17,
+                    file: "tcbinfo/tdx-00806f050000-17.json".to_owned(),
+                },
+            ]
+        );
+        assert_eq!(
+            store.qe_identities(),
+            [
+                QeIdentityEntry {
+                    id: "QE".to_owned(),
+                    tcb_evaluation_data_number: 0,
+                    file: "qeidentity/qe-0.json".to_owned(),
+                },
+                QeIdentityEntry {
+                    id: "TD_QE".to_owned(),
+                    tcb_evaluation_data_number: 17,
+                    file: "qeidentity/td_qe-17.json".to_owned(),
+                },
+            ]
+        );
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("tcbinfo/tdx-00806f050000-17.json")).unwrap(),
+            TCB_INFO_V3
+        );
+    }
+
+    #[test]
+    fn replaces_collateral_with_the_same_key() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut store = CollateralStore::open(dir.path()).unwrap();
+
+        store.import(TCB_INFO_V3).unwrap();
+        let reissued = TCB_INFO_V3.replace(r#""signature":"00""#, r#""signature":"01""#);
+        store.import(&reissued).unwrap();
+
+        assert_eq!(store.tcb_infos().len(), 1);
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join(&store.tcb_infos()[0].file)).unwrap(),
+            reissued
+        );
+    }
+
+    #[test]
+    fn rejects_ambiguous_collateral() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut store = CollateralStore::open(dir.path()).unwrap();
+
+        for json in [
+            r#"{"signature":"00"}"#,
+            r#"{"enclaveIdentity":{"id":"QE"},"qeIdentity":{"version":1}}"#,
+        ] {
+            assert_eq!(
+                store.import(json).unwrap_err().to_string(),
+                "Expected exactly one of tcbInfo, enclaveIdentity and qeIdentity"
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_invalid_index() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join(INDEX_FILE), r#"{"tcbInfo":[]}"#).unwrap();
+
+        let err = CollateralStore::open(dir.path()).unwrap_err().to_string();
+        assert!(err.starts_with("Invalid store index"), "{err}");
+    }
+
+    #[test]
+    fn import_rejects_hostile_ids() {
+        let root = tempfile::tempdir().unwrap();
+        let dir = root.path().join("store");
+        let mut store = CollateralStore::open(&dir).unwrap();
+
+        for json in [
+            r#"{"tcbInfo":{"id":"../../pwned","fmspc":"00906ED50000","tcbEvaluationDataNumber":17}}"#,
+            r#"{"tcbInfo":{"id":"SGX","fmspc":"../../pwned","tcbEvaluationDataNumber":17}}"#,
+            r#"{"enclaveIdentity":{"id":"../../pwned","tcbEvaluationDataNumber":17}}"#,
+            r#"{"qeIdentity":{"id":"/tmp/pwned"}}"#,
+        ] {
+            assert!(store.import(json).is_err(), "accepted {json}");
+        }
+        assert!(!dir.exists());
+        assert_eq!(std::fs::read_dir(root.path()).unwrap().count(), 0);
+    }
+
+    #[test]
+    fn path_stays_within_store() {
+        let store = CollateralStore::open(Path::new("store")).unwrap();
+
+        assert!(store.path("tcbinfo/sgx-00906ed50000-17.json").is_ok());
+        for file in [
+            "../pwned.json",
+            "tcbinfo/../../pwned.json",
+            "/tmp/pwned.json",
+            "",
+        ] {
+            assert!(store.path(file).is_err(), "accepted {file}");
+        }
+    }
+}
@@ -0,0 +1,415 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use clap::Args;
+use eyre::Result;
+use minijinja::{Environment, Error, ErrorKind, Value, value::ValueKind};
+use serde::Serialize;
+
+use crate::{
+    datetime::parse_datetime,
+    emitter::{
+        DateFormat, Expr, StringFormat, TCB_STATUS_TYPE, TcbStatusFormat, tcb_date_lets,
+        tcb_date_var_name,
+    },
+};
+
+/// Options for rendering generated Cairo code through user-supplied templates.
+#[derive(Debug, Clone, Args)]
+pub struct TemplateArgs {
+    /// Directory with templates overriding the default ones, named `tcbinfo.cairo.j2`,
+    /// `qeidentity.cairo.j2` and `quote.cairo.j2`. Templates missing from the directory fall back
+    /// to the default.
+    #[clap(long)]
+    template_dir: Option<PathBuf>,
+}
+
+/// Kind of generated Cairo file, each rendered by its own template.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TemplateKind {
+    Tcbinfo,
+    Qeidentity,
+    Quote,
+}
+
+impl TemplateKind {
+    pub const ALL: [Self; 3] = [Self::Tcbinfo, Self::Qeidentity, Self::Quote];
+
+    pub const fn file_name(&self) -> &'static str {
+        match self {
+            Self::Tcbinfo => "tcbinfo.cairo.j2",
+            Self::Qeidentity => "qeidentity.cairo.j2",
+            Self::Quote => "quote.cairo.j2",
+        }
+    }
+
+    /// Template reproducing the built-in output.
+    pub const fn default_source(&self) -> &'static str {
+        match self {
+            Self::Tcbinfo => include_str!("templates/tcbinfo.cairo.j2"),
+            Self::Qeidentity => include_str!("templates/qeidentity.cairo.j2"),
+            Self::Quote => include_str!("templates/quote.cairo.j2"),
+        }
+    }
+}
+
+/// Code generated by the built-in emitter, exposed to templates next to the parsed model.
+#[derive(Debug, Serialize)]
+pub struct Generated {
+    /// `use` statement needed by the generated dates, or an empty string.
+    pub date_imports: String,
+    /// `use` statement importing the generated types.
+    pub type_imports: String,
+    /// Type annotation of `let` bindings holding dates, or `None` when it is inferred.
+    pub date_let_type: Option<String>,
+    /// Type definitions to include in the output, each followed by an empty line.
+    pub type_defs: String,
+    /// The `data` function returning the whole collateral or quote.
+    pub data: String,
+    /// Signed body items requested with `--signed-body`, or an empty string.
+    pub signed_body: String,
+    /// Module path the types are imported from.
+    pub types_module: String,
+    /// Resolved type names keyed by their default name.
+    pub types: BTreeMap<String, String>,
+}
+
+impl TemplateArgs {
+    /// Renders the template of `kind` against `model`, whose fields become top-level template
+    /// variables next to the fields of `generated`.
+    ///
+    /// Filters rendering Cairo expressions take an optional indentation level, laying out the
+    /// expression as the built-in output would at that level.
+    pub fn render<M>(
+        &self,
+        kind: TemplateKind,
+        model: &M,
+        generated: Generated,
+        date_format: DateFormat,
+        string_format: StringFormat,
+        tcb_status_format: TcbStatusFormat,
+    ) -> Result<String>
+    where
+        M: Serialize,
+    {
+        let source = match &self.template_dir {
+            Some(dir) => read_template(dir, kind)?,
+            None => None,
+        };
+
+        let tcb_status_type = generated
+            .types
+            .get(TCB_STATUS_TYPE)
+            .cloned()
+            .unwrap_or_else(|| TCB_STATUS_TYPE.to_owned());
+
+        let mut env = Environment::new();
+        env.set_keep_trailing_newline(true);
+        env.add_filter("cairo_bytes", |hex_str: &str, level: Option<usize>| {
+            Ok(Expr::bytes(&decode_hex(hex_str)?)
+                .span()
+                .render(level.unwrap_or_default()))
+        });
+        env.add_filter(
+            "cairo_fixed_bytes",
+            |hex_str: &str, level: Option<usize>| {
+                Ok(Expr::fixed_bytes(&decode_hex(hex_str)?)
+                    .span()
+                    .render(level.unwrap_or_default()))
+            },
+        );
+        env.add_filter("cairo_string", move |value: &str| {
+            Ok(Expr::string(value, string_format)
+                .map_err(filter_error)?
+                .render(0))
+        });
+        env.add_filter("cairo_tcb_status", move |status: &str| {
+            Ok(
+                Expr::tcb_status(status, tcb_status_format, string_format, &tcb_status_type)
+                    .map_err(filter_error)?
+                    .render(0),
+            )
+        });
+        env.add_filter(
+            "cairo_date",
+            move |datetime_str: &str, level: Option<usize>| {
+                let date = parse_datetime(datetime_str).map_err(filter_error)?;
+                Ok(Expr::date(&date, date_format)
+                    .map_err(filter_error)?
+                    .render(level.unwrap_or_default()))
+            },
+        );
+        env.add_filter("cairo_some", |value: String, level: Option<usize>| {
+            Expr::some(Expr::raw(value)).render(level.unwrap_or_default())
+        });
+        env.add_filter("cairo_array", |items: Vec<String>, level: Option<usize>| {
+            Expr::array(items.into_iter().map(Expr::raw).collect())
+                .render(level.unwrap_or_default())
+        });
+        env.add_filter("tcb_date_var", |datetime_str: &str| {
+            Ok(tcb_date_var_name(
+                &parse_datetime(datetime_str).map_err(filter_error)?,
+            ))
+        });
+        env.add_filter("tcb_dates", move |value: Value| -> Result<_, Error> {
+            let mut dates = vec![];
+            collect_tcb_dates(&value, &mut dates)?;
+
+            Ok(tcb_date_lets(dates.iter().map(String::as_str), date_format)
+                .map_err(filter_error)?
+                .into_iter()
+                .map(|binding| vec![binding.name, binding.comment.unwrap_or_default()])
+                .collect::<Vec<_>>())
+        });
+
+        let name = kind.file_name();
+        match source {
+            Some(source) => env.add_template_owned(name, source)?,
+            None => env.add_template(name, kind.default_source())?,
+        }
+
+        let mut context = serde_json::to_value(model)?;
+        let serde_json::Value::Object(fields) = serde_json::to_value(generated)? else {
+            unreachable!("generated code serializes to an object");
+        };
+        let Some(object) = context.as_object_mut() else {
+            eyre::bail!("Template model must serialize to an object");
+        };
+        object.extend(fields);
+
+        Ok(env.get_template(name)?.render(context)?)
+    }
+}
+
+/// Writes the default templates into `dir`.
+pub fn write_default_templates(dir: &Path) -> Result<()> {
+    std::fs::create_dir_all(dir)?;
+    for kind in TemplateKind::ALL {
+        std::fs::write(dir.join(kind.file_name()), kind.default_source())?;
+    }
+
+    Ok(())
+}
+
+fn read_template(dir: &Path, kind: TemplateKind) -> Result<Option<String>> {
+    if !dir.is_dir() {
+        eyre::bail!("Template directory not found: {}", dir.display());
+    }
+
+    let path = dir.join(kind.file_name());
+    if path.exists() {
+        Ok(Some(std::fs::read_to_string(path)?))
+    } else {
+        Ok(None)
+    }
+}
+
+fn decode_hex(hex_str: &str) -> Result<Vec<u8>, Error> {
+    hex::decode(hex_str).map_err(|err| {
+        Error::new(
+            ErrorKind::InvalidOperation,
+            format!("invalid hex string \"{hex_str}\": {err}"),
+        )
+    })
+}
+
+/// Collects the `tcbDate` of every TCB level found in `value`.
+fn collect_tcb_dates(value: &Value, dates: &mut Vec<String>) -> Result<(), Error> {
+    match value.kind() {
+        ValueKind::Map => {
+            if let Some(date) = value.get_attr("tcbDate")?.as_str() {
+                dates.push(date.to_owned());
+            }
+            for key in value.try_iter()? {
+                collect_tcb_dates(&value.get_item(&key)?, dates)?;
+            }
+        }
+        ValueKind::Seq => {
+            for item in value.try_iter()? {
+                collect_tcb_dates(&item, dates)?;
+            }
+        }
+        _ => {}
+    }
+
+    Ok(())
+}
+
+fn filter_error(err: eyre::Report) -> Error {
+    Error::new(ErrorKind::InvalidOperation, err.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn generated() -> Generated {
+        Generated {
+            date_imports: "use time::Month;\n".to_owned(),
+            type_imports: "use crate::types::Tcb;\n".to_owned(),
+            date_let_type: None,
+            type_defs: "pub enum TcbStatus {}\n\n".to_owned(),
+            data: "pub fn data() -> Tcb {\n    Tcb { isvsvn: 8 }\n}\n".to_owned(),
+            signed_body: String::new(),
+            types_module: "crate::types".to_owned(),
+            types: BTreeMap::from([("Tcb".to_owned(), "MyTcb".to_owned())]),
+        }
+    }
+
+    fn render(
+        template_dir: Option<&Path>,
+        date_format: DateFormat,
+        string_format: StringFormat,
+    ) -> Result<String> {
+        let args = TemplateArgs {
+            template_dir: template_dir.map(Path::to_path_buf),
+        };
+        let model = serde_json::json!({
+            "issueDate": "2024-03-18T10:35:22Z",
+            "mrsigner": "0aff",
+            "id": "Q'E",
+            "long": "00".repeat(20),
+            "tcbLevels": [
+                {"tcbDate": "2023-08-09T00:00:00Z", "tcbStatus": "OutOfDate"},
+                {"tcbDate": "2024-03-13T00:00:00Z", "tcbStatus": "UpToDate", "advisoryIDs": ["A"]},
+            ],
+        });
+
+        args.render(
+            TemplateKind::Qeidentity,
+            &model,
+            generated(),
+            date_format,
+            string_format,
+            TcbStatusFormat::Enum,
+        )
+    }
+
+    #[test]
+    fn renders_generated_code_by_default() {
+        assert_eq!(
+            render(None, DateFormat::OffsetDateTime, StringFormat::ByteArray).unwrap(),
+            "use time::Month;\nuse crate::types::Tcb;\n\
+             \npub enum TcbStatus {}\n\npub fn data() -> Tcb {\n    Tcb { isvsvn: 8 }\n}\n"
+        );
+    }
+
+    #[test]
+    fn exposes_model_and_filters_to_overrides() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("qeidentity.cairo.j2"),
+            "{{ types_module }}::{{ types.Tcb }}\n\
+             {{ mrsigner | cairo_bytes }} {{ mrsigner | cairo_fixed_bytes }}\n\
+             {{ id | cairo_string }} {{ issueDate | tcb_date_var }}\n\
+             {{ issueDate | cairo_date }}\n",
+        )
+        .unwrap();
+
+        assert_eq!(
+            render(
+                Some(dir.path()),
+                DateFormat::Unix,
+                StringFormat::ShortString
+            )
+            .unwrap(),
+            "crate::types::MyTcb\n\
+             array![0x0a, 0xff].span() [0x0a, 0xff].span()\n\
+             'Q\\'E' tcb_date_2024_03_18_10_35_22\n\
+             1710758122\n"
+        );
+    }
+
+    #[test]
+    fn lays_out_expressions_at_the_given_level() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("qeidentity.cairo.j2"),
+            "{{ long | cairo_bytes(1) }}\n\
+             {% for level in tcbLevels %}{{ level.tcbStatus | cairo_tcb_status }} {% endfor %}\n\
+             {{ tcbLevels[1].advisoryIDs | map(\"cairo_string\") | cairo_array | cairo_some }}\n\
+             {% for name, date in tcbLevels | tcb_dates %}{{ name }}={{ date }} {% endfor %}\n",
+        )
+        .unwrap();
+
+        let long = Expr::bytes(&[0; 20]).span().render(1);
+        assert_eq!(
+            render(
+                Some(dir.path()),
+                DateFormat::OffsetDateTime,
+                StringFormat::ShortString
+            )
+            .unwrap(),
+            format!(
+                "{long}\n\
+                 TcbStatus::OutOfDate TcbStatus::UpToDate \n\
+                 Option::Some(array!['A'])\n\
+                 tcb_date_2024_03_13=2024-03-13T00:00:00Z tcb_date_2023_08_09=2023-08-09T00:00:00Z \n"
+            )
+        );
+        assert!(long.starts_with("array![\n        0x00,"), "{long}");
+    }
+
+    #[test]
+    fn falls_back_to_default_templates() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("tcbinfo.cairo.j2"), "").unwrap();
+        assert_eq!(
+            render(
+                Some(dir.path()),
+                DateFormat::OffsetDateTime,
+                StringFormat::ByteArray
+            )
+            .unwrap(),
+            render(None, DateFormat::OffsetDateTime, StringFormat::ByteArray).unwrap()
+        );
+
+        let missing = dir.path().join("missing");
+        assert_eq!(
+            render(
+                Some(&missing),
+                DateFormat::OffsetDateTime,
+                StringFormat::ByteArray
+            )
+            .unwrap_err()
+            .to_string(),
+            format!("Template directory not found: {}", missing.display())
+        );
+    }
+
+    #[test]
+    fn reports_filter_errors() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(
+            dir.path().join("qeidentity.cairo.j2"),
+            "{{ issueDate | cairo_bytes }}",
+        )
+        .unwrap();
+
+        let error = render(
+            Some(dir.path()),
+            DateFormat::OffsetDateTime,
+            StringFormat::ByteArray,
+        )
+        .unwrap_err();
+        assert!(
+            format!("{error:#}")
+                .contains("invalid hex string \"2024-03-18T10:35:22Z\": Invalid character"),
+            "{error:#}"
+        );
+    }
+
+    #[test]
+    fn writes_default_templates() {
+        let dir = tempfile::tempdir().unwrap();
+        let output = dir.path().join("templates");
+        write_default_templates(&output).unwrap();
+
+        for kind in TemplateKind::ALL {
+            let source = std::fs::read_to_string(output.join(kind.file_name())).unwrap();
+            assert_eq!(source, kind.default_source());
+        }
+    }
+}
@@ -0,0 +1,273 @@
+//! Fixtures shared by unit tests.
+
+use std::collections::BTreeSet;
+
+use clap::Parser;
+use eyre::Result;
+use rcgen::{
+    BasicConstraints, CertificateParams, CertificateRevocationListParams, CertifiedIssuer, DnType,
+    IsCa, KeyIdMethod, KeyPair, SerialNumber, SigningKey, date_time_ymd,
+};
+use ring::{
+    rand::SystemRandom,
+    signature::{ECDSA_P256_SHA256_FIXED_SIGNING, EcdsaKeyPair},
+};
+
+use crate::emitter::{Expr, TypeDef};
+
+/// Root CA, intermediate CA and leaf certificate, mirroring the Intel signing chains.
+pub struct Pki {
+    pub root: CertifiedIssuer<'static, KeyPair>,
+    pub intermediate: CertifiedIssuer<'static, KeyPair>,
+    pub leaf: CertifiedIssuer<'static, KeyPair>,
+    /// Empty DER-encoded CRL of the intermediate CA.
+    pub pck_crl: Vec<u8>,
+    /// Empty DER-encoded CRL of the root CA.
+    pub root_ca_crl: Vec<u8>,
+}
+
+impl Pki {
+    pub fn new(name: &str) -> Self {
+        let root = CertifiedIssuer::self_signed(
+            params(&format!("{name} Root CA"), true),
+            KeyPair::generate().unwrap(),
+        )
+        .unwrap();
+        let intermediate = CertifiedIssuer::signed_by(
+            params(&format!("{name} Intermediate CA"), true),
+            KeyPair::generate().unwrap(),
+            &root,
+        )
+        .unwrap();
+        let leaf = CertifiedIssuer::signed_by(
+            params(&format!("{name} Signing"), false),
+            KeyPair::generate().unwrap(),
+            &intermediate,
+        )
+        .unwrap();
+
+        Self {
+            pck_crl: crl(&intermediate),
+            root_ca_crl: crl(&root),
+            root,
+            intermediate,
+            leaf,
+        }
+    }
+
+    pub fn root_der(&self) -> Vec<u8> {
+        self.root.der().to_vec()
+    }
+
+    /// DER-encoded certificates from leaf to root.
+    pub fn chain_der(&self) -> [Vec<u8>; 3] {
+        [&self.leaf, &self.intermediate, &self.root].map(|cert| cert.der().to_vec())
+    }
+
+    /// PEM chain from leaf to root.
+    pub fn chain_pem(&self) -> String {
+        [&self.leaf, &self.intermediate, &self.root]
+            .map(|cert| cert.pem())
+            .concat()
+    }
+
+    /// PEM issuer chain of a PCK CRL signed by the intermediate CA.
+    pub fn crl_chain_pem(&self) -> String {
+        [&self.intermediate, &self.root]
+            .map(|cert| cert.pem())
+            .concat()
+    }
+
+    /// Hex-encoded `r || s` signature of `bytes` by the leaf, as found in collateral.
+    pub fn sign(&self, bytes: &[u8]) -> String {
+        let key = EcdsaKeyPair::from_pkcs8(
+            &ECDSA_P256_SHA256_FIXED_SIGNING,
+            &self.leaf.key().serialize_der(),
+            &SystemRandom::new(),
+        )
+        .unwrap();
+
+        hex::encode(key.sign(&SystemRandom::new(), bytes).unwrap())
+    }
+}
+
+/// Parameters of a certificate named `common_name`, with `basicConstraints CA:true` if `is_ca`.
+pub fn params(common_name: &str, is_ca: bool) -> CertificateParams {
+    let mut params = CertificateParams::default();
+    params
+        .distinguished_name
+        .push(DnType::CommonName, common_name);
+    if is_ca {
+        params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+    }
+
+    params
+}
+
+/// Empty CRL issued by `issuer`.
+fn crl(issuer: &CertifiedIssuer<'static, impl SigningKey>) -> Vec<u8> {
+    CertificateRevocationListParams {
+        this_update: date_time_ymd(2024, 3, 18),
+        next_update: date_time_ymd(2024, 4, 17),
+        crl_number: SerialNumber::from_slice(&[1]),
+        issuing_distribution_point: None,
+        revoked_certs: vec![],
+        key_identifier_method: KeyIdMethod::Sha256,
+    }
+    .signed_by(issuer)
+    .unwrap()
+    .der()
+    .to_vec()
+}
+
+/// SGX TCB Info v3 with a single TCB level per given `(pcesvn, tcbDate, tcbStatus)`, signed by
+/// `pki` unless it is `None`.
+pub fn tcb_info_json(pki: Option<&Pki>, levels: &[(u16, &str, &str)]) -> String {
+    let levels = levels
+        .iter()
+        .map(|(pcesvn, tcb_date, tcb_status)| {
+            let components = vec![r#"{"svn":2}"#; 16].join(",");
+            format!(
+                r#"{{"tcb":{{"sgxtcbcomponents":[{components}],"pcesvn":{pcesvn}}},"tcbDate":"{tcb_date}","tcbStatus":"{tcb_status}"}}"#
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(",");
+    let body = format!(
+        r#"{{"id":"SGX","version":3,"issueDate":"2024-03-18T10:35:22Z","nextUpdate":"2024-04-17T10:35:22Z","fmspc":"00906ED50000","pceId":"0000","tcbType":0,"tcbEvaluationDataNumber":16,"tcbLevels":[{levels}]}}"#
+    );
+
+    signed_json("tcbInfo", &body, pki)
+}
+
+/// SGX QE identity v2 with a single TCB level per given `(isvsvn, tcbDate, tcbStatus)`, signed by
+/// `pki` unless it is `None`.
+pub fn qe_identity_json(pki: Option<&Pki>, levels: &[(u16, &str, &str)]) -> String {
+    let levels = levels
+        .iter()
+        .map(|(isvsvn, tcb_date, tcb_status)| {
+            format!(
+                r#"{{"tcb":{{"isvsvn":{isvsvn}}},"tcbDate":"{tcb_date}","tcbStatus":"{tcb_status}"}}"#
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(",");
+    let body = format!(
+        r#"{{"id":"QE","version":2,"issueDate":"2024-03-18T10:35:22Z","nextUpdate":"2024-04-17T10:35:22Z","tcbEvaluationDataNumber":16,"miscselect":"00000000","miscselectMask":"FFFFFFFF","attributes":"11000000000000000000000000000000","attributesMask":"FBFFFFFFFFFFFFFF0000000000000000","mrsigner":"DC9E2A7C6F948F17474E34A7FC43ED030F7C1563F1BABDDF6340C82E0E54A8C5","isvprodid":1,"tcbLevels":[{levels}]}}"#
+    );
+
+    signed_json("enclaveIdentity", &body, pki)
+}
+
+fn signed_json(key: &str, body: &str, pki: Option<&Pki>) -> String {
+    let signature = match pki {
+        Some(pki) => pki.sign(body.as_bytes()),
+        None => "00".repeat(64),
+    };
+
+    format!(r#"{{"{key}":{body},"signature":"{signature}"}}"#)
+}
+
+/// Runs the preprocess command `C` with `args`, returning the generated output.
+pub fn run_command<C, F>(args: &[&str], run: F) -> Result<String>
+where
+    C: Parser,
+    F: FnOnce(C) -> Result<()>,
+{
+    let dir = tempfile::tempdir()?;
+    let output_path = dir.path().join("output");
+
+    let mut command = vec!["preprocess", "--output", output_path.to_str().unwrap()];
+    command.extend(args);
+    run(C::try_parse_from(command)?)?;
+
+    Ok(std::fs::read_to_string(output_path)?)
+}
+
+/// Runs the preprocess command `C` on `input` with `args`, returning the generated output.
+pub fn run_preprocess<C, F>(input: &[u8], args: &[&str], run: F) -> Result<String>
+where
+    C: Parser,
+    F: FnOnce(C) -> Result<()>,
+{
+    let dir = tempfile::tempdir()?;
+    let input_path = dir.path().join("input");
+    std::fs::write(&input_path, input)?;
+
+    run_command(
+        &[&["--input", input_path.to_str().unwrap()], args].concat(),
+        run,
+    )
+}
+
+/// URL-encodes `value` the way PCS encodes issuer chain headers.
+pub fn url_encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                (byte as char).to_string()
+            }
+            byte => format!("%{byte:02X}"),
+        })
+        .collect()
+}
+
+/// Asserts that every struct literal and enum variant in `expr` matches its definition in
+/// `type_defs`, field names and order included. Returns the names of the types used.
+pub fn assert_matches_type_defs(expr: &Expr, type_defs: &[TypeDef]) -> BTreeSet<String> {
+    let mut used = BTreeSet::new();
+    check_type_defs(expr, type_defs, &mut used);
+    used
+}
+
+fn check_type_defs(expr: &Expr, type_defs: &[TypeDef], used: &mut BTreeSet<String>) {
+    let check_variant = |path: &str, used: &mut BTreeSet<String>| {
+        let Some((name, variant)) = path.rsplit_once("::") else {
+            return;
+        };
+        if let Some(TypeDef::Enum { variants, .. }) =
+            type_defs.iter().find(|type_def| type_def.name() == name)
+        {
+            assert!(
+                variants.iter().any(|(known, _)| known == variant),
+                "{name} has no variant {variant}"
+            );
+            used.insert(name.to_owned());
+        }
+    };
+
+    match expr {
+        Expr::Raw(value) => check_variant(value, used),
+        Expr::Call { path, args } => {
+            check_variant(path, used);
+            for arg in args {
+                check_type_defs(arg, type_defs, used);
+            }
+        }
+        Expr::Struct { path, fields } => {
+            let Some(TypeDef::Struct {
+                fields: defined, ..
+            }) = type_defs.iter().find(|type_def| type_def.name() == path)
+            else {
+                panic!("No struct definition for {path}");
+            };
+            assert_eq!(
+                fields.iter().map(|(name, _)| name).collect::<Vec<_>>(),
+                defined.iter().map(|(name, _)| name).collect::<Vec<_>>(),
+                "Fields of {path}"
+            );
+            used.insert(path.clone());
+
+            for (_, value) in fields {
+                check_type_defs(value, type_defs, used);
+            }
+        }
+        Expr::Array { items, .. } => {
+            for item in items {
+                check_type_defs(item, type_defs, used);
+            }
+        }
+        Expr::Span(inner) => check_type_defs(inner, type_defs, used),
+    }
+}
@@ -0,0 +1,416 @@
+use std::path::{Path, PathBuf};
+
+use clap::Args;
+use eyre::Result;
+use ring::signature::{ECDSA_P256_SHA256_FIXED, UnparsedPublicKey};
+use x509_parser::{
+    certificate::X509Certificate, pem::Pem, prelude::FromDer,
+    revocation_list::CertificateRevocationList,
+};
+
+use crate::{
+    bundle::{Bundle, BundleItem},
+    chain::order_chain,
+    signed_body::SignedBody,
+};
+
+/// DER-encoded Intel SGX Root CA, the trust anchor unless `--root-ca` is given.
+const INTEL_SGX_ROOT_CA: &[u8] = include_bytes!("certs/intel_sgx_root_ca.der");
+
+/// Options for verifying collateral signatures against the issuer chain served by PCS.
+#[derive(Debug, Clone, Args)]
+pub struct VerifyArgs {
+    /// Path to the issuer chain of the collateral, i.e. the `TCB-Info-Issuer-Chain` or
+    /// `SGX-Enclave-Identity-Issuer-Chain` header returned by PCS. Accepted both as PEM and as the
+    /// URL-encoded header value.
+    #[clap(long)]
+    issuer_chain: Option<PathBuf>,
+    /// Path to the trusted root CA certificate in PEM or DER format, instead of the built-in Intel
+    /// SGX Root CA. The issuer chain must end with this exact certificate.
+    #[clap(long)]
+    root_ca: Option<PathBuf>,
+    /// Generate Cairo even if the collateral signature is not verified.
+    #[clap(long)]
+    allow_unverified: bool,
+}
+
+impl VerifyArgs {
+    /// Verifies that `signed_body` is signed by the leaf of a valid issuer chain rooted at the
+    /// Intel SGX Root CA, or the one of `--root-ca`. Without `--issuer-chain`, the chain is read from the
+    /// `issuer_chain_item` item of `bundle`. The `root-ca` item of `bundle` is never trusted, and
+    /// must be the trusted root CA if present. Failures are reported as warnings instead with
+    /// `--allow-unverified`.
+    pub fn verify(
+        &self,
+        signed_body: &SignedBody,
+        bundle: Option<&Bundle>,
+        issuer_chain_item: BundleItem,
+    ) -> Result<()> {
+        match (
+            self.try_verify(signed_body, bundle, issuer_chain_item),
+            self.allow_unverified,
+        ) {
+            (Ok(()), _) => Ok(()),
+            (Err(err), true) => {
+                eprintln!("Warning: collateral signature not verified: {err}");
+                Ok(())
+            }
+            (Err(err), false) => Err(err.wrap_err(
+                "Collateral signature verification failed, use --allow-unverified to generate \
+                 Cairo anyway",
+            )),
+        }
+    }
+
+    /// Certificates of the issuer chain passed with `--issuer-chain`, or else found as
+    /// `issuer_chain_item` in `bundle`, if any.
+    pub fn issuer_chain(
+        &self,
+        bundle: Option<&Bundle>,
+        issuer_chain_item: BundleItem,
+    ) -> Result<Option<Vec<Vec<u8>>>> {
+        if let Some(issuer_chain) = &self.issuer_chain {
+            return Ok(Some(decode_issuer_chain(&std::fs::read(issuer_chain)?)?));
+        }
+
+        bundle
+            .map(|bundle| bundle.find(issuer_chain_item))
+            .transpose()?
+            .flatten()
+            .map(|raw| decode_issuer_chain(&raw))
+            .transpose()
+    }
+
+    fn try_verify(
+        &self,
+        signed_body: &SignedBody,
+        bundle: Option<&Bundle>,
+        issuer_chain_item: BundleItem,
+    ) -> Result<()> {
+        let Some(issuer_chain) = self.issuer_chain(bundle, issuer_chain_item)? else {
+            eyre::bail!(
+                "--issuer-chain is required, unless found as the {} bundle item",
+                issuer_chain_item.name()
+            );
+        };
+        let root_ca = match &self.root_ca {
+            Some(root_ca) => read_certificate(root_ca)?,
+            None => INTEL_SGX_ROOT_CA.to_vec(),
+        };
+        if let Some(bundled_root_ca) = bundle
+            .map(|bundle| bundle.find(BundleItem::RootCa))
+            .transpose()?
+            .flatten()
+            && parse_certificate(&bundled_root_ca)? != root_ca
+        {
+            eyre::bail!(
+                "The {} bundle item is not the trusted root CA",
+                BundleItem::RootCa.name()
+            );
+        }
+
+        verify_signed_body(issuer_chain, &root_ca, signed_body)
+    }
+}
+
+/// Verifies that `signed_body` is signed by the leaf of `issuer_chain`, which must be a valid
+/// chain ending with `root_ca`.
+pub fn verify_signed_body(
+    issuer_chain: Vec<Vec<u8>>,
+    root_ca: &[u8],
+    signed_body: &SignedBody,
+) -> Result<()> {
+    let chain = order_chain(issuer_chain)?;
+    if chain.last().map(Vec::as_slice) != Some(root_ca) {
+        eyre::bail!("Issuer chain does not end with the trusted root CA");
+    }
+
+    let (_, signing_cert) = X509Certificate::from_der(&chain[0])
+        .map_err(|err| eyre::eyre!("Failed to parse signing certificate: {err}"))?;
+
+    let signature = signed_body.signature()?;
+    UnparsedPublicKey::new(
+        &ECDSA_P256_SHA256_FIXED,
+        &signing_cert.public_key().subject_public_key.data,
+    )
+    .verify(&signed_body.bytes, &signature)
+    .map_err(|_| {
+        eyre::eyre!(
+            "Invalid collateral signature for signing certificate {}",
+            signing_cert.subject()
+        )
+    })
+}
+
+/// Verifies that the DER-encoded `crl` is signed by the DER-encoded `issuer` certificate.
+pub fn verify_crl(crl: &[u8], issuer: &[u8]) -> Result<()> {
+    let (_, crl) = CertificateRevocationList::from_der(crl)
+        .map_err(|err| eyre::eyre!("Failed to parse CRL: {err}"))?;
+    let (_, issuer) = X509Certificate::from_der(issuer)
+        .map_err(|err| eyre::eyre!("Failed to parse CRL issuer certificate: {err}"))?;
+    if crl.issuer() != issuer.subject() {
+        eyre::bail!(
+            "CRL is issued by {}, not by {}",
+            crl.issuer(),
+            issuer.subject()
+        );
+    }
+
+    crl.verify_signature(issuer.public_key())
+        .map_err(|err| eyre::eyre!("Invalid CRL signature: {err}"))
+}
+
+/// Decodes the certificates of an issuer chain, URL-decoding it first if needed.
+fn decode_issuer_chain(raw: &[u8]) -> Result<Vec<Vec<u8>>> {
+    let raw = std::str::from_utf8(raw)?;
+
+    // Spaces are always escaped in header values
+    if raw.contains("-----BEGIN ") {
+        parse_issuer_chain(raw.as_bytes())
+    } else {
+        parse_issuer_chain(&url_decode(raw.trim())?)
+    }
+}
+
+/// Parses the DER-encoded certificates of a PEM issuer chain.
+pub fn parse_issuer_chain(pem: &[u8]) -> Result<Vec<Vec<u8>>> {
+    let mut certs = vec![];
+    for pem in Pem::iter_from_buffer(pem) {
+        let pem = pem.map_err(|err| eyre::eyre!("Failed to parse issuer chain: {err}"))?;
+        if pem.label != "CERTIFICATE" {
+            eyre::bail!("Unexpected PEM label in issuer chain: {}", pem.label);
+        }
+
+        certs.push(pem.contents);
+    }
+
+    if certs.is_empty() {
+        eyre::bail!("Empty issuer chain");
+    }
+
+    Ok(certs)
+}
+
+/// Reads a single certificate in PEM or DER format.
+pub fn read_certificate(path: &Path) -> Result<Vec<u8>> {
+    parse_certificate(&std::fs::read(path)?)
+}
+
+/// Parses a single certificate in PEM or DER format.
+fn parse_certificate(raw: &[u8]) -> Result<Vec<u8>> {
+    if !raw.starts_with(b"-----BEGIN") {
+        return Ok(raw.to_vec());
+    }
+
+    match Pem::iter_from_buffer(raw).next() {
+        Some(Ok(pem)) if pem.label == "CERTIFICATE" => Ok(pem.contents),
+        Some(Ok(pem)) => eyre::bail!("Unexpected PEM label: {}", pem.label),
+        Some(Err(err)) => eyre::bail!("Failed to parse PEM file: {err}"),
+        None => eyre::bail!("Empty PEM file"),
+    }
+}
+
+/// Decodes `%XX` escapes of a URL-encoded header value.
+pub fn url_decode(value: &str) -> Result<Vec<u8>> {
+    let mut decoded = vec![];
+    let mut bytes = value.bytes();
+    while let Some(byte) = bytes.next() {
+        if byte != b'%' {
+            decoded.push(byte);
+            continue;
+        }
+
+        let escape = [
+            bytes.next().unwrap_or_default(),
+            bytes.next().unwrap_or_default(),
+        ];
+        let Ok(&[byte]) = hex::decode(escape).as_deref() else {
+            eyre::bail!("Invalid URL escape: %{}", String::from_utf8_lossy(&escape));
+        };
+        decoded.push(byte);
+    }
+
+    Ok(decoded)
+}
+
+#[cfg(test)]
+mod tests {
+    use tempfile::TempDir;
+
+    use super::*;
+    use crate::test_utils::{Pki, url_encode};
+
+    const BODY: &str = r#"{"id":"QE"}"#;
+
+    /// Signed body of collateral whose signed object is `body`, signed by the leaf of `pki`.
+    fn signed_body(pki: &Pki, body: &str) -> SignedBody {
+        let json = format!(r#"{{"enclaveIdentity":{body},"signature":"00"}}"#);
+        SignedBody::extract(&json, "enclaveIdentity", &pki.sign(body.as_bytes())).unwrap()
+    }
+
+    /// Arguments reading the issuer chain and root CA from `dir`.
+    fn args(dir: &TempDir, issuer_chain: &[u8], root_ca: &[u8]) -> VerifyArgs {
+        let (chain_path, root_path) = (dir.path().join("chain.pem"), dir.path().join("root.der"));
+        std::fs::write(&chain_path, issuer_chain).unwrap();
+        std::fs::write(&root_path, root_ca).unwrap();
+
+        VerifyArgs {
+            issuer_chain: Some(chain_path),
+            root_ca: Some(root_path),
+            allow_unverified: false,
+        }
+    }
+
+    #[test]
+    fn url_decode_unescapes_header_values() {
+        assert_eq!(url_decode("a%2Bb%0a-c.d").unwrap(), b"a+b\n-c.d");
+        assert_eq!(url_decode("").unwrap(), b"");
+        assert!(url_decode("a%zz").is_err());
+        assert!(url_decode("a%2").is_err());
+    }
+
+    #[test]
+    fn accepts_valid_signature_with_pem_or_url_encoded_chain() {
+        let pki = Pki::new("Test");
+        let dir = tempfile::tempdir().unwrap();
+        let signed_body = signed_body(&pki, BODY);
+
+        let chain = pki.chain_pem();
+        args(&dir, chain.as_bytes(), &pki.root_der())
+            .verify(&signed_body, None, BundleItem::QeidentityIssuerChain)
+            .unwrap();
+        args(
+            &dir,
+            url_encode(&chain).as_bytes(),
+            pki.root.pem().as_bytes(),
+        )
+        .verify(&signed_body, None, BundleItem::QeidentityIssuerChain)
+        .unwrap();
+    }
+
+    #[test]
+    fn rejects_tampered_body() {
+        let pki = Pki::new("Test");
+        let dir = tempfile::tempdir().unwrap();
+        let mut signed_body = signed_body(&pki, BODY);
+        signed_body.bytes = br#"{"id":"TD_QE"}"#.to_vec();
+
+        let args = args(&dir, pki.chain_pem().as_bytes(), &pki.root_der());
+        let error = args
+            .try_verify(&signed_body, None, BundleItem::QeidentityIssuerChain)
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Invalid collateral signature for signing certificate CN=Test Signing"
+        );
+    }
+
+    #[test]
+    fn rejects_chain_of_another_root() {
+        let pki = Pki::new("Test");
+        let dir = tempfile::tempdir().unwrap();
+
+        let args = args(
+            &dir,
+            pki.chain_pem().as_bytes(),
+            &Pki::new("Other").root_der(),
+        );
+        let error = args
+            .try_verify(
+                &signed_body(&pki, BODY),
+                None,
+                BundleItem::QeidentityIssuerChain,
+            )
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Issuer chain does not end with the trusted root CA"
+        );
+    }
+
+    #[test]
+    fn trusts_intel_root_by_default() {
+        let root_ca = order_chain(vec![INTEL_SGX_ROOT_CA.to_vec()]).unwrap();
+        let (_, root_ca) = X509Certificate::from_der(&root_ca[0]).unwrap();
+        assert_eq!(
+            root_ca.subject().to_string(),
+            "CN=Intel SGX Root CA, O=Intel Corporation, L=Santa Clara, ST=CA, C=US"
+        );
+
+        let pki = Pki::new("Test");
+        let dir = tempfile::tempdir().unwrap();
+        let mut args = args(&dir, pki.chain_pem().as_bytes(), &pki.root_der());
+        args.root_ca = None;
+        let error = args
+            .try_verify(
+                &signed_body(&pki, BODY),
+                None,
+                BundleItem::QeidentityIssuerChain,
+            )
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Issuer chain does not end with the trusted root CA"
+        );
+    }
+
+    #[test]
+    fn reports_invalid_bundled_issuer_chain() {
+        let args = VerifyArgs {
+            issuer_chain: None,
+            root_ca: None,
+            allow_unverified: false,
+        };
+        let bundle = Bundle {
+            items: [(BundleItem::QeidentityIssuerChain, "zz".to_owned())].into(),
+            ..Default::default()
+        };
+
+        let error = args
+            .issuer_chain(Some(&bundle), BundleItem::QeidentityIssuerChain)
+            .unwrap_err();
+        assert_eq!(error.to_string(), "Invalid character 'z' at position 0");
+    }
+
+    #[test]
+    fn warns_only_with_allow_unverified() {
+        let pki = Pki::new("Test");
+        let mut args = VerifyArgs {
+            issuer_chain: None,
+            root_ca: None,
+            allow_unverified: false,
+        };
+
+        let error = args
+            .verify(
+                &signed_body(&pki, BODY),
+                None,
+                BundleItem::QeidentityIssuerChain,
+            )
+            .unwrap_err();
+        assert_eq!(
+            format!("{error:#}"),
+            "Collateral signature verification failed, use --allow-unverified to generate Cairo \
+             anyway: --issuer-chain is required, unless found as the qeidentity-issuer-chain \
+             bundle item"
+        );
+
+        args.allow_unverified = true;
+        args.verify(
+            &signed_body(&pki, BODY),
+            None,
+            BundleItem::QeidentityIssuerChain,
+        )
+        .unwrap();
+
+        let json = format!(r#"{{"enclaveIdentity":{BODY},"signature":"00"}}"#);
+        let malformed = SignedBody::extract(&json, "enclaveIdentity", "00").unwrap();
+        args.verify(&malformed, None, BundleItem::QeidentityIssuerChain)
+            .unwrap();
+        args.allow_unverified = false;
+        assert!(
+            args.verify(&malformed, None, BundleItem::QeidentityIssuerChain)
+                .is_err()
+        );
+    }
+}